@@ -0,0 +1,66 @@
+//! rough throughput benchmark for the parser, run with `cargo bench --bench parse`
+
+use dynamic_json::{parse_bytes, try_parse, JsonReader, JsonType};
+use std::time::{Duration, Instant};
+
+/// build a document shaped like a log export: an array of small flat records
+fn log_export(records: usize) -> String {
+    let mut s = String::from("[\n");
+    for i in 0..records {
+        if i > 0 {
+            s.push_str(",\n");
+        }
+        s.push_str(&format!(
+            r#"  {{ "id": {}, "timestamp": "2022-03-{:02}T12:{:02}:{:02}.{:03}Z", "level": "{}", "latency": {}.{}, "ok": {}, "tags": ["api", "v2", null], "message": "request \"GET /items/{}\" finished in café" }}"#,
+            i,
+            i % 28 + 1,
+            i % 60,
+            (i * 7) % 60,
+            i % 1000,
+            if i % 10 == 0 { "warn" } else { "info" },
+            i % 500,
+            i % 97,
+            i % 3 != 0,
+            i
+        ));
+    }
+    s.push_str("\n]\n");
+    s
+}
+
+fn bench<F: FnMut()>(name: &str, size: usize, mut f: F) {
+    let mut runs = 0;
+    let mut total = Duration::ZERO;
+    while runs < 3 || total < Duration::from_secs(2) {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+        runs += 1;
+    }
+    let per_run = total / runs;
+    let mb = size as f64 / 1024.0 / 1024.0;
+    println!(
+        "{:<12} {:>8.1} ms/run {:>8.1} MB/s",
+        name,
+        per_run.as_secs_f64() * 1000.0,
+        mb / per_run.as_secs_f64()
+    );
+}
+
+fn main() {
+    let input = log_export(100_000);
+    println!("input: {:.1} MB", input.len() as f64 / 1024.0 / 1024.0);
+    bench("try_parse", input.len(), || {
+        try_parse(&input).unwrap();
+    });
+    bench("parse_bytes", input.len(), || {
+        parse_bytes(input.as_bytes()).unwrap();
+    });
+    bench("from_reader", input.len(), || {
+        JsonType::from_reader(input.as_bytes()).unwrap();
+    });
+    bench("events", input.len(), || {
+        let mut reader = JsonReader::new(&input);
+        while reader.next_event().unwrap().is_some() {}
+    });
+}
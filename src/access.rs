@@ -0,0 +1,201 @@
+use crate::{JsonObject, JsonType};
+
+/// 2^63 and 2^64, the first values out of range of i64 and u64
+const I64_END: f64 = 9_223_372_036_854_775_808.0;
+const U64_END: f64 = 18_446_744_073_709_551_616.0;
+
+impl JsonType {
+    /// the json name of the type: `null`, `boolean`, `number`, `string`,
+    /// `array` or `object`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JsonType::Null => "null",
+            JsonType::Bool(_) => "boolean",
+            JsonType::Number(_) => "number",
+            JsonType::String(_) => "string",
+            JsonType::Array(_) => "array",
+            JsonType::Object(_) => "object",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonType::Null)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, JsonType::Bool(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, JsonType::Number(_))
+    }
+
+    /// a number without fraction that fits in an i64
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// a number without fraction that fits in a u64
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, JsonType::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, JsonType::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, JsonType::Object(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonType::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            JsonType::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonType::Number(num) => Some(*num),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            JsonType::Number(num) => Some(num),
+            _ => None,
+        }
+    }
+
+    /// the number if it has no fraction and fits in an i64
+    ///
+    /// ```
+    /// use dynamic_json::JsonType;
+    /// assert_eq!(JsonType::from(-3).as_i64(), Some(-3));
+    /// assert_eq!(JsonType::from(1.5).as_i64(), None);
+    /// assert_eq!(JsonType::from(1e300).as_i64(), None);
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonType::Number(num) if num.fract() == 0.0 && (-I64_END..I64_END).contains(num) => {
+                Some(*num as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// the number if it has no fraction and fits in a u64
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonType::Number(num) if num.fract() == 0.0 && (0.0..U64_END).contains(num) => {
+                Some(*num as u64)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonType::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            JsonType::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonType>> {
+        match self {
+            JsonType::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonType>> {
+        match self {
+            JsonType::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonObject> {
+        match self {
+            JsonType::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonObject> {
+        match self {
+            JsonType::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// move the value out, leaving `null` in its place
+    pub fn take(&mut self) -> JsonType {
+        std::mem::replace(self, JsonType::Null)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse, JsonType};
+
+    #[test]
+    fn typed_access() {
+        let mut json =
+            parse(r#"{ "n": -2, "f": 0.5, "s": "x", "b": true, "a": [null], "big": 1e19 }"#);
+        assert_eq!(json["n"].as_i64(), Some(-2));
+        assert_eq!(json["n"].as_u64(), None);
+        assert_eq!(json["f"].as_f64(), Some(0.5));
+        assert!(!json["f"].is_i64());
+        assert_eq!(json["big"].as_i64(), None);
+        assert_eq!(json["big"].as_u64(), Some(10_000_000_000_000_000_000));
+        assert_eq!(json["s"].as_str(), Some("x"));
+        assert_eq!(json["b"].as_bool(), Some(true));
+        assert_eq!(json["s"].as_bool(), None);
+        assert!(json["a"].as_array().unwrap()[0].is_null());
+
+        let types: Vec<_> = json
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|v| v.type_name())
+            .collect();
+        assert_eq!(
+            types,
+            ["number", "number", "string", "boolean", "array", "number"]
+        );
+
+        let obj = json.as_object_mut().unwrap();
+        obj.get_mut("s")
+            .and_then(|s| s.as_str_mut())
+            .unwrap()
+            .push('y');
+        *obj.get_mut("f").and_then(|f| f.as_f64_mut()).unwrap() += 1.0;
+        let arr = obj.get_mut("a").unwrap().take();
+        assert_eq!(arr, JsonType::Array(vec![JsonType::Null]));
+        assert_eq!(
+            json.serialize(),
+            r#"{"n":-2,"f":1.5,"s":"xy","b":true,"a":null,"big":10000000000000000000}"#
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{InsertJsonObject, JsonObject, JsonType};
+use std::fmt;
+
+use crate::{InsertJsonObject, JsonObject, JsonType, Scalar};
 
 impl From<&str> for JsonType {
     fn from(s: &str) -> Self {
@@ -20,7 +22,7 @@ impl From<i32> for JsonType {
 
 impl From<f64> for JsonType {
     fn from(v: f64) -> Self {
-        JsonType::Number(f64::from(v))
+        JsonType::Number(v)
     }
 }
 
@@ -30,6 +32,17 @@ impl From<bool> for JsonType {
     }
 }
 
+impl From<Scalar<'_>> for JsonType {
+    fn from(v: Scalar<'_>) -> Self {
+        match v {
+            Scalar::Null => JsonType::Null,
+            Scalar::Bool(b) => JsonType::Bool(b),
+            Scalar::Number(num) => JsonType::Number(num),
+            Scalar::String(s) => JsonType::String(s.to_string()),
+        }
+    }
+}
+
 impl From<JsonObject> for JsonType {
     fn from(v: JsonObject) -> Self {
         JsonType::Object(v)
@@ -54,7 +67,7 @@ impl From<&i32> for JsonType {
 
 impl From<&f64> for JsonType {
     fn from(v: &f64) -> Self {
-        JsonType::Number(f64::from(*v))
+        JsonType::Number(*v)
     }
 }
 
@@ -93,9 +106,7 @@ impl<V: Into<JsonType>> FromIterator<(String, V)> for JsonType {
 impl<V: Into<JsonType>> FromIterator<(String, V)> for JsonObject {
     fn from_iter<T: IntoIterator<Item = (String, V)>>(iter: T) -> Self {
         let mut obj = JsonObject::new();
-        for item in iter {
-            obj.insert(&item.0, item.1.into());
-        }
+        obj.extend(iter);
         obj
     }
 }
@@ -104,7 +115,7 @@ impl<'a, V: Into<JsonType>> FromIterator<(&'a str, V)> for JsonType {
     fn from_iter<T: IntoIterator<Item = (&'a str, V)>>(iter: T) -> Self {
         let mut obj = JsonObject::new();
         for item in iter {
-            obj.insert(&item.0, item.1.into());
+            obj.insert(item.0, item.1.into());
         }
         JsonType::Object(obj)
     }
@@ -114,14 +125,30 @@ impl<'a, V: Into<JsonType>> FromIterator<(&'a str, V)> for JsonObject {
     fn from_iter<T: IntoIterator<Item = (&'a str, V)>>(iter: T) -> Self {
         let mut obj = JsonObject::new();
         for item in iter {
-            obj.insert(&item.0, item.1.into());
+            obj.insert(item.0, item.1.into());
         }
         obj
     }
 }
 
-impl ToString for JsonType {
-    fn to_string(&self) -> String {
-        self.serialize()
+impl<V: Into<JsonType>> Extend<(String, V)> for JsonObject {
+    fn extend<T: IntoIterator<Item = (String, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.inner_map.insert(k, v.into());
+        }
+    }
+}
+
+impl<'a, V: Into<JsonType>> Extend<(&'a str, V)> for JsonObject {
+    fn extend<T: IntoIterator<Item = (&'a str, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.inner_map.insert(k.to_string(), v.into());
+        }
+    }
+}
+
+impl fmt::Display for JsonType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.serialize())
     }
 }
@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::path::{JsonPath, Segment};
+use crate::JsonType;
+
+/// a difference found by `diff`, paths point into the old value for
+/// removals and into the new value otherwise
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    Added {
+        path: JsonPath,
+        value: JsonType,
+    },
+    Removed {
+        path: JsonPath,
+        value: JsonType,
+    },
+    /// a different value of the same type
+    Changed {
+        path: JsonPath,
+        old: JsonType,
+        new: JsonType,
+    },
+    /// a value of another type, like a number that became a string
+    TypeChanged {
+        path: JsonPath,
+        old: JsonType,
+        new: JsonType,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &JsonPath {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. }
+            | Change::TypeChanged { path, .. } => path,
+        }
+    }
+}
+
+/// one line per change, like `~ a.b: 1 -> 2`
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self.path() {
+            path if path.is_root() => "(root)".to_string(),
+            path => path.to_string(),
+        };
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {}: {}", path, value.serialize()),
+            Change::Removed { value, .. } => write!(f, "- {}: {}", path, value.serialize()),
+            Change::Changed { old, new, .. } => {
+                write!(f, "~ {}: {} -> {}", path, old.serialize(), new.serialize())
+            }
+            Change::TypeChanged { old, new, .. } => write!(
+                f,
+                "~ {}: {} ({}) -> {} ({})",
+                path,
+                old.serialize(),
+                old.type_name(),
+                new.serialize(),
+                new.type_name()
+            ),
+        }
+    }
+}
+
+/// how the elements of two arrays are paired up
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum ArrayDiff {
+    /// elements at the same index, the tail of the longer array is added or
+    /// removed
+    #[default]
+    Index,
+    /// a longest common subsequence, so an inserted element is one addition
+    /// instead of a change of every later element
+    Lcs,
+    /// objects with the same value of this member, like `"id"`, elements
+    /// without it are paired by their order
+    Key(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayDiff,
+}
+
+/// the changes from `old` to `new`, comparing arrays by index
+///
+/// ```
+/// use dynamic_json::{diff, parse};
+/// let old = parse(r#"{ "name": "a", "tags": ["x"], "size": 1 }"#);
+/// let new = parse(r#"{ "name": "b", "tags": ["x", "y"], "size": "1" }"#);
+/// let changes: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+/// assert_eq!(changes, [
+///     r#"~ name: "a" -> "b""#,
+///     r#"+ tags[1]: "y""#,
+///     r#"~ size: 1 (number) -> "1" (string)"#,
+/// ]);
+/// ```
+pub fn diff(old: &JsonType, new: &JsonType) -> Vec<Change> {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+/// the changes from `old` to `new`
+///
+/// ```
+/// use dynamic_json::{diff_with, parse, ArrayDiff, Change, DiffOptions};
+/// let old = parse(r#"[{ "id": 1, "v": "a" }, { "id": 2, "v": "b" }]"#);
+/// let new = parse(r#"[{ "id": 2, "v": "c" }]"#);
+/// let options = DiffOptions { arrays: ArrayDiff::Key("id".to_string()) };
+/// let changes = diff_with(&old, &new, &options);
+/// assert!(matches!(&changes[0], Change::Removed { path, .. } if path.to_string() == "[0]"));
+/// assert_eq!(changes[1].to_string(), r#"~ [0].v: "b" -> "c""#);
+/// ```
+pub fn diff_with(old: &JsonType, new: &JsonType, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_values(old, new, &mut JsonPath::root(), options, &mut changes);
+    changes
+}
+
+fn diff_values(
+    old: &JsonType,
+    new: &JsonType,
+    path: &mut JsonPath,
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (JsonType::Object(a), JsonType::Object(b)) => {
+            for (key, value) in a {
+                path.push(Segment::Key(key.clone()));
+                match b.get(key) {
+                    Some(new) => diff_values(value, new, path, options, changes),
+                    None => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains(key)) {
+                changes.push(Change::Added {
+                    path: path.child(Segment::Key(key.clone())),
+                    value: value.clone(),
+                });
+            }
+        }
+        (JsonType::Array(a), JsonType::Array(b)) => {
+            let pairs = match &options.arrays {
+                ArrayDiff::Index => (0..a.len().min(b.len())).map(|i| (i, i)).collect(),
+                ArrayDiff::Lcs => lcs_pairs(a, b),
+                ArrayDiff::Key(key) => key_pairs(a, b, key),
+            };
+            diff_paired(a, b, &pairs, path, options, changes);
+        }
+        _ if old.type_name() != new.type_name() => changes.push(Change::TypeChanged {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => changes.push(Change::Changed {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+/// compare paired elements, elements without a partner were removed or added
+fn diff_paired(
+    a: &[JsonType],
+    b: &[JsonType],
+    pairs: &[(usize, usize)],
+    path: &mut JsonPath,
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    let mut paired = vec![false; b.len()];
+    let mut partner = vec![None; a.len()];
+    for &(i, j) in pairs {
+        partner[i] = Some(j);
+        paired[j] = true;
+    }
+    for (i, old) in a.iter().enumerate() {
+        match partner[i] {
+            Some(j) => {
+                path.push(Segment::Index(j));
+                diff_values(old, &b[j], path, options, changes);
+                path.pop();
+            }
+            None => changes.push(Change::Removed {
+                path: path.child(Segment::Index(i)),
+                value: old.clone(),
+            }),
+        }
+    }
+    for (j, new) in b.iter().enumerate().filter(|(j, _)| !paired[*j]) {
+        changes.push(Change::Added {
+            path: path.child(Segment::Index(j)),
+            value: new.clone(),
+        });
+    }
+}
+
+/// the common subsequence, and between two of its elements the unmatched
+/// elements of both sides paired up in order
+pub(crate) fn lcs_pairs(a: &[JsonType], b: &[JsonType]) -> Vec<(usize, usize)> {
+    let common = common_subsequence(a.len(), b.len(), |i, j| a[i] == b[j]);
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    for &(x, y) in common.iter().chain([(a.len(), b.len())].iter()) {
+        pairs.extend((i..x).zip(j..y));
+        if x < a.len() {
+            pairs.push((x, y));
+        }
+        (i, j) = (x + 1, y + 1);
+    }
+    pairs
+}
+
+fn key_pairs(a: &[JsonType], b: &[JsonType], key: &str) -> Vec<(usize, usize)> {
+    let key_of = |value: &JsonType| Some(value.as_object()?.get(key)?.serialize());
+    let mut keyed = HashMap::new();
+    let mut unkeyed = vec![];
+    for (j, value) in b.iter().enumerate() {
+        match key_of(value) {
+            Some(k) => {
+                keyed.entry(k).or_insert(j);
+            }
+            None => unkeyed.push(j),
+        }
+    }
+    let mut unkeyed = unkeyed.into_iter();
+    let mut pairs = vec![];
+    for (i, value) in a.iter().enumerate() {
+        let j = match key_of(value) {
+            Some(k) => keyed.remove(&k),
+            None => unkeyed.next(),
+        };
+        if let Some(j) = j {
+            pairs.push((i, j));
+        }
+    }
+    pairs
+}
+
+/// index pairs of a longest common subsequence of two sequences of length
+/// `n` and `m`, with the linear space variant of Myers' O(ND) algorithm
+fn common_subsequence<F: Fn(usize, usize) -> bool>(
+    n: usize,
+    m: usize,
+    eq: F,
+) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    subsequence_between(&eq, (0, n), (0, m), &mut pairs);
+    pairs
+}
+
+/// append the common subsequence of `a.0..a.1` and `b.0..b.1`, splitting
+/// both at the middle of a shortest edit script
+fn subsequence_between<F: Fn(usize, usize) -> bool>(
+    eq: &F,
+    (mut a0, mut a1): (usize, usize),
+    (mut b0, mut b1): (usize, usize),
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    while a0 < a1 && b0 < b1 && eq(a0, b0) {
+        pairs.push((a0, b0));
+        a0 += 1;
+        b0 += 1;
+    }
+    let mut suffix = 0;
+    while a0 < a1 && b0 < b1 && eq(a1 - 1, b1 - 1) {
+        a1 -= 1;
+        b1 -= 1;
+        suffix += 1;
+    }
+    if a0 < a1 && b0 < b1 {
+        let (x, y) = middle_snake(|x, y| eq(a0 + x, b0 + y), a1 - a0, b1 - b0);
+        subsequence_between(eq, (a0, a0 + x), (b0, b0 + y), pairs);
+        subsequence_between(eq, (a0 + x, a1), (b0 + y, b1), pairs);
+    }
+    pairs.extend((0..suffix).map(|i| (a1 + i, b1 + i)));
+}
+
+/// where the forward and backward searches for a shortest edit script of
+/// two non empty sequences, which differ at both ends, meet
+fn middle_snake<F: Fn(usize, usize) -> bool>(eq: F, n: usize, m: usize) -> (usize, usize) {
+    let (n, m) = (n as isize, m as isize);
+    let max = (n + m + 1) / 2;
+    let at = |k: isize| (k + max + 1) as usize;
+    let mut forward = vec![-1isize; 2 * max as usize + 3];
+    let mut backward = forward.clone();
+    forward[at(1)] = 0;
+    backward[at(1)] = 0;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let step = |v: &[isize], k: isize, d: isize| match k == -d
+        || (k != d && v[at(k - 1)] < v[at(k + 1)])
+    {
+        true => v[at(k + 1)],
+        false => v[at(k - 1)] + 1,
+    };
+    // diagonals that left the grid on either side are not searched again
+    let (mut forward_start, mut forward_end) = (0, 0);
+    let (mut backward_start, mut backward_end) = (0, 0);
+    for d in 0..max {
+        for k in (-d + forward_start..=d - forward_end).step_by(2) {
+            let mut x = step(&forward, k, d);
+            let mut y = x - k;
+            while x < n && y < m && eq(x as usize, y as usize) {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            if x > n {
+                forward_end += 2;
+            } else if y > m {
+                forward_start += 2;
+            } else if odd {
+                let other = delta - k;
+                if other.abs() <= max && backward[at(other)] != -1 && x >= n - backward[at(other)] {
+                    return (x as usize, y as usize);
+                }
+            }
+        }
+        for k in (-d + backward_start..=d - backward_end).step_by(2) {
+            let mut x = step(&backward, k, d);
+            let mut y = x - k;
+            while x < n && y < m && eq((n - x - 1) as usize, (m - y - 1) as usize) {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            if x > n {
+                backward_end += 2;
+            } else if y > m {
+                backward_start += 2;
+            } else if !odd {
+                let other = delta - k;
+                if other.abs() <= max && forward[at(other)] != -1 && forward[at(other)] >= n - x {
+                    let x = forward[at(other)];
+                    return (x as usize, (x - other) as usize);
+                }
+            }
+        }
+    }
+    // the searches only miss each other when nothing is in common
+    (n as usize, 0)
+}
+
+/// lines of context around every change in `unified_diff`
+const CONTEXT: usize = 3;
+
+/// a unified diff of the pretty printed values, empty if they are equal
+///
+/// ```
+/// use dynamic_json::{parse, unified_diff};
+/// let old = parse(r#"{ "a": 1, "b": [1, 2] }"#);
+/// let new = parse(r#"{ "a": 1, "b": [1, 3] }"#);
+/// assert_eq!(unified_diff(&old, &new), "\
+/// --- old
+/// +++ new
+/// @@ -2,6 +2,6 @@
+///    \"a\": 1,
+///    \"b\": [
+///      1,
+/// -    2
+/// +    3
+///    ]
+///  }
+/// ");
+/// // instead of assert_eq!(old, new)
+/// assert!(old != new, "{}", unified_diff(&old, &new));
+/// ```
+pub fn unified_diff(old: &JsonType, new: &JsonType) -> String {
+    let old = old.pretty_serialize(2);
+    let new = new.pretty_serialize(2);
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    // (tag, line, lines of old and new before it)
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    let common = common_subsequence(a.len(), b.len(), |i, j| a[i] == b[j]);
+    for &(x, y) in common.iter().chain([(a.len(), b.len())].iter()) {
+        for line in &a[i..x] {
+            lines.push(('-', *line, i, j));
+            i += 1;
+        }
+        for line in &b[j..y] {
+            lines.push(('+', *line, i, j));
+            j += 1;
+        }
+        if x < a.len() {
+            lines.push((' ', a[x], i, j));
+            (i, j) = (x + 1, y + 1);
+        }
+    }
+    let mut out = String::new();
+    let mut start = 0;
+    while let Some(first) = lines[start..].iter().position(|l| l.0 != ' ') {
+        let first = start + first;
+        // extend the hunk while the next change is close enough to share context
+        let mut last = first;
+        while let Some(next) = lines[last + 1..].iter().position(|l| l.0 != ' ') {
+            if next > 2 * CONTEXT {
+                break;
+            }
+            last += next + 1;
+        }
+        let from = first.saturating_sub(CONTEXT).max(start);
+        let to = (last + 1 + CONTEXT).min(lines.len());
+        let hunk = &lines[from..to];
+        let old_len = hunk.iter().filter(|l| l.0 != '+').count();
+        let new_len = hunk.iter().filter(|l| l.0 != '-').count();
+        let (_, _, old_start, new_start) = hunk[0];
+        if out.is_empty() {
+            out.push_str("--- old\n+++ new\n");
+        }
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        for (tag, line, ..) in hunk {
+            out.push(*tag);
+            out.push_str(line);
+            out.push('\n');
+        }
+        start = to;
+    }
+    out
+}
+
+/// `start,len` of a hunk with a 1 based start, an empty range names the
+/// line before it
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{common_subsequence, diff_with, unified_diff, ArrayDiff, DiffOptions};
+    use crate::parse;
+
+    fn changes(old: &str, new: &str, arrays: ArrayDiff) -> Vec<String> {
+        let options = DiffOptions { arrays };
+        let changes = diff_with(&parse(old), &parse(new), &options);
+        changes.iter().map(|change| change.to_string()).collect()
+    }
+
+    #[test]
+    fn structural_diff() {
+        assert!(changes(
+            r#"{"a":[1,{"b":2}]}"#,
+            r#"{"a":[1,{"b":2}]}"#,
+            ArrayDiff::Index
+        )
+        .is_empty());
+        assert_eq!(
+            changes("1", "true", ArrayDiff::Index),
+            ["~ (root): 1 (number) -> true (boolean)"]
+        );
+        assert_eq!(
+            changes(
+                r#"{"a":1,"b":{"c":null,"d":[1]},"e":"x"}"#,
+                r#"{"b":{"c":{},"d":[2]},"e":"y","f":[]}"#,
+                ArrayDiff::Index
+            ),
+            [
+                "- a: 1",
+                "~ b.c: null (null) -> {} (object)",
+                "~ b.d[0]: 1 -> 2",
+                r#"~ e: "x" -> "y""#,
+                "+ f: []",
+            ]
+        );
+        let (old, new) = ("[1,2,3,4]", "[0,1,2,4,5]");
+        assert_eq!(
+            changes(old, new, ArrayDiff::Index),
+            [
+                "~ [0]: 1 -> 0",
+                "~ [1]: 2 -> 1",
+                "~ [2]: 3 -> 2",
+                "+ [4]: 5"
+            ]
+        );
+        assert_eq!(
+            changes(old, new, ArrayDiff::Lcs),
+            ["- [2]: 3", "+ [0]: 0", "+ [4]: 5"]
+        );
+        assert_eq!(
+            changes(r#"[{"v":1},2]"#, r#"[{"v":2},3,4]"#, ArrayDiff::Lcs),
+            ["~ [0].v: 1 -> 2", "~ [1]: 2 -> 3", "+ [2]: 4"]
+        );
+        let old = r#"[{"id":1,"n":"a"},{"id":2,"n":"b"},{"id":3,"n":"c"},"x"]"#;
+        let new = r#"[{"id":3,"n":"c"},{"id":1,"n":"z"},{"id":4},"y"]"#;
+        assert_eq!(
+            changes(old, new, ArrayDiff::Key("id".to_string())),
+            [
+                r#"~ [1].n: "a" -> "z""#,
+                r#"- [1]: {"id":2,"n":"b"}"#,
+                r#"~ [3]: "x" -> "y""#,
+                r#"+ [2]: {"id":4}"#
+            ]
+        );
+
+        let a: Vec<char> = "ABCABBA".chars().collect();
+        let b: Vec<char> = "CBABAC".chars().collect();
+        assert_eq!(
+            common_subsequence(a.len(), b.len(), |i, j| a[i] == b[j]).len(),
+            4
+        );
+        assert!(common_subsequence(0, 3, |_, _| true).is_empty());
+    }
+
+    #[test]
+    fn longest_subsequence() {
+        let mut seed = 7u32;
+        let mut next = |len: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % len
+        };
+        for _ in 0..500 {
+            let a: Vec<u32> = (0..next(12)).map(|_| next(3)).collect();
+            let b: Vec<u32> = (0..next(12)).map(|_| next(3)).collect();
+            let mut longest = vec![vec![0; b.len() + 1]; a.len() + 1];
+            for i in (0..a.len()).rev() {
+                for j in (0..b.len()).rev() {
+                    longest[i][j] = match a[i] == b[j] {
+                        true => longest[i + 1][j + 1] + 1,
+                        false => longest[i + 1][j].max(longest[i][j + 1]),
+                    };
+                }
+            }
+            let pairs = common_subsequence(a.len(), b.len(), |i, j| a[i] == b[j]);
+            assert_eq!(pairs.len(), longest[0][0], "{:?} {:?}", a, b);
+            assert!(pairs.iter().all(|&(i, j)| a[i] == b[j]));
+            assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        }
+
+        let a: Vec<usize> = (0..2000).collect();
+        let b: Vec<usize> = (2000..4000).collect();
+        assert!(common_subsequence(a.len(), b.len(), |i, j| a[i] == b[j]).is_empty());
+    }
+
+    #[test]
+    fn unified_text() {
+        let old = parse(r#"{"a":1,"b":2,"c":3,"d":4,"e":5,"f":6,"g":7,"h":8,"i":9,"j":10}"#);
+        assert_eq!(unified_diff(&old, &old), "");
+        let new = parse(r#"{"z":0,"a":1,"b":2,"c":3,"d":4,"e":5,"f":6,"g":7,"h":8,"i":9}"#);
+        assert_eq!(
+            unified_diff(&old, &new),
+            r#"--- old
++++ new
+@@ -1,4 +1,5 @@
+ {
++  "z": 0,
+   "a": 1,
+   "b": 2,
+   "c": 3,
+@@ -7,6 +8,5 @@
+   "f": 6,
+   "g": 7,
+   "h": 8,
+-  "i": 9,
+-  "j": 10
++  "i": 9
+ }
+"#
+        );
+        let changed = unified_diff(&parse("[1]"), &parse("2"));
+        assert_eq!(
+            changed,
+            "--- old\n+++ new\n@@ -1,3 +1 @@\n-[\n-  1\n-]\n+2\n"
+        );
+    }
+}
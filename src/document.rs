@@ -0,0 +1,851 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::{ErrorKind, JsonError, PathError};
+use crate::lexer::{Lexer, Token};
+use crate::options::ParseOptions;
+use crate::path::{JsonPath, Segment, ToPath};
+use crate::read::SliceSource;
+use crate::ser::serialize_string;
+use crate::{serialize, JsonObject, JsonType};
+
+/// a json document that remembers how it was written: whitespace, comments,
+/// key order, duplicate keys and the spelling of numbers and strings
+///
+/// values can be changed by path like `JsonType::get`, everything that
+/// is not touched is written back byte for byte
+///
+/// ```
+/// use dynamic_json::JsonDocument;
+/// let s = "{\n    \"name\": \"demo\",\n    \"version\": 1.0e0\n}\n";
+/// let mut doc = JsonDocument::parse(s).unwrap();
+/// doc.set("name", "edited").unwrap();
+/// doc.set("private", true).unwrap();
+/// assert_eq!(
+///     doc.to_string(),
+///     "{\n    \"name\": \"edited\",\n    \"version\": 1.0e0,\n    \"private\": true\n}\n"
+/// );
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsonDocument {
+    /// whitespace and comments before the root value
+    prefix: String,
+    root: Node,
+    /// whitespace, comments and allowed trailing content after the root value
+    suffix: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Node {
+    /// a string, number or literal as written, together with its value
+    Scalar(String, JsonType),
+    Object(Vec<Entry>, Close),
+    Array(Vec<Entry>, Close),
+}
+
+/// an object member or array element
+#[derive(Debug, PartialEq, Clone)]
+struct Entry {
+    /// whitespace and comments before the key or element
+    before: String,
+    /// the key as written and decoded, `None` for array elements
+    key: Option<(String, String)>,
+    /// the text between key and value, including the colon
+    colon: String,
+    value: Node,
+    /// whitespace and comments between the value and the next comma or bracket
+    after: String,
+}
+
+/// the end of an object or array
+#[derive(Debug, PartialEq, Clone, Default)]
+struct Close {
+    trailing_comma: bool,
+    /// whitespace and comments before the bracket, after the trailing comma
+    space: String,
+}
+
+/// an object or array being parsed
+struct Frame {
+    object: bool,
+    entries: Vec<Entry>,
+    before: String,
+    key: Option<(String, String)>,
+    colon: String,
+}
+
+/// layout of new entries, taken from their siblings
+struct Style {
+    /// indentation of the entries when they are on lines of their own
+    indent: Option<String>,
+    /// one level of indentation
+    unit: String,
+    /// `before` of entries when they share a line
+    space: String,
+    colon: String,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    lexer: Lexer<SliceSource<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn offset(&self) -> usize {
+        self.lexer.source().offset()
+    }
+
+    /// skip whitespace and comments, returning them
+    fn space(&mut self) -> Result<String, JsonError> {
+        let start = self.offset();
+        self.lexer.skip_spaces()?;
+        Ok(self.text[start..self.offset()].to_string())
+    }
+
+    fn key(&mut self) -> Result<(String, String), JsonError> {
+        let start = self.offset();
+        match self.lexer.peek()? {
+            Some(b'"') => self.lexer.parse_string()?,
+            Some(b'\'') if self.lexer.options.allow_single_quotes => self.lexer.parse_string()?,
+            Some(b)
+                if self.lexer.options.allow_unquoted_keys && self.lexer.starts_identifier(b) =>
+            {
+                self.lexer.parse_identifier()?
+            }
+            _ => return Err(self.lexer.unexpected()),
+        }
+        let raw = self.text[start..self.offset()].to_string();
+        Ok((raw, self.lexer.string().to_string()))
+    }
+
+    fn colon(&mut self) -> Result<String, JsonError> {
+        let start = self.offset();
+        self.lexer.skip_spaces()?;
+        if self.lexer.peek()? != Some(b':') {
+            return Err(self.lexer.unexpected());
+        }
+        self.lexer.bump();
+        self.lexer.skip_spaces()?;
+        Ok(self.text[start..self.offset()].to_string())
+    }
+
+    /// read the key of the next member when `frame` is an object
+    fn member(&mut self, frame: &mut Frame) -> Result<(), JsonError> {
+        if frame.object {
+            frame.key = Some(self.key()?);
+            frame.colon = self.colon()?;
+        }
+        Ok(())
+    }
+
+    fn parse(&mut self) -> Result<JsonDocument, JsonError> {
+        let prefix = self.space()?;
+        let mut stack: Vec<Frame> = vec![];
+        let root = 'value: loop {
+            let first = match self.lexer.peek()? {
+                Some(b) => b,
+                None => return Err(self.lexer.error(ErrorKind::UnexpectedEof)),
+            };
+            let mut node = match first {
+                b'{' | b'[' => {
+                    self.lexer.bump();
+                    let object = first == b'{';
+                    let space = self.space()?;
+                    if self.lexer.peek()? == Some(if object { b'}' } else { b']' }) {
+                        self.lexer.bump();
+                        container(object, vec![], Close::default(), space)
+                    } else {
+                        let mut frame = Frame {
+                            object,
+                            entries: vec![],
+                            before: space,
+                            key: None,
+                            colon: String::new(),
+                        };
+                        self.member(&mut frame)?;
+                        stack.push(frame);
+                        continue;
+                    }
+                }
+                _ => {
+                    let start = self.offset();
+                    let value = match self.lexer.parse_scalar(first)? {
+                        Token::Null => JsonType::Null,
+                        Token::Bool(b) => JsonType::Bool(b),
+                        Token::Number(num) => JsonType::Number(num),
+                        Token::String => JsonType::String(self.lexer.string().to_string()),
+                    };
+                    Node::Scalar(self.text[start..self.offset()].to_string(), value)
+                }
+            };
+            // attach the finished value, closing every container that ends after it
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => break 'value node,
+                };
+                let after = self.space()?;
+                frame.entries.push(Entry {
+                    before: std::mem::take(&mut frame.before),
+                    key: frame.key.take(),
+                    colon: std::mem::take(&mut frame.colon),
+                    value: node,
+                    after,
+                });
+                let close = if frame.object { b'}' } else { b']' };
+                match self.lexer.peek()? {
+                    Some(b',') => {
+                        self.lexer.bump();
+                        let space = self.space()?;
+                        if self.lexer.peek()? == Some(close)
+                            && self.lexer.options.allow_trailing_commas
+                        {
+                            self.lexer.bump();
+                            let close = Close {
+                                trailing_comma: true,
+                                space,
+                            };
+                            node = self.finish(&mut stack, close);
+                            continue;
+                        }
+                        let mut frame = stack.pop().unwrap();
+                        frame.before = space;
+                        self.member(&mut frame)?;
+                        stack.push(frame);
+                        continue 'value;
+                    }
+                    Some(b) if b == close => {
+                        self.lexer.bump();
+                        node = self.finish(&mut stack, Close::default());
+                    }
+                    _ => return Err(self.lexer.unexpected()),
+                }
+            }
+        };
+        let mut suffix = self.space()?;
+        if self.lexer.peek()?.is_some() {
+            if !self.lexer.options.allow_trailing_content {
+                return Err(self.lexer.error(ErrorKind::TrailingCharacters));
+            }
+            suffix.push_str(&self.text[self.offset()..]);
+        }
+        Ok(JsonDocument {
+            prefix,
+            root,
+            suffix,
+        })
+    }
+
+    fn finish(&mut self, stack: &mut Vec<Frame>, close: Close) -> Node {
+        let frame = stack.pop().unwrap();
+        container(frame.object, frame.entries, close, String::new())
+    }
+}
+
+fn container(object: bool, entries: Vec<Entry>, mut close: Close, space: String) -> Node {
+    close.space.insert_str(0, &space);
+    if object {
+        Node::Object(entries, close)
+    } else {
+        Node::Array(entries, close)
+    }
+}
+
+impl JsonDocument {
+    /// parse strict json
+    pub fn parse(s: &str) -> Result<JsonDocument, JsonError> {
+        JsonDocument::parse_with(s, &ParseOptions::default())
+    }
+
+    /// parse with the given extensions, comments and trailing commas are kept as written
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<JsonDocument, JsonError> {
+        let mut lexer = Lexer::new(SliceSource::new(s.as_bytes()));
+        lexer.options = options.clone();
+        Parser { text: s, lexer }.parse()
+    }
+
+    /// the value of the whole document
+    pub fn to_json(&self) -> JsonType {
+        self.root.to_json()
+    }
+
+    /// the value at a path, `""` is the root
+    pub fn get<P: ToPath + ?Sized>(&self, path: &P) -> Option<JsonType> {
+        let path = path.to_path().ok()?;
+        let mut node = &self.root;
+        for segment in path.segments() {
+            let (entries, index) = node.find(segment)?;
+            node = &entries[index].value;
+        }
+        Some(node.to_json())
+    }
+
+    /// replace the value at `path`, or add it when the last segment is a new
+    /// key of an object or the length of an array
+    pub fn set<P: ToPath + ?Sized, T: Into<JsonType>>(
+        &mut self,
+        path: &P,
+        value: T,
+    ) -> Result<(), PathError> {
+        let value = value.into();
+        let path = path.to_path()?;
+        let (last, parents) = match path.segments().split_last() {
+            Some(split) => split,
+            None => {
+                self.root = build(&value, &Style::default(), "");
+                return Ok(());
+            }
+        };
+        let parent = self.parent_mut(parents)?;
+        match parent.find_mut(last) {
+            Some((entries, index)) => {
+                let style = Style::of(entries);
+                let entry = &mut entries[index];
+                let indent = line_indent(&entry.before).unwrap_or_default();
+                entry.value = build(&value, &style, &indent);
+                Ok(())
+            }
+            None => parent.insert(last, value, &path),
+        }
+    }
+
+    /// add a member to an object, or insert an element into an array before
+    /// the given index
+    pub fn insert<P: ToPath + ?Sized, T: Into<JsonType>>(
+        &mut self,
+        path: &P,
+        value: T,
+    ) -> Result<(), PathError> {
+        let path = path.to_path()?;
+        let (last, parents) = match path.segments().split_last() {
+            Some(split) => split,
+            None => {
+                return Err(PathError::NotFound {
+                    path: String::new(),
+                })
+            }
+        };
+        let parent = self.parent_mut(parents)?;
+        if let Node::Object(..) = parent {
+            if let Some((entries, index)) = parent.find_mut(last) {
+                entries.remove(index);
+            }
+        }
+        parent.insert(last, value.into(), &path)
+    }
+
+    /// remove the value at `path` together with its comments and return it,
+    /// every member with a duplicated key is removed
+    pub fn remove<P: ToPath + ?Sized>(&mut self, path: &P) -> Result<JsonType, PathError> {
+        let path = path.to_path()?;
+        let (last, parents) = match path.segments().split_last() {
+            Some(split) => split,
+            None => {
+                return Err(PathError::NotFound {
+                    path: String::new(),
+                })
+            }
+        };
+        let node = self.parent_mut(parents)?;
+        let mut removed = None;
+        while let Some((_, index)) = node.find(last) {
+            let entry = node.remove_entry(index);
+            removed.get_or_insert_with(|| entry.value.to_json());
+            if let Node::Array(..) = node {
+                break;
+            }
+        }
+        removed.ok_or_else(|| node.missing(path.segments()))
+    }
+
+    fn parent_mut(&mut self, parents: &[Segment]) -> Result<&mut Node, PathError> {
+        let mut node = &mut self.root;
+        for (i, segment) in parents.iter().enumerate() {
+            let index = match node.find(segment) {
+                Some((_, index)) => index,
+                None => return Err(node.missing(&parents[..=i])),
+            };
+            node = match node {
+                Node::Object(entries, _) | Node::Array(entries, _) => &mut entries[index].value,
+                Node::Scalar(..) => unreachable!("scalars have no entries"),
+            };
+        }
+        Ok(node)
+    }
+}
+
+impl Node {
+    /// the error for the last segment of `path` selecting nothing in this node
+    fn missing(&self, path: &[Segment]) -> PathError {
+        let full: JsonPath = path.iter().cloned().collect();
+        match (self, path.last()) {
+            (Node::Scalar(_, value), _) => PathError::TypeMismatch {
+                path: full.parent().unwrap_or_default().to_string(),
+                expected: "object or array",
+                found: value.type_name(),
+            },
+            (Node::Array(..), Some(Segment::Key(_))) => PathError::InvalidIndex {
+                path: full.to_string(),
+            },
+            _ => PathError::NotFound {
+                path: full.to_string(),
+            },
+        }
+    }
+
+    /// take out an entry, the comments on the line after the comma before it
+    /// stay with the previous value and its own comments go with it
+    fn remove_entry(&mut self, index: usize) -> Entry {
+        let (entries, close) = match self {
+            Node::Object(entries, close) | Node::Array(entries, close) => (entries, close),
+            Node::Scalar(..) => unreachable!("scalars have no entries"),
+        };
+        let removed = entries.remove(index);
+        let kept = first_line(&removed.before).trim_end();
+        if index == entries.len() {
+            let tail = format!("{}{}", removed.after, close.space);
+            close.space = join_lines(kept, without_first_line(&tail));
+            if entries.is_empty() {
+                close.trailing_comma = false;
+            }
+        } else {
+            let next = &mut entries[index];
+            next.before = join_lines(kept, without_first_line(&next.before));
+        }
+        removed
+    }
+
+    fn to_json(&self) -> JsonType {
+        match self {
+            Node::Scalar(_, value) => value.clone(),
+            Node::Object(entries, _) => {
+                let mut obj = JsonObject::new();
+                for entry in entries {
+                    if let Some((_, key)) = &entry.key {
+                        obj.inner_map.insert(key.clone(), entry.value.to_json());
+                    }
+                }
+                JsonType::Object(obj)
+            }
+            Node::Array(entries, _) => {
+                JsonType::Array(entries.iter().map(|entry| entry.value.to_json()).collect())
+            }
+        }
+    }
+
+    fn find(&self, segment: &Segment) -> Option<(&[Entry], usize)> {
+        let index = match (self, segment) {
+            (Node::Object(entries, _), segment) => {
+                let segment = segment_key(segment);
+                entries
+                    .iter()
+                    .rposition(|entry| matches!(&entry.key, Some((_, key)) if *key == segment))?
+            }
+            (Node::Array(entries, _), Segment::Index(index)) if *index < entries.len() => *index,
+            _ => return None,
+        };
+        match self {
+            Node::Object(entries, _) | Node::Array(entries, _) => Some((entries, index)),
+            Node::Scalar(..) => None,
+        }
+    }
+
+    fn find_mut(&mut self, segment: &Segment) -> Option<(&mut Vec<Entry>, usize)> {
+        let index = self.find(segment)?.1;
+        match self {
+            Node::Object(entries, _) | Node::Array(entries, _) => Some((entries, index)),
+            Node::Scalar(..) => None,
+        }
+    }
+
+    /// add a new entry to this container, laid out like its siblings
+    fn insert(
+        &mut self,
+        segment: &Segment,
+        value: JsonType,
+        path: &JsonPath,
+    ) -> Result<(), PathError> {
+        let parent = path.parent().unwrap_or_default();
+        let (entries, close, key, index) = match self {
+            Node::Object(entries, close) => {
+                let segment = segment_key(segment);
+                let key = Some((serialize_string(&segment), segment.into_owned()));
+                let len = entries.len();
+                (entries, close, key, len)
+            }
+            Node::Array(entries, close) => match segment {
+                Segment::Index(index) if *index <= entries.len() => (entries, close, None, *index),
+                Segment::Index(index) => {
+                    return Err(PathError::IndexOutOfRange {
+                        path: parent.to_string(),
+                        index: *index,
+                        len: entries.len(),
+                    })
+                }
+                Segment::Key(_) => {
+                    return Err(PathError::InvalidIndex {
+                        path: path.to_string(),
+                    })
+                }
+            },
+            Node::Scalar(_, value) => {
+                return Err(PathError::TypeMismatch {
+                    path: parent.to_string(),
+                    expected: "object or array",
+                    found: value.type_name(),
+                })
+            }
+        };
+        let style = Style::of(entries);
+        let before = match &style.indent {
+            Some(indent) => format!("\n{}", indent),
+            None if entries.is_empty() => String::new(),
+            None => style.space.clone(),
+        };
+        let indent = style.indent.clone().unwrap_or_default();
+        let mut entry = Entry {
+            before,
+            colon: if key.is_some() {
+                style.colon.clone()
+            } else {
+                String::new()
+            },
+            key,
+            value: build(&value, &style, &indent),
+            after: String::new(),
+        };
+        if index == entries.len() {
+            if let Some(last) = entries.last_mut() {
+                entry.after = std::mem::take(&mut last.after);
+            } else if style.indent.is_some() && !close.space.contains('\n') {
+                close.space = format!("\n{}", container_indent(&indent, &style.unit));
+            }
+        } else {
+            std::mem::swap(&mut entry.before, &mut entries[index].before);
+            if index == 0 && entries.len() > 1 {
+                entries[index].before = entries[1].before.clone();
+            }
+        }
+        entries.insert(index, entry);
+        Ok(())
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (entries, close, brackets) = match self {
+            Node::Scalar(raw, _) => return f.write_str(raw),
+            Node::Object(entries, close) => (entries, close, ('{', '}')),
+            Node::Array(entries, close) => (entries, close, ('[', ']')),
+        };
+        write!(f, "{}", brackets.0)?;
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(&entry.before)?;
+            if let Some((raw, _)) = &entry.key {
+                f.write_str(raw)?;
+                f.write_str(&entry.colon)?;
+            }
+            entry.value.write(f)?;
+            f.write_str(&entry.after)?;
+        }
+        if close.trailing_comma {
+            f.write_str(",")?;
+        }
+        write!(f, "{}{}", close.space, brackets.1)
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent: None,
+            unit: "  ".to_string(),
+            space: " ".to_string(),
+            colon: ": ".to_string(),
+        }
+    }
+}
+
+impl Style {
+    fn of(entries: &[Entry]) -> Style {
+        let mut style = Style::default();
+        if let Some(entry) = entries.iter().find(|entry| entry.key.is_some()) {
+            style.colon = entry.colon.clone();
+        }
+        match entries {
+            [_, second, ..] => style.space = second.before.clone(),
+            [first] if !first.before.is_empty() => style.space = first.before.clone(),
+            _ => (),
+        }
+        style.indent = entries.iter().find_map(|entry| line_indent(&entry.before));
+        if let (Some(indent), Some(last)) = (&style.indent, entries.last()) {
+            let outer = line_indent(&last.after).unwrap_or_default();
+            if indent.len() > outer.len() && indent.starts_with(&outer) {
+                style.unit = indent[outer.len()..].to_string();
+            }
+        }
+        style
+    }
+}
+
+/// the indentation of the last line of `space`, if it contains a line break
+fn line_indent(space: &str) -> Option<String> {
+    let line = &space[space.rfind('\n')? + 1..];
+    Some(
+        line.chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect(),
+    )
+}
+
+fn container_indent(indent: &str, unit: &str) -> String {
+    indent.strip_suffix(unit).unwrap_or_default().to_string()
+}
+
+fn first_line(space: &str) -> &str {
+    match space.find('\n') {
+        Some(end) => &space[..end],
+        None => space,
+    }
+}
+
+/// `space` from its first line break, or its trailing blanks on a single line
+fn without_first_line(space: &str) -> &str {
+    match space.find('\n') {
+        Some(end) => &space[end..],
+        None => &space[space.trim_end().len()..],
+    }
+}
+
+/// comments followed by `space`, breaking the line after a line comment
+fn join_lines(comments: &str, space: &str) -> String {
+    let line_comment = comments
+        .rsplit("*/")
+        .next()
+        .unwrap_or_default()
+        .contains("//");
+    match line_comment && !space.contains('\n') {
+        true => format!("{}\n{}", comments, space),
+        false => format!("{}{}", comments, space),
+    }
+}
+
+/// layout a new value whose entries are indented one `unit` deeper than `indent`
+fn build(value: &JsonType, style: &Style, indent: &str) -> Node {
+    let inner = format!("{}{}", indent, style.unit);
+    let before = |i: usize| match style.indent {
+        Some(_) => format!("\n{}", inner),
+        None if i == 0 => String::new(),
+        None => style.space.clone(),
+    };
+    let close = |empty: bool| Close {
+        trailing_comma: false,
+        space: match style.indent {
+            Some(_) if !empty => format!("\n{}", indent),
+            _ => String::new(),
+        },
+    };
+    match value {
+        JsonType::Object(obj) => {
+            let entries = obj
+                .into_iter()
+                .enumerate()
+                .map(|(i, (key, value))| Entry {
+                    before: before(i),
+                    key: Some((serialize(&key.as_str().into(), 0, 0), key.clone())),
+                    colon: style.colon.clone(),
+                    value: build(value, style, &inner),
+                    after: String::new(),
+                })
+                .collect::<Vec<_>>();
+            let close = close(entries.is_empty());
+            Node::Object(entries, close)
+        }
+        JsonType::Array(arr) => {
+            let entries = arr
+                .iter()
+                .enumerate()
+                .map(|(i, value)| Entry {
+                    before: before(i),
+                    key: None,
+                    colon: String::new(),
+                    value: build(value, style, &inner),
+                    after: String::new(),
+                })
+                .collect::<Vec<_>>();
+            let close = close(entries.is_empty());
+            Node::Array(entries, close)
+        }
+        _ => Node::Scalar(serialize(value, 0, 0), value.clone()),
+    }
+}
+
+/// the member name a segment selects, indices look up keys spelled as numbers
+fn segment_key(segment: &Segment) -> Cow<'_, str> {
+    match segment {
+        Segment::Key(key) => Cow::Borrowed(key),
+        Segment::Index(index) => Cow::Owned(index.to_string()),
+    }
+}
+
+impl fmt::Display for JsonDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.prefix)?;
+        self.root.write(f)?;
+        f.write_str(&self.suffix)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonDocument;
+    use crate::{json_arr, parse, JsonType, ParseOptions, PathError};
+
+    #[test]
+    fn byte_identical() {
+        let inputs = [
+            "{}",
+            " [ ] ",
+            "{\n\t\"b\" :1.50, \"a\":[ 1e3 ,-0.0,\"\\u0041\" ],\n\"b\": {}\n}\n",
+            "[[[[\"deep\"]]]]",
+            "\"scalar\"",
+        ];
+        for input in inputs {
+            let doc = JsonDocument::parse(input).unwrap();
+            assert_eq!(doc.to_string(), input);
+            assert_eq!(doc.to_json(), parse(input));
+        }
+        let input = "// settings\n{ 'a': 1, /* two */ b: [2,], } // end\n";
+        let doc = JsonDocument::parse_with(input, &ParseOptions::lenient()).unwrap();
+        assert_eq!(doc.to_string(), input);
+        assert_eq!(doc.get("b.0"), Some(JsonType::from(2)));
+        assert!(JsonDocument::parse("[1,]").is_err());
+    }
+
+    #[test]
+    fn edit_by_path() {
+        let input = "{\n  \"name\": \"x\", \"n\": 1.0,\n  \"list\": [\n    1,\n    2\n  ]\n}";
+        let mut doc = JsonDocument::parse(input).unwrap();
+        doc.set("name", "y").unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"name\": \"y\", \"n\": 1.0,\n  \"list\": [\n    1,\n    2\n  ]\n}"
+        );
+
+        doc.insert("list.0", 0).unwrap();
+        doc.set("list.3", json_arr![]).unwrap();
+        assert_eq!(doc.remove("list.2"), Ok(JsonType::from(2)));
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"name\": \"y\", \"n\": 1.0,\n  \"list\": [\n    0,\n    1,\n    []\n  ]\n}"
+        );
+
+        assert_eq!(doc.remove("list"), Ok(json_arr![0, 1, json_arr![]]));
+        doc.set("nested", json_arr![1, json_arr![2]]).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"name\": \"y\", \"n\": 1.0,\n  \"nested\": [\n    1,\n    [\n      2\n    ]\n  ]\n}"
+        );
+
+        let mut doc = JsonDocument::parse("{ \"a\": 1, \"b\": 2 }").unwrap();
+        doc.remove("a").unwrap();
+        doc.set("c", 3).unwrap();
+        assert_eq!(doc.to_string(), "{ \"b\": 2, \"c\": 3 }");
+        doc.remove("b").unwrap();
+        doc.remove("c").unwrap();
+        assert_eq!(doc.to_string(), "{ }");
+
+        assert!(doc.set("x.y", 1).is_err());
+
+        let mut doc =
+            JsonDocument::parse("{\n    \"version\": 1.10,\n    \"deps\": [\"a\"]\n}").unwrap();
+        doc.set("deps.1", "b").unwrap();
+        doc.remove("version").unwrap();
+        assert_eq!(doc.to_string(), "{\n    \"deps\": [\"a\", \"b\"]\n}");
+        assert!(doc.insert("", 1).is_err());
+        assert!(doc.remove("x").is_err());
+
+        let mut doc = JsonDocument::parse("{\"a\": {}}").unwrap();
+        doc.set(r#"a["b.c"]"#, 1).unwrap();
+        assert_eq!(doc.to_string(), "{\"a\": {\"b.c\": 1}}");
+        assert_eq!(doc.get(r#"a["b.c"]"#), Some(JsonType::from(1)));
+    }
+
+    #[test]
+    fn remove_keeps_comments() {
+        let options = ParseOptions {
+            allow_comments: true,
+            ..Default::default()
+        };
+        let input = "{\n  \"a\": 1, // one\n  \"b\": 2 // two\n}";
+        let mut doc = JsonDocument::parse_with(input, &options).unwrap();
+        assert_eq!(doc.remove("b"), Ok(JsonType::from(2)));
+        assert_eq!(doc.to_string(), "{\n  \"a\": 1 // one\n}");
+
+        let input = "[\n  1, // one\n  2, // two\n  3\n]";
+        let mut doc = JsonDocument::parse_with(input, &options).unwrap();
+        doc.remove("1").unwrap();
+        assert_eq!(doc.to_string(), "[\n  1, // one\n  3\n]");
+        doc.remove("1").unwrap();
+        doc.remove("0").unwrap();
+        assert_eq!(doc.to_string(), "[\n]");
+
+        let mut doc = JsonDocument::parse_with("[1, // one\n2]", &options).unwrap();
+        doc.remove("1").unwrap();
+        assert_eq!(doc.to_string(), "[1 // one\n]");
+    }
+
+    #[test]
+    fn remove_duplicate_keys() {
+        let mut doc = JsonDocument::parse(r#"{ "a": 1, "b": 2, "a": 3 }"#).unwrap();
+        assert_eq!(doc.remove("a"), Ok(JsonType::from(3)));
+        assert_eq!(doc.get("a"), None);
+        assert_eq!(doc.to_string(), r#"{ "b": 2 }"#);
+    }
+
+    #[test]
+    fn edit_errors() {
+        let mut doc = JsonDocument::parse(r#"{ "a": [1], "s": "x" }"#).unwrap();
+        assert_eq!(
+            doc.set("a.2", 1).unwrap_err(),
+            PathError::IndexOutOfRange {
+                path: "a".to_string(),
+                index: 2,
+                len: 1,
+            }
+        );
+        assert_eq!(
+            doc.insert("a.k", 1).unwrap_err(),
+            PathError::InvalidIndex {
+                path: "a.k".to_string()
+            }
+        );
+        assert_eq!(
+            doc.set("s.k", 1).unwrap_err(),
+            PathError::TypeMismatch {
+                path: "s".to_string(),
+                expected: "object or array",
+                found: "string",
+            }
+        );
+        assert_eq!(doc.set("x.y", 1).unwrap_err().path(), "x");
+        assert!(matches!(doc.insert("", 1), Err(PathError::NotFound { .. })));
+        assert!(matches!(doc.set("a[", 1), Err(PathError::Syntax { .. })));
+        assert_eq!(
+            doc.remove("a.3").unwrap_err(),
+            PathError::NotFound {
+                path: "a[3]".to_string()
+            }
+        );
+        assert_eq!(
+            doc.remove("s.k").unwrap_err(),
+            PathError::TypeMismatch {
+                path: "s".to_string(),
+                expected: "object or array",
+                found: "string",
+            }
+        );
+        assert_eq!(doc.remove("x.y").unwrap_err().path(), "x");
+    }
+}
@@ -0,0 +1,252 @@
+use std::{fmt, io};
+
+/// the reason why parsing failed
+#[derive(Debug, PartialEq, Clone)]
+pub enum ErrorKind {
+    /// input ended before the json value was complete
+    UnexpectedEof,
+    /// a character that is not allowed at this position
+    UnexpectedChar(char),
+    /// a number that can not be parsed
+    InvalidNumber,
+    /// a literal other than `null`, `true` or `false`
+    InvalidLiteral,
+    /// an unknown escape sequence or a malformed `\uXXXX`
+    InvalidEscape,
+    /// a `\uXXXX` escape that is not a valid unicode scalar, e.g. a lone surrogate
+    InvalidUnicode,
+    /// a raw control character inside a string
+    ControlCharacter,
+    /// bytes that are not valid utf-8
+    InvalidUtf8,
+    /// something other than whitespace after the root value
+    TrailingCharacters,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ErrorKind::InvalidLiteral => write!(f, "invalid literal"),
+            ErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ErrorKind::InvalidUnicode => write!(f, "invalid unicode escape"),
+            ErrorKind::ControlCharacter => write!(f, "control character in string"),
+            ErrorKind::InvalidUtf8 => write!(f, "invalid utf-8"),
+            ErrorKind::TrailingCharacters => write!(f, "trailing characters"),
+        }
+    }
+}
+
+/// error returned by the fallible apis of this crate
+///
+/// ```
+/// use dynamic_json::{try_parse, ErrorKind};
+/// let err = try_parse("{\n  \"a\": tru }").unwrap_err();
+/// assert_eq!(err.kind(), Some(&ErrorKind::InvalidLiteral));
+/// assert_eq!(err.line(), Some(2));
+/// assert_eq!(err.column(), Some(11));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonError {
+    /// the input is not valid json
+    Syntax {
+        kind: ErrorKind,
+        /// byte offset of the offending character
+        offset: usize,
+        /// 1-based line number
+        line: usize,
+        /// 1-based column number, counted in characters
+        column: usize,
+        /// input text right before the offending character
+        snippet: String,
+    },
+    /// reading the input failed
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+        /// byte offset where the read was attempted
+        offset: usize,
+    },
+}
+
+impl JsonError {
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        match self {
+            JsonError::Syntax { kind, .. } => Some(kind),
+            _ => None,
+        }
+    }
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            JsonError::Syntax { offset, .. } | JsonError::Io { offset, .. } => Some(*offset),
+        }
+    }
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            JsonError::Syntax { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            JsonError::Syntax { column, .. } => Some(*column),
+            _ => None,
+        }
+    }
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            JsonError::Syntax { snippet, .. } => Some(snippet),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax {
+                kind,
+                offset,
+                line,
+                column,
+                snippet,
+            } => write!(
+                f,
+                "{} at line {} column {} (byte {}) near `{}`",
+                kind, line, column, offset, snippet
+            ),
+            JsonError::Io {
+                message, offset, ..
+            } => write!(f, "io error at byte {}: {}", offset, message),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// error of the operations that address a value by path, `path` is the part
+/// of the path up to the segment that failed
+#[derive(Debug, PartialEq, Clone)]
+pub enum PathError {
+    /// `path` does not follow the path grammar, `offset` is the byte that failed
+    Syntax { path: String, offset: usize },
+    /// there is no value at `path`
+    NotFound { path: String },
+    /// the value at `path` is a `found` where an `expected` is needed
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// the last segment of `path` is not an array index
+    InvalidIndex { path: String },
+    /// `index` is past the end of the array at `path` which has `len` elements
+    IndexOutOfRange {
+        path: String,
+        index: usize,
+        len: usize,
+    },
+}
+
+impl PathError {
+    pub fn path(&self) -> &str {
+        match self {
+            PathError::Syntax { path, .. }
+            | PathError::NotFound { path }
+            | PathError::TypeMismatch { path, .. }
+            | PathError::InvalidIndex { path }
+            | PathError::IndexOutOfRange { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Syntax { path, offset } => {
+                write!(f, "invalid path `{}` at byte {}", path, offset)
+            }
+            PathError::NotFound { path } => write!(f, "can not found item {}", path),
+            PathError::TypeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(f, "expect {} at {} but found {}", expected, path, found),
+            PathError::InvalidIndex { path } => write!(f, "invalid array index at {}", path),
+            PathError::IndexOutOfRange { path, index, len } => write!(
+                f,
+                "index {} out of range for array of length {} at {}",
+                index, len, path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// error of the query languages, see `JsonType::query`
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryError {
+    /// the query does not parse or is not well typed, `offset` is the byte
+    /// where the problem was found
+    Syntax { message: String, offset: usize },
+    /// evaluating a valid query failed, e.g. indexing a number
+    Runtime { message: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Syntax { message, offset } => {
+                write!(f, "{} at byte {} of the query", message, offset)
+            }
+            QueryError::Runtime { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// error of a JSON Patch, `index` is the operation in the patch document
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchError {
+    /// the operation is not a valid RFC 6902 operation
+    Invalid { index: usize, message: String },
+    /// the operation refers to a location that can not be used, the path of
+    /// the error is a JSON Pointer
+    Path { index: usize, error: PathError },
+    /// a `test` operation found a different value at `path`
+    TestFailed { index: usize, path: String },
+}
+
+impl PatchError {
+    pub fn index(&self) -> usize {
+        match self {
+            PatchError::Invalid { index, .. }
+            | PatchError::Path { index, .. }
+            | PatchError::TestFailed { index, .. } => *index,
+        }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Invalid { index, message } => {
+                write!(f, "invalid operation {}: {}", index, message)
+            }
+            PatchError::Path { index, error } => write!(f, "operation {} failed: {}", index, error),
+            PatchError::TestFailed { index, path } => {
+                write!(
+                    f,
+                    "operation {} failed: test at {} does not match",
+                    index, path
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
@@ -0,0 +1,1076 @@
+use std::cmp::Ordering;
+
+use crate::error::QueryError;
+use crate::{try_parse, JsonObject, JsonType};
+
+/// a compiled JMESPath expression
+///
+/// the messages of errors named by the specification start with the name:
+/// `unknown-function`, `invalid-arity` and `invalid-value` are reported by
+/// `compile` as `QueryError::Syntax`, `invalid-type` by `search` as
+/// `QueryError::Runtime`
+///
+/// ```
+/// use dynamic_json::{parse, JmesPath};
+/// let json = parse(r#"{ "reservations": [
+///     { "instances": [{ "id": "a", "state": "running" }, { "id": "b", "state": "stopped" }] },
+///     { "instances": [{ "id": "c", "state": "running" }] }
+/// ] }"#);
+/// let expr = JmesPath::compile("reservations[].instances[?state=='running'].id | [0]").unwrap();
+/// assert_eq!(expr.search(&json).unwrap().serialize(), r#"["a"]"#);
+/// let expr = JmesPath::compile("length(reservations[].instances[])").unwrap();
+/// assert_eq!(expr.search(&json).unwrap().as_u64(), Some(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct JmesPath {
+    ast: Ast,
+}
+
+#[derive(Debug, Clone)]
+enum Ast {
+    Identity,
+    Field(String),
+    /// `left.right`
+    Sub(Box<Ast>, Box<Ast>),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    /// `left[n]` and `left[a:b]`, the index is applied to the value of left
+    IndexOf(Box<Ast>, Box<Ast>),
+    /// evaluate right on every element of the array left evaluates to
+    Projection(Box<Ast>, Box<Ast>),
+    /// like `Projection` over the values of an object
+    ValueProjection(Box<Ast>, Box<Ast>),
+    /// `left[?cond]`, then right on the elements that pass
+    Filter(Box<Ast>, Box<Ast>, Box<Ast>),
+    Flatten(Box<Ast>),
+    MultiList(Vec<Ast>),
+    MultiHash(Vec<(String, Ast)>),
+    Literal(JsonType),
+    Pipe(Box<Ast>, Box<Ast>),
+    Or(Box<Ast>, Box<Ast>),
+    And(Box<Ast>, Box<Ast>),
+    Not(Box<Ast>),
+    Compare(Cmp, Box<Ast>, Box<Ast>),
+    Call(Function, Vec<Ast>),
+    Expref(Box<Ast>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Avg,
+    Ceil,
+    Contains,
+    EndsWith,
+    Floor,
+    Join,
+    Keys,
+    Length,
+    Map,
+    Max,
+    MaxBy,
+    Merge,
+    Min,
+    MinBy,
+    NotNull,
+    Reverse,
+    Sort,
+    SortBy,
+    StartsWith,
+    Sum,
+    ToArray,
+    ToString,
+    ToNumber,
+    Type,
+    Values,
+}
+
+/// every function with its name and the number of arguments it takes,
+/// `None` as maximum for the variadic ones
+const FUNCTIONS: &[(&str, Function, usize, Option<usize>)] = &[
+    ("abs", Function::Abs, 1, Some(1)),
+    ("avg", Function::Avg, 1, Some(1)),
+    ("ceil", Function::Ceil, 1, Some(1)),
+    ("contains", Function::Contains, 2, Some(2)),
+    ("ends_with", Function::EndsWith, 2, Some(2)),
+    ("floor", Function::Floor, 1, Some(1)),
+    ("join", Function::Join, 2, Some(2)),
+    ("keys", Function::Keys, 1, Some(1)),
+    ("length", Function::Length, 1, Some(1)),
+    ("map", Function::Map, 2, Some(2)),
+    ("max", Function::Max, 1, Some(1)),
+    ("max_by", Function::MaxBy, 2, Some(2)),
+    ("merge", Function::Merge, 1, None),
+    ("min", Function::Min, 1, Some(1)),
+    ("min_by", Function::MinBy, 2, Some(2)),
+    ("not_null", Function::NotNull, 1, None),
+    ("reverse", Function::Reverse, 1, Some(1)),
+    ("sort", Function::Sort, 1, Some(1)),
+    ("sort_by", Function::SortBy, 2, Some(2)),
+    ("starts_with", Function::StartsWith, 2, Some(2)),
+    ("sum", Function::Sum, 1, Some(1)),
+    ("to_array", Function::ToArray, 1, Some(1)),
+    ("to_string", Function::ToString, 1, Some(1)),
+    ("to_number", Function::ToNumber, 1, Some(1)),
+    ("type", Function::Type, 1, Some(1)),
+    ("values", Function::Values, 1, Some(1)),
+];
+
+impl Function {
+    fn name(self) -> &'static str {
+        let found = FUNCTIONS.iter().find(|(_, f, ..)| *f == self);
+        found.map_or("", |(name, ..)| name)
+    }
+}
+
+impl JmesPath {
+    pub fn compile(expression: &str) -> Result<JmesPath, QueryError> {
+        let tokens = lex(expression)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let ast = parser.expression(0)?;
+        if parser.peek() != &Token::Eof {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(JmesPath { ast })
+    }
+
+    /// evaluate against `json`, type errors of functions are reported as
+    /// `QueryError::Runtime`
+    pub fn search(&self, json: &JsonType) -> Result<JsonType, QueryError> {
+        eval(&self.ast, json)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    QuotedIdentifier(String),
+    Number(i64),
+    Literal(JsonType),
+    Dot,
+    Star,
+    Flatten,
+    Filter,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+    At,
+    Ampersand,
+    Pipe,
+    Or,
+    And,
+    Not,
+    Cmp(Cmp),
+    Eof,
+}
+
+impl Token {
+    /// binding power in the Pratt parser, tokens below 10 end a projection
+    fn power(&self) -> u8 {
+        match self {
+            Token::Pipe => 1,
+            Token::Or => 2,
+            Token::And => 3,
+            Token::Cmp(_) => 5,
+            Token::Flatten => 9,
+            Token::Star => 20,
+            Token::Filter => 21,
+            Token::Dot => 40,
+            Token::Not => 45,
+            Token::LBrace => 50,
+            Token::LBracket => 55,
+            Token::LParen => 60,
+            _ => 0,
+        }
+    }
+}
+
+fn syntax(message: &str, offset: usize) -> QueryError {
+    QueryError::Syntax {
+        message: message.to_string(),
+        offset,
+    }
+}
+
+/// the tokens of an expression with their byte offsets
+fn lex(src: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let next = bytes.get(i + 1).copied();
+        let token = match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                let len = bytes[i..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                    .count();
+                i += len;
+                Token::Identifier(src[start..i].to_string())
+            }
+            b'-' | b'0'..=b'9' => {
+                let sign = usize::from(bytes[i] == b'-');
+                let len = bytes[i + sign..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                if len == 0 {
+                    return Err(syntax("expect a digit after `-`", i));
+                }
+                i += sign + len;
+                let number = src[start..i]
+                    .parse()
+                    .map_err(|_| syntax("number out of range", start))?;
+                Token::Number(number)
+            }
+            b'"' => {
+                let end =
+                    closing(bytes, i, b'"').ok_or_else(|| syntax("unterminated identifier", i))?;
+                i = end + 1;
+                match try_parse(&src[start..i]) {
+                    Ok(JsonType::String(s)) => Token::QuotedIdentifier(s),
+                    _ => return Err(syntax("invalid quoted identifier", start)),
+                }
+            }
+            b'\'' => {
+                let end =
+                    closing(bytes, i, b'\'').ok_or_else(|| syntax("unterminated string", i))?;
+                i = end + 1;
+                Token::Literal(JsonType::String(src[start + 1..end].replace("\\'", "'")))
+            }
+            b'`' => {
+                let end =
+                    closing(bytes, i, b'`').ok_or_else(|| syntax("unterminated literal", i))?;
+                i = end + 1;
+                let text = src[start + 1..end].replace("\\`", "`");
+                match try_parse(text.trim()) {
+                    Ok(value) => Token::Literal(value),
+                    Err(_) => return Err(syntax("invalid json literal", start)),
+                }
+            }
+            b'[' => match next {
+                Some(b'?') => {
+                    i += 2;
+                    Token::Filter
+                }
+                Some(b']') => {
+                    i += 2;
+                    Token::Flatten
+                }
+                _ => {
+                    i += 1;
+                    Token::LBracket
+                }
+            },
+            b'|' | b'&' if next == Some(bytes[i]) => {
+                i += 2;
+                if bytes[start] == b'|' {
+                    Token::Or
+                } else {
+                    Token::And
+                }
+            }
+            b'=' | b'!' | b'<' | b'>' if next == Some(b'=') => {
+                i += 2;
+                Token::Cmp(match bytes[start] {
+                    b'=' => Cmp::Eq,
+                    b'!' => Cmp::Ne,
+                    b'<' => Cmp::Le,
+                    _ => Cmp::Ge,
+                })
+            }
+            b => {
+                i += 1;
+                match b {
+                    b'.' => Token::Dot,
+                    b'*' => Token::Star,
+                    b']' => Token::RBracket,
+                    b'{' => Token::LBrace,
+                    b'}' => Token::RBrace,
+                    b'(' => Token::LParen,
+                    b')' => Token::RParen,
+                    b',' => Token::Comma,
+                    b':' => Token::Colon,
+                    b'@' => Token::At,
+                    b'&' => Token::Ampersand,
+                    b'|' => Token::Pipe,
+                    b'!' => Token::Not,
+                    b'<' => Token::Cmp(Cmp::Lt),
+                    b'>' => Token::Cmp(Cmp::Gt),
+                    _ => return Err(syntax("unexpected character", start)),
+                }
+            }
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::Eof, src.len()));
+    Ok(tokens)
+}
+
+/// the position of the quote closing the one at `start`, skipping escapes
+fn closing(bytes: &[u8], start: usize, quote: u8) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// nesting limit of the parser
+const MAX_DEPTH: usize = 256;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn peek_at(&self, n: usize) -> &Token {
+        let i = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[i].0
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        syntax(message, self.tokens[self.pos].1)
+    }
+
+    fn expect(&mut self, token: Token, message: &str) -> Result<(), QueryError> {
+        if *self.peek() != token {
+            return Err(self.error(message));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn expression(&mut self, power: u8) -> Result<Ast, QueryError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        let mut left = self.nud()?;
+        while power < self.peek().power() {
+            left = self.led(left)?;
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    fn nud(&mut self) -> Result<Ast, QueryError> {
+        let offset = self.tokens[self.pos].1;
+        Ok(match self.advance() {
+            Token::Literal(value) => Ast::Literal(value),
+            Token::Identifier(name) => Ast::Field(name),
+            Token::QuotedIdentifier(name) => {
+                if *self.peek() == Token::LParen {
+                    return Err(self.error("quoted identifiers can not be called"));
+                }
+                Ast::Field(name)
+            }
+            Token::Star => {
+                let right = match self.peek() {
+                    Token::RBracket => Ast::Identity,
+                    _ => self.projection_rhs(Token::Star.power())?,
+                };
+                Ast::ValueProjection(Box::new(Ast::Identity), Box::new(right))
+            }
+            Token::Filter => self.filter(Ast::Identity)?,
+            Token::LBrace => self.multi_hash()?,
+            Token::Flatten => {
+                let left = Ast::Flatten(Box::new(Ast::Identity));
+                let right = self.projection_rhs(Token::Flatten.power())?;
+                Ast::Projection(Box::new(left), Box::new(right))
+            }
+            Token::LBracket => match (self.peek(), self.peek_at(1)) {
+                (Token::Number(_) | Token::Colon, _) => {
+                    let index = self.index()?;
+                    self.project_if_slice(Ast::Identity, index)?
+                }
+                (Token::Star, Token::RBracket) => {
+                    self.advance();
+                    self.advance();
+                    let right = self.projection_rhs(Token::Star.power())?;
+                    Ast::Projection(Box::new(Ast::Identity), Box::new(right))
+                }
+                _ => self.multi_list()?,
+            },
+            Token::At => Ast::Identity,
+            Token::Ampersand => Ast::Expref(Box::new(self.expression(0)?)),
+            Token::Not => Ast::Not(Box::new(self.expression(Token::Not.power())?)),
+            Token::LParen => {
+                let ast = self.expression(0)?;
+                self.expect(Token::RParen, "expect `)`")?;
+                ast
+            }
+            Token::Eof => return Err(syntax("unexpected end of expression", offset)),
+            _ => return Err(syntax("unexpected token", offset)),
+        })
+    }
+
+    fn led(&mut self, left: Ast) -> Result<Ast, QueryError> {
+        let offset = self.tokens[self.pos].1;
+        let token = self.advance();
+        let power = token.power();
+        Ok(match token {
+            Token::Dot => {
+                if *self.peek() == Token::Star {
+                    self.advance();
+                    let right = self.projection_rhs(power)?;
+                    Ast::ValueProjection(Box::new(left), Box::new(right))
+                } else {
+                    let right = self.dot_rhs(power)?;
+                    Ast::Sub(Box::new(left), Box::new(right))
+                }
+            }
+            Token::Pipe => Ast::Pipe(Box::new(left), Box::new(self.expression(power)?)),
+            Token::Or => Ast::Or(Box::new(left), Box::new(self.expression(power)?)),
+            Token::And => Ast::And(Box::new(left), Box::new(self.expression(power)?)),
+            Token::Cmp(cmp) => Ast::Compare(cmp, Box::new(left), Box::new(self.expression(power)?)),
+            Token::LParen => {
+                let name = match left {
+                    Ast::Field(name) => name,
+                    _ => return Err(syntax("invalid function call", offset)),
+                };
+                self.call(&name, offset)?
+            }
+            Token::Filter => self.filter(left)?,
+            Token::Flatten => {
+                let left = Ast::Flatten(Box::new(left));
+                let right = self.projection_rhs(power)?;
+                Ast::Projection(Box::new(left), Box::new(right))
+            }
+            Token::LBracket => match self.peek() {
+                Token::Number(_) | Token::Colon => {
+                    let index = self.index()?;
+                    self.project_if_slice(left, index)?
+                }
+                _ => {
+                    self.expect(Token::Star, "expect an index, a slice or `*`")?;
+                    self.expect(Token::RBracket, "expect `]`")?;
+                    let right = self.projection_rhs(Token::Star.power())?;
+                    Ast::Projection(Box::new(left), Box::new(right))
+                }
+            },
+            _ => return Err(syntax("unexpected token", offset)),
+        })
+    }
+
+    /// the arguments of a call after `(`
+    fn call(&mut self, name: &str, offset: usize) -> Result<Ast, QueryError> {
+        let mut args = vec![];
+        while *self.peek() != Token::RParen {
+            args.push(self.expression(0)?);
+            if *self.peek() == Token::Comma {
+                self.advance();
+                if *self.peek() == Token::RParen {
+                    return Err(self.error("expect an argument"));
+                }
+            } else if *self.peek() != Token::RParen {
+                return Err(self.error("expect `,` or `)`"));
+            }
+        }
+        self.advance();
+        let (_, function, min, max) = FUNCTIONS
+            .iter()
+            .find(|(n, ..)| *n == name)
+            .ok_or_else(|| syntax(&format!("unknown-function: {}()", name), offset))?;
+        if args.len() < *min || max.is_some_and(|max| args.len() > max) {
+            return Err(syntax(
+                &format!("invalid-arity: wrong number of arguments for {}()", name),
+                offset,
+            ));
+        }
+        Ok(Ast::Call(*function, args))
+    }
+
+    /// after `[?`
+    fn filter(&mut self, left: Ast) -> Result<Ast, QueryError> {
+        let condition = self.expression(0)?;
+        self.expect(Token::RBracket, "expect `]`")?;
+        let right = match self.peek() {
+            Token::Flatten => Ast::Identity,
+            _ => self.projection_rhs(Token::Filter.power())?,
+        };
+        Ok(Ast::Filter(
+            Box::new(left),
+            Box::new(right),
+            Box::new(condition),
+        ))
+    }
+
+    /// an index or slice after `[`, including the `]`
+    fn index(&mut self) -> Result<Ast, QueryError> {
+        if let (Token::Number(n), Token::RBracket) = (self.peek().clone(), self.peek_at(1)) {
+            self.advance();
+            self.advance();
+            return Ok(Ast::Index(n));
+        }
+        let mut parts = [None, None, None];
+        let mut part = 0;
+        let mut step = 0;
+        loop {
+            match self.peek().clone() {
+                Token::Number(n) if parts[part].is_none() => {
+                    parts[part] = Some(n);
+                    step = self.tokens[self.pos].1;
+                }
+                Token::Colon if part < 2 => part += 1,
+                Token::RBracket => break,
+                _ => return Err(self.error("invalid slice")),
+            }
+            self.advance();
+        }
+        self.advance();
+        if parts[2] == Some(0) {
+            return Err(syntax("invalid-value: slice step can not be 0", step));
+        }
+        Ok(Ast::Slice(parts[0], parts[1], parts[2]))
+    }
+
+    fn project_if_slice(&mut self, left: Ast, index: Ast) -> Result<Ast, QueryError> {
+        let slice = matches!(index, Ast::Slice(..));
+        let ast = Ast::IndexOf(Box::new(left), Box::new(index));
+        if !slice {
+            return Ok(ast);
+        }
+        let right = self.projection_rhs(Token::Star.power())?;
+        Ok(Ast::Projection(Box::new(ast), Box::new(right)))
+    }
+
+    fn projection_rhs(&mut self, power: u8) -> Result<Ast, QueryError> {
+        match self.peek() {
+            token if token.power() < 10 => Ok(Ast::Identity),
+            Token::LBracket | Token::Filter => self.expression(power),
+            Token::Dot => {
+                self.advance();
+                self.dot_rhs(power)
+            }
+            _ => Err(self.error("unexpected token after a projection")),
+        }
+    }
+
+    fn dot_rhs(&mut self, power: u8) -> Result<Ast, QueryError> {
+        match self.peek() {
+            Token::Identifier(_) | Token::QuotedIdentifier(_) | Token::Star => {
+                self.expression(power)
+            }
+            Token::LBracket => {
+                self.advance();
+                self.multi_list()
+            }
+            Token::LBrace => {
+                self.advance();
+                self.multi_hash()
+            }
+            _ => Err(self.error("expect an identifier, `[` or `{` after `.`")),
+        }
+    }
+
+    /// after `[`
+    fn multi_list(&mut self) -> Result<Ast, QueryError> {
+        let mut items = vec![];
+        loop {
+            items.push(self.expression(0)?);
+            match self.peek() {
+                Token::Comma => self.advance(),
+                Token::RBracket => {
+                    self.advance();
+                    return Ok(Ast::MultiList(items));
+                }
+                _ => return Err(self.error("expect `,` or `]`")),
+            };
+        }
+    }
+
+    /// after `{`
+    fn multi_hash(&mut self) -> Result<Ast, QueryError> {
+        let mut entries = vec![];
+        loop {
+            let key = match self.peek() {
+                Token::Identifier(key) | Token::QuotedIdentifier(key) => key.clone(),
+                _ => return Err(self.error("expect a key")),
+            };
+            self.advance();
+            self.expect(Token::Colon, "expect `:`")?;
+            entries.push((key, self.expression(0)?));
+            match self.peek() {
+                Token::Comma => self.advance(),
+                Token::RBrace => {
+                    self.advance();
+                    return Ok(Ast::MultiHash(entries));
+                }
+                _ => return Err(self.error("expect `,` or `}`")),
+            };
+        }
+    }
+}
+
+/// false, null, empty strings, arrays and objects are false
+fn truthy(value: &JsonType) -> bool {
+    match value {
+        JsonType::Null | JsonType::Bool(false) => false,
+        JsonType::String(s) => !s.is_empty(),
+        JsonType::Array(arr) => !arr.is_empty(),
+        JsonType::Object(obj) => !obj.is_empty(),
+        _ => true,
+    }
+}
+
+fn eval(ast: &Ast, value: &JsonType) -> Result<JsonType, QueryError> {
+    Ok(match ast {
+        Ast::Identity => value.clone(),
+        Ast::Field(name) => match value {
+            JsonType::Object(obj) => obj.get(name).cloned().unwrap_or(JsonType::Null),
+            _ => JsonType::Null,
+        },
+        Ast::Sub(left, right) => eval(right, &eval(left, value)?)?,
+        Ast::Index(index) => match value {
+            JsonType::Array(arr) => {
+                let len = arr.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+                match (0..len).contains(&index) {
+                    true => arr[index as usize].clone(),
+                    false => JsonType::Null,
+                }
+            }
+            _ => JsonType::Null,
+        },
+        Ast::Slice(start, stop, step) => match value {
+            JsonType::Array(arr) => {
+                let indices = slice(arr.len() as i64, *start, *stop, step.unwrap_or(1));
+                JsonType::Array(indices.into_iter().map(|i| arr[i].clone()).collect())
+            }
+            _ => JsonType::Null,
+        },
+        Ast::IndexOf(left, index) => eval(index, &eval(left, value)?)?,
+        Ast::Projection(left, right) => match eval(left, value)? {
+            JsonType::Array(arr) => project(arr, right)?,
+            _ => JsonType::Null,
+        },
+        Ast::ValueProjection(left, right) => match eval(left, value)? {
+            JsonType::Object(obj) => project(obj.into_iter().map(|(_, v)| v), right)?,
+            _ => JsonType::Null,
+        },
+        Ast::Filter(left, right, condition) => match eval(left, value)? {
+            JsonType::Array(arr) => {
+                let mut passed = vec![];
+                for item in arr {
+                    if truthy(&eval(condition, &item)?) {
+                        passed.push(item);
+                    }
+                }
+                project(passed, right)?
+            }
+            _ => JsonType::Null,
+        },
+        Ast::Flatten(inner) => match eval(inner, value)? {
+            JsonType::Array(arr) => {
+                let mut flat = vec![];
+                for item in arr {
+                    match item {
+                        JsonType::Array(inner) => flat.extend(inner),
+                        other => flat.push(other),
+                    }
+                }
+                JsonType::Array(flat)
+            }
+            _ => JsonType::Null,
+        },
+        Ast::MultiList(items) => match value {
+            JsonType::Null => JsonType::Null,
+            _ => JsonType::Array(
+                items
+                    .iter()
+                    .map(|item| eval(item, value))
+                    .collect::<Result<_, _>>()?,
+            ),
+        },
+        Ast::MultiHash(entries) => match value {
+            JsonType::Null => JsonType::Null,
+            _ => {
+                let mut obj = JsonObject::new();
+                for (key, item) in entries {
+                    obj.inner_map.insert(key.clone(), eval(item, value)?);
+                }
+                JsonType::Object(obj)
+            }
+        },
+        Ast::Literal(literal) => literal.clone(),
+        Ast::Pipe(left, right) => eval(right, &eval(left, value)?)?,
+        Ast::Or(left, right) => {
+            let left = eval(left, value)?;
+            match truthy(&left) {
+                true => left,
+                false => eval(right, value)?,
+            }
+        }
+        Ast::And(left, right) => {
+            let left = eval(left, value)?;
+            match truthy(&left) {
+                true => eval(right, value)?,
+                false => left,
+            }
+        }
+        Ast::Not(inner) => JsonType::Bool(!truthy(&eval(inner, value)?)),
+        Ast::Compare(cmp, left, right) => compare(*cmp, &eval(left, value)?, &eval(right, value)?),
+        Ast::Call(function, args) => call(*function, args, value)?,
+        // an expression reference on its own has no value
+        Ast::Expref(_) => JsonType::Null,
+    })
+}
+
+/// right applied to each item, null results are dropped
+fn project<I: IntoIterator<Item = JsonType>>(
+    items: I,
+    right: &Ast,
+) -> Result<JsonType, QueryError> {
+    let mut projected = vec![];
+    for item in items {
+        let result = eval(right, &item)?;
+        if !result.is_null() {
+            projected.push(result);
+        }
+    }
+    Ok(JsonType::Array(projected))
+}
+
+/// python style slicing
+fn slice(len: i64, start: Option<i64>, stop: Option<i64>, step: i64) -> Vec<usize> {
+    let bound = |value: Option<i64>, default: i64| match value {
+        None => default,
+        Some(v) if v < 0 => (len + v).max(if step < 0 { -1 } else { 0 }),
+        Some(v) => v.min(if step < 0 { len - 1 } else { len }),
+    };
+    let mut indices = vec![];
+    if step > 0 {
+        let (mut i, stop) = (bound(start, 0), bound(stop, len));
+        while i < stop {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    } else {
+        let (mut i, stop) = (bound(start, len - 1), bound(stop, -1));
+        while i > stop {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    indices
+}
+
+fn compare(cmp: Cmp, left: &JsonType, right: &JsonType) -> JsonType {
+    let ordering = match (cmp, left, right) {
+        (Cmp::Eq, ..) => return JsonType::Bool(left == right),
+        (Cmp::Ne, ..) => return JsonType::Bool(left != right),
+        (_, JsonType::Number(a), JsonType::Number(b)) => a.partial_cmp(b),
+        _ => return JsonType::Null,
+    };
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        None => return JsonType::Bool(false),
+    };
+    JsonType::Bool(match cmp {
+        Cmp::Lt => ordering == Ordering::Less,
+        Cmp::Le => ordering != Ordering::Greater,
+        Cmp::Gt => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    })
+}
+
+fn invalid_type(function: Function, expected: &str, found: &JsonType) -> QueryError {
+    QueryError::Runtime {
+        message: format!(
+            "invalid-type: {}() expects {} but got {}",
+            function.name(),
+            expected,
+            found.type_name()
+        ),
+    }
+}
+
+fn call(function: Function, args: &[Ast], value: &JsonType) -> Result<JsonType, QueryError> {
+    use Function::*;
+    // expression references are kept as they are, everything else is evaluated
+    let expref = |i: usize| match &args[i] {
+        Ast::Expref(inner) => Ok(inner.as_ref()),
+        _ => Err(QueryError::Runtime {
+            message: format!(
+                "invalid-type: argument {} of {}() must be an expression reference",
+                i + 1,
+                function.name()
+            ),
+        }),
+    };
+    let mut values = vec![];
+    for (i, arg) in args.iter().enumerate() {
+        match (function, i, arg) {
+            (Map, 0, _) | (SortBy | MinBy | MaxBy, 1, _) => values.push(JsonType::Null),
+            (_, _, Ast::Expref(_)) => {
+                return Err(invalid_type(function, "a value", &JsonType::Null));
+            }
+            _ => values.push(eval(arg, value)?),
+        }
+    }
+    let number = |v: &JsonType| match v {
+        JsonType::Number(n) => Ok(*n),
+        other => Err(invalid_type(function, "a number", other)),
+    };
+    let string = |v: &JsonType| match v {
+        JsonType::String(s) => Ok(s.clone()),
+        other => Err(invalid_type(function, "a string", other)),
+    };
+    let array = |v: &JsonType| match v {
+        JsonType::Array(arr) => Ok(arr.clone()),
+        other => Err(invalid_type(function, "an array", other)),
+    };
+    let object = |v: &JsonType| match v {
+        JsonType::Object(obj) => Ok(obj.clone()),
+        other => Err(invalid_type(function, "an object", other)),
+    };
+    let numbers =
+        |v: &JsonType| -> Result<Vec<f64>, QueryError> { array(v)?.iter().map(number).collect() };
+    Ok(match function {
+        Abs => JsonType::Number(number(&values[0])?.abs()),
+        Ceil => JsonType::Number(number(&values[0])?.ceil()),
+        Floor => JsonType::Number(number(&values[0])?.floor()),
+        Avg => {
+            let numbers = numbers(&values[0])?;
+            match numbers.is_empty() {
+                true => JsonType::Null,
+                false => {
+                    JsonType::Number(numbers.iter().fold(0.0, |a, b| a + b) / numbers.len() as f64)
+                }
+            }
+        }
+        // `Iterator::sum` of no floats is `-0.0`, which is written as `-0`
+        Sum => JsonType::Number(numbers(&values[0])?.iter().fold(0.0, |a, b| a + b)),
+        Contains => match &values[0] {
+            JsonType::Array(arr) => JsonType::Bool(arr.contains(&values[1])),
+            JsonType::String(s) => JsonType::Bool(s.contains(string(&values[1])?.as_str())),
+            other => return Err(invalid_type(function, "an array or a string", other)),
+        },
+        StartsWith => JsonType::Bool(string(&values[0])?.starts_with(&string(&values[1])?)),
+        EndsWith => JsonType::Bool(string(&values[0])?.ends_with(&string(&values[1])?)),
+        Join => {
+            let glue = string(&values[0])?;
+            let parts: Vec<_> = array(&values[1])?
+                .iter()
+                .map(string)
+                .collect::<Result<_, _>>()?;
+            JsonType::String(parts.join(&glue))
+        }
+        Keys => JsonType::Array(
+            object(&values[0])?
+                .keys()
+                .map(|k| k.as_str().into())
+                .collect(),
+        ),
+        Values => JsonType::Array(object(&values[0])?.values().cloned().collect()),
+        Length => JsonType::Number(match &values[0] {
+            JsonType::String(s) => s.chars().count(),
+            JsonType::Array(arr) => arr.len(),
+            JsonType::Object(obj) => obj.len(),
+            other => return Err(invalid_type(function, "a string, array or object", other)),
+        } as f64),
+        Map => {
+            let expr = expref(0)?;
+            let items = array(&values[1])?;
+            JsonType::Array(
+                items
+                    .iter()
+                    .map(|item| eval(expr, item))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        Max | Min | Sort => {
+            let mut items = array(&values[0])?;
+            sort_values(function, &mut items, |item| item)?;
+            match function {
+                Sort => JsonType::Array(items),
+                Max => items.pop().unwrap_or(JsonType::Null),
+                _ => items.into_iter().next().unwrap_or(JsonType::Null),
+            }
+        }
+        MaxBy | MinBy | SortBy => {
+            let expr = expref(1)?;
+            let items = array(&values[0])?;
+            let mut keyed = vec![];
+            for item in items {
+                keyed.push((eval(expr, &item)?, item));
+            }
+            sort_values(function, &mut keyed, |(key, _)| key)?;
+            let mut items = keyed.into_iter().map(|(_, item)| item);
+            match function {
+                SortBy => JsonType::Array(items.collect()),
+                MaxBy => items.next_back().unwrap_or(JsonType::Null),
+                _ => items.next().unwrap_or(JsonType::Null),
+            }
+        }
+        Merge => {
+            let mut merged = JsonObject::new();
+            for value in &values {
+                for (k, v) in object(value)? {
+                    merged.inner_map.insert(k, v);
+                }
+            }
+            JsonType::Object(merged)
+        }
+        NotNull => values
+            .into_iter()
+            .find(|value| !value.is_null())
+            .unwrap_or(JsonType::Null),
+        Reverse => match &values[0] {
+            JsonType::String(s) => JsonType::String(s.chars().rev().collect()),
+            JsonType::Array(arr) => JsonType::Array(arr.iter().rev().cloned().collect()),
+            other => return Err(invalid_type(function, "an array or a string", other)),
+        },
+        ToArray => match &values[0] {
+            JsonType::Array(_) => values[0].clone(),
+            other => JsonType::Array(vec![other.clone()]),
+        },
+        ToString => match &values[0] {
+            JsonType::String(_) => values[0].clone(),
+            other => JsonType::String(other.serialize()),
+        },
+        ToNumber => match &values[0] {
+            JsonType::Number(_) => values[0].clone(),
+            JsonType::String(s) => match try_parse(s) {
+                Ok(JsonType::Number(n)) => JsonType::Number(n),
+                _ => JsonType::Null,
+            },
+            _ => JsonType::Null,
+        },
+        Type => JsonType::String(values[0].type_name().to_string()),
+    })
+}
+
+/// stable sort by a key that must be all numbers or all strings
+fn sort_values<T, F: Fn(&T) -> &JsonType>(
+    function: Function,
+    items: &mut [T],
+    key: F,
+) -> Result<(), QueryError> {
+    let numbers = items.iter().all(|item| key(item).is_number());
+    let strings = items.iter().all(|item| key(item).is_string());
+    if !numbers && !strings {
+        let found = items
+            .iter()
+            .map(&key)
+            .find(|key| !key.is_number() && !key.is_string())
+            .or_else(|| items.last().map(&key))
+            .unwrap_or(&JsonType::Null);
+        return Err(invalid_type(
+            function,
+            "only numbers or only strings",
+            found,
+        ));
+    }
+    items.sort_by(|a, b| match (key(a), key(b)) {
+        (JsonType::Number(a), JsonType::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (JsonType::String(a), JsonType::String(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::JmesPath;
+    use crate::{parse, QueryError};
+
+    #[test]
+    fn jmespath_errors() {
+        let json = parse(r#"{ "a": [1, "x"], "s": "str" }"#);
+        let syntax = |expr: &str| match JmesPath::compile(expr) {
+            Err(QueryError::Syntax { offset, .. }) => offset,
+            other => panic!("{}: {:?}", expr, other),
+        };
+        assert_eq!(syntax("a[?b"), 4);
+        assert_eq!(syntax("a.[0]"), 3);
+        assert_eq!(syntax("nope(a)"), 4);
+        assert_eq!(syntax("length(a, s)"), 6);
+        assert_eq!(syntax("a[::0]"), 4);
+        let runtime = |expr: &str| match JmesPath::compile(expr).unwrap().search(&json) {
+            Err(QueryError::Runtime { message }) => message,
+            other => panic!("{}: {:?}", expr, other),
+        };
+        assert_eq!(
+            runtime("sum(a)"),
+            "invalid-type: sum() expects a number but got string"
+        );
+        assert!(runtime("sort_by(a, &@)").starts_with("invalid-type"));
+        assert!(runtime("length(`1`)").starts_with("invalid-type"));
+    }
+
+    #[test]
+    fn extreme_slice_steps() {
+        let json = parse("[0, 1, 2]");
+        for (expr, expected) in [
+            ("[1::9223372036854775807]", "[1]"),
+            ("[-1::-9223372036854775808]", "[2]"),
+            ("[::-9223372036854775807]", "[2]"),
+        ] {
+            let found = JmesPath::compile(expr).unwrap().search(&json).unwrap();
+            assert_eq!(found.serialize(), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn empty_sums() {
+        let json = parse(r#"{ "empty": [] }"#);
+        for (expr, expected) in [
+            ("sum(`[]`)", "0"),
+            ("sum(empty)", "0"),
+            ("avg(empty)", "null"),
+        ] {
+            let found = JmesPath::compile(expr).unwrap().search(&json).unwrap();
+            assert_eq!(found.serialize(), expected, "{}", expr);
+        }
+    }
+}
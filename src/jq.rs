@@ -0,0 +1,1916 @@
+use std::cmp::Ordering;
+
+use crate::error::QueryError;
+use crate::{try_parse, JsonObject, JsonType};
+
+/// a filter of a jq compatible language, compiled once and applied to any
+/// number of values
+///
+/// the language covers paths like `.a[0]`, `.[]` and `..`, pipes, `,`,
+/// array and object construction, arithmetic, comparisons, `and`/`or`/`//`,
+/// `if`, `try`, `reduce`, `as $var` bindings, string interpolation and the
+/// common builtins such as `map`, `select`, `sort_by`, `group_by`, `keys`,
+/// `length` and `to_entries`
+///
+/// ```
+/// use dynamic_json::{parse, JqFilter};
+/// let json = parse(r#"{ "items": [{ "id": 2, "title": "b" }, { "id": 1, "title": "a" }] }"#);
+/// let filter = JqFilter::compile(".items | map({id, name: .title}) | sort_by(.id)").unwrap();
+/// let out = filter.apply(&json).unwrap();
+/// assert_eq!(out[0].serialize(), r#"[{"id":1,"name":"a"},{"id":2,"name":"b"}]"#);
+///
+/// let filter = JqFilter::compile(r#".items[] | "\(.id): \(.title)""#).unwrap();
+/// let lines: Vec<_> = filter.apply(&json).unwrap().into_iter().map(|v| v.serialize()).collect();
+/// assert_eq!(lines, [r#""2: b""#, r#""1: a""#]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JqFilter {
+    ast: Ast,
+}
+
+#[derive(Debug, Clone)]
+enum Ast {
+    Identity,
+    /// `..`
+    Recurse,
+    Literal(JsonType),
+    /// a string with `\(..)` parts
+    Format(Vec<Part>),
+    /// `t[k]`, `t.k`, the key is evaluated against the input of `t`
+    Index(Box<Ast>, Box<Ast>),
+    Slice(Box<Ast>, Option<Box<Ast>>, Option<Box<Ast>>),
+    /// `t[]`
+    Iterate(Box<Ast>),
+    Array(Option<Box<Ast>>),
+    Object(Vec<(Ast, Ast)>),
+    Neg(Box<Ast>),
+    Pipe(Box<Ast>, Box<Ast>),
+    Comma(Box<Ast>, Box<Ast>),
+    Binary(BinOp, Box<Ast>, Box<Ast>),
+    And(Box<Ast>, Box<Ast>),
+    Or(Box<Ast>, Box<Ast>),
+    /// `a // b`
+    Alt(Box<Ast>, Box<Ast>),
+    If(Vec<(Ast, Ast)>, Option<Box<Ast>>),
+    Try(Box<Ast>, Option<Box<Ast>>),
+    /// `source as $name | body`
+    Bind(Box<Ast>, String, Box<Ast>),
+    /// `reduce source as $name (init; update)`
+    Reduce(Box<Ast>, String, Box<Ast>, Box<Ast>),
+    Var(String),
+    Call(Builtin, Vec<Ast>),
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Value(Ast),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// the builtins with the number of arguments each takes
+const BUILTINS: &[(&str, usize)] = &[
+    ("empty", 0),
+    ("error", 0),
+    ("error", 1),
+    ("not", 0),
+    ("length", 0),
+    ("keys", 0),
+    ("keys_unsorted", 0),
+    ("has", 1),
+    ("type", 0),
+    ("map", 1),
+    ("map_values", 1),
+    ("select", 1),
+    ("values", 0),
+    ("recurse", 0),
+    ("to_entries", 0),
+    ("from_entries", 0),
+    ("with_entries", 1),
+    ("add", 0),
+    ("any", 0),
+    ("any", 1),
+    ("all", 0),
+    ("all", 1),
+    ("range", 1),
+    ("range", 2),
+    ("sort", 0),
+    ("sort_by", 1),
+    ("group_by", 1),
+    ("unique", 0),
+    ("unique_by", 1),
+    ("min", 0),
+    ("max", 0),
+    ("min_by", 1),
+    ("max_by", 1),
+    ("reverse", 0),
+    ("flatten", 0),
+    ("flatten", 1),
+    ("first", 0),
+    ("last", 0),
+    ("first", 1),
+    ("last", 1),
+    ("limit", 2),
+    ("contains", 1),
+    ("tostring", 0),
+    ("tonumber", 0),
+    ("tojson", 0),
+    ("fromjson", 0),
+    ("ascii_downcase", 0),
+    ("ascii_upcase", 0),
+    ("startswith", 1),
+    ("endswith", 1),
+    ("ltrimstr", 1),
+    ("rtrimstr", 1),
+    ("split", 1),
+    ("join", 1),
+    ("floor", 0),
+    ("sqrt", 0),
+    ("abs", 0),
+];
+
+/// index into `BUILTINS`, resolved when the filter is compiled
+#[derive(Debug, Clone, Copy)]
+struct Builtin(usize);
+
+impl Builtin {
+    fn name(self) -> &'static str {
+        BUILTINS[self.0].0
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "if", "then", "elif", "else", "end", "as", "and", "or", "reduce", "foreach", "try", "catch",
+    "label", "def", "import", "include", "__loc__",
+];
+
+/// a raised error carries any value, `try ... catch` receives it
+struct Raise(JsonType);
+
+impl Raise {
+    fn new(message: String) -> Raise {
+        Raise(JsonType::String(message))
+    }
+}
+
+impl From<Raise> for QueryError {
+    fn from(raise: Raise) -> Self {
+        let message = match raise.0 {
+            JsonType::String(message) => message,
+            value => format!("{} (not a string)", value.serialize()),
+        };
+        QueryError::Runtime { message }
+    }
+}
+
+/// receives the outputs of a filter one at a time, so a stream can end early
+type Out<'o> = dyn FnMut(JsonType) -> Result<(), Raise> + 'o;
+
+/// longest string `string * number` may build, in bytes
+const MAX_REPEAT_LEN: usize = 1 << 28;
+
+/// variables in scope, innermost first
+struct Env<'a> {
+    name: &'a str,
+    value: JsonType,
+    parent: Option<&'a Env<'a>>,
+}
+
+fn lookup<'a>(env: Option<&'a Env<'a>>, name: &str) -> Result<&'a JsonType, Raise> {
+    let mut env = env;
+    while let Some(scope) = env {
+        if scope.name == name {
+            return Ok(&scope.value);
+        }
+        env = scope.parent;
+    }
+    // the parser only accepts variables that are bound
+    Err(Raise::new(format!("${} is not defined", name)))
+}
+
+impl JqFilter {
+    pub fn compile(filter: &str) -> Result<JqFilter, QueryError> {
+        let mut parser = Parser {
+            src: filter,
+            pos: 0,
+            vars: vec![],
+            depth: 0,
+        };
+        parser.space();
+        let ast = parser.pipe()?;
+        if parser.pos != filter.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(JqFilter { ast })
+    }
+
+    /// every output of the filter for `input`, the first error ends the stream
+    pub fn apply(&self, input: &JsonType) -> Result<Vec<JsonType>, QueryError> {
+        Ok(collect(&self.ast, input, None)?)
+    }
+}
+
+fn collect(ast: &Ast, input: &JsonType, env: Option<&Env>) -> Result<Vec<JsonType>, Raise> {
+    let mut values = vec![];
+    eval(ast, input, env, &mut |value| {
+        values.push(value);
+        Ok(())
+    })?;
+    Ok(values)
+}
+
+/// outputs are passed to `out` as they are produced, an error from `out`
+/// ends the evaluation
+fn eval(ast: &Ast, input: &JsonType, env: Option<&Env>, out: &mut Out) -> Result<(), Raise> {
+    match ast {
+        Ast::Identity => out(input.clone())?,
+        Ast::Recurse => recurse(input, out)?,
+        Ast::Literal(value) => out(value.clone())?,
+        Ast::Format(parts) => {
+            let mut strings = vec![String::new()];
+            for part in parts {
+                match part {
+                    Part::Text(text) => strings.iter_mut().for_each(|s| s.push_str(text)),
+                    Part::Value(ast) => {
+                        let values = collect(ast, input, env)?;
+                        strings = values
+                            .iter()
+                            .flat_map(|value| {
+                                let value = to_string(value);
+                                strings.iter().map(move |s| format!("{}{}", s, value))
+                            })
+                            .collect();
+                    }
+                }
+            }
+            for s in strings {
+                out(JsonType::String(s))?;
+            }
+        }
+        Ast::Index(target, key) => {
+            let keys = collect(key, input, env)?;
+            eval(target, input, env, &mut |target| {
+                for key in &keys {
+                    out(index(&target, key)?)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ast::Slice(target, from, to) => {
+            let bound = |ast: &Option<Box<Ast>>| match ast {
+                Some(ast) => collect(ast, input, env),
+                None => Ok(vec![JsonType::Null]),
+            };
+            let (froms, tos) = (bound(from)?, bound(to)?);
+            eval(target, input, env, &mut |target| {
+                for to in &tos {
+                    for from in &froms {
+                        out(slice(&target, from, to)?)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ast::Iterate(target) => {
+            eval(target, input, env, &mut |target| match target {
+                JsonType::Array(arr) => arr.into_iter().try_for_each(&mut *out),
+                JsonType::Object(obj) => obj.into_iter().try_for_each(|(_, v)| out(v)),
+                other => Err(Raise::new(format!(
+                    "Cannot iterate over {}",
+                    describe(&other)
+                ))),
+            })?;
+        }
+        Ast::Array(None) => out(JsonType::Array(vec![]))?,
+        Ast::Array(Some(body)) => out(JsonType::Array(collect(body, input, env)?))?,
+        Ast::Object(entries) => {
+            let mut objects = vec![JsonObject::new()];
+            for (key, value) in entries {
+                let keys = collect(key, input, env)?;
+                let values = collect(value, input, env)?;
+                let mut next = vec![];
+                for obj in &objects {
+                    for key in &keys {
+                        let key = match key {
+                            JsonType::String(key) => key,
+                            other => {
+                                return Err(Raise::new(format!(
+                                    "Object keys must be strings, not {}",
+                                    other.type_name()
+                                )))
+                            }
+                        };
+                        for value in &values {
+                            let mut obj = obj.clone();
+                            obj.inner_map.insert(key.clone(), value.clone());
+                            next.push(obj);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            for obj in objects {
+                out(JsonType::Object(obj))?;
+            }
+        }
+        Ast::Neg(body) => {
+            eval(body, input, env, &mut |value| match value {
+                JsonType::Number(n) => out(JsonType::Number(-n)),
+                other => Err(Raise::new(format!(
+                    "{} cannot be negated",
+                    describe(&other)
+                ))),
+            })?;
+        }
+        Ast::Pipe(left, right) => {
+            eval(left, input, env, &mut |value| eval(right, &value, env, out))?;
+        }
+        Ast::Comma(left, right) => {
+            eval(left, input, env, out)?;
+            eval(right, input, env, out)?;
+        }
+        Ast::Binary(op, left, right) => {
+            let rights = collect(right, input, env)?;
+            let lefts = collect(left, input, env)?;
+            for r in &rights {
+                for l in &lefts {
+                    out(binary(*op, l, r)?)?;
+                }
+            }
+        }
+        Ast::And(left, right) | Ast::Or(left, right) => {
+            let or = matches!(ast, Ast::Or(..));
+            for l in collect(left, input, env)? {
+                if truthy(&l) == or {
+                    out(JsonType::Bool(or))?;
+                    continue;
+                }
+                for r in collect(right, input, env)? {
+                    out(JsonType::Bool(truthy(&r)))?;
+                }
+            }
+        }
+        Ast::Alt(left, right) => {
+            // errors of `left` are ignored, the outputs before them count
+            let mut values = vec![];
+            let _ = eval(left, input, env, &mut |value| {
+                values.push(value);
+                Ok(())
+            });
+            let values: Vec<_> = values.into_iter().filter(truthy).collect();
+            if values.is_empty() {
+                eval(right, input, env, out)?;
+            }
+            for value in values {
+                out(value)?;
+            }
+        }
+        Ast::If(branches, otherwise) => {
+            let (cond, then) = &branches[0];
+            for c in collect(cond, input, env)? {
+                if truthy(&c) {
+                    eval(then, input, env, out)?;
+                } else if branches.len() > 1 {
+                    let rest = Ast::If(branches[1..].to_vec(), otherwise.clone());
+                    eval(&rest, input, env, out)?;
+                } else if let Some(otherwise) = otherwise {
+                    eval(otherwise, input, env, out)?;
+                } else {
+                    out(input.clone())?;
+                }
+            }
+        }
+        Ast::Try(body, catch) => {
+            // the body is collected first, so errors raised by `out` are not caught
+            let mut values = vec![];
+            let result = eval(body, input, env, &mut |value| {
+                values.push(value);
+                Ok(())
+            });
+            for value in values {
+                out(value)?;
+            }
+            if let Err(Raise(error)) = result {
+                if let Some(catch) = catch {
+                    eval(catch, &error, env, out)?;
+                }
+            }
+        }
+        Ast::Bind(source, name, body) => {
+            eval(source, input, env, &mut |value| {
+                let scope = Env {
+                    name,
+                    value,
+                    parent: env,
+                };
+                eval(body, input, Some(&scope), out)
+            })?;
+        }
+        Ast::Reduce(source, name, init, update) => {
+            let items = collect(source, input, env)?;
+            for mut acc in collect(init, input, env)? {
+                for item in &items {
+                    let scope = Env {
+                        name,
+                        value: item.clone(),
+                        parent: env,
+                    };
+                    acc = collect(update, &acc, Some(&scope))?
+                        .pop()
+                        .unwrap_or(JsonType::Null);
+                }
+                out(acc)?;
+            }
+        }
+        Ast::Var(name) => out(lookup(env, name)?.clone())?,
+        Ast::Call(builtin, args) => call(*builtin, args, input, env, out)?,
+    }
+    Ok(())
+}
+
+fn recurse(value: &JsonType, out: &mut Out) -> Result<(), Raise> {
+    out(value.clone())?;
+    match value {
+        JsonType::Array(arr) => arr.iter().try_for_each(|v| recurse(v, out)),
+        JsonType::Object(obj) => obj.values().try_for_each(|v| recurse(v, out)),
+        _ => Ok(()),
+    }
+}
+
+fn truthy(value: &JsonType) -> bool {
+    !matches!(value, JsonType::Null | JsonType::Bool(false))
+}
+
+/// `number (1)`, the way jq names a value in errors
+fn describe(value: &JsonType) -> String {
+    let mut text = value.serialize();
+    if text.len() > 11 {
+        let end = (0..=10)
+            .rev()
+            .find(|i| text.is_char_boundary(*i))
+            .unwrap_or(0);
+        text.truncate(end);
+        text.push_str("...");
+    }
+    format!("{} ({})", value.type_name(), text)
+}
+
+fn to_string(value: &JsonType) -> String {
+    match value {
+        JsonType::String(s) => s.clone(),
+        other => other.serialize(),
+    }
+}
+
+fn index(target: &JsonType, key: &JsonType) -> Result<JsonType, Raise> {
+    Ok(match (target, key) {
+        (JsonType::Object(obj), JsonType::String(key)) => {
+            obj.get(key).cloned().unwrap_or(JsonType::Null)
+        }
+        (JsonType::Array(arr), JsonType::Number(n)) => {
+            let n = n.floor();
+            let i = if n < 0.0 { arr.len() as f64 + n } else { n };
+            match i >= 0.0 {
+                true => arr.get(i as usize).cloned().unwrap_or(JsonType::Null),
+                false => JsonType::Null,
+            }
+        }
+        (JsonType::Null, JsonType::String(_) | JsonType::Number(_)) => JsonType::Null,
+        (target, JsonType::String(key)) => {
+            return Err(Raise::new(format!(
+                "Cannot index {} with \"{}\"",
+                target.type_name(),
+                key
+            )))
+        }
+        (target, key) => {
+            return Err(Raise::new(format!(
+                "Cannot index {} with {}",
+                target.type_name(),
+                key.type_name()
+            )))
+        }
+    })
+}
+
+fn slice(target: &JsonType, from: &JsonType, to: &JsonType) -> Result<JsonType, Raise> {
+    let len = match target {
+        JsonType::Null => return Ok(JsonType::Null),
+        JsonType::Array(arr) => arr.len(),
+        JsonType::String(s) => s.chars().count(),
+        other => {
+            return Err(Raise::new(format!(
+                "Cannot index {} with object",
+                other.type_name()
+            )))
+        }
+    };
+    let bound = |value: &JsonType, default: usize| match value {
+        JsonType::Null => Ok(default),
+        JsonType::Number(n) => {
+            let n = if *n < 0.0 { len as f64 + n } else { *n };
+            Ok(n.floor().clamp(0.0, len as f64) as usize)
+        }
+        _ => Err(Raise::new(
+            "Start and end indices of an array slice must be numbers".into(),
+        )),
+    };
+    let from = bound(from, 0)?;
+    let to = bound(to, len)?.max(from);
+    Ok(match target {
+        JsonType::Array(arr) => JsonType::Array(arr[from..to].to_vec()),
+        JsonType::String(s) => JsonType::String(s.chars().skip(from).take(to - from).collect()),
+        _ => JsonType::Null,
+    })
+}
+
+fn binary(op: BinOp, l: &JsonType, r: &JsonType) -> Result<JsonType, Raise> {
+    use JsonType::*;
+    let ordering = || compare(l, r);
+    Ok(match (op, l, r) {
+        (BinOp::Eq, ..) => Bool(ordering() == Ordering::Equal),
+        (BinOp::Ne, ..) => Bool(ordering() != Ordering::Equal),
+        (BinOp::Lt, ..) => Bool(ordering() == Ordering::Less),
+        (BinOp::Le, ..) => Bool(ordering() != Ordering::Greater),
+        (BinOp::Gt, ..) => Bool(ordering() == Ordering::Greater),
+        (BinOp::Ge, ..) => Bool(ordering() != Ordering::Less),
+        (BinOp::Add, Null, other) | (BinOp::Add, other, Null) => other.clone(),
+        (BinOp::Add, Number(a), Number(b)) => Number(a + b),
+        (BinOp::Add, String(a), String(b)) => String(format!("{}{}", a, b)),
+        (BinOp::Add, Array(a), Array(b)) => Array(a.iter().chain(b).cloned().collect()),
+        (BinOp::Add, Object(a), Object(b)) => {
+            let mut obj = a.clone();
+            for (k, v) in b {
+                obj.inner_map.insert(k.clone(), v.clone());
+            }
+            Object(obj)
+        }
+        (BinOp::Sub, Number(a), Number(b)) => Number(a - b),
+        (BinOp::Sub, Array(a), Array(b)) => {
+            Array(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+        }
+        (BinOp::Mul, Number(a), Number(b)) => Number(a * b),
+        (BinOp::Mul, String(s), Number(n)) | (BinOp::Mul, Number(n), String(s)) => {
+            if *n <= 0.0 {
+                Null
+            } else if s.len() as f64 * n.ceil() > MAX_REPEAT_LEN as f64 {
+                return Err(Raise::new("Repeat string result too long".into()));
+            } else {
+                String(s.repeat(n.ceil() as usize))
+            }
+        }
+        (BinOp::Mul, Object(a), Object(b)) => Object(deep_merge(a, b)),
+        (BinOp::Div, Number(_), Number(b)) | (BinOp::Rem, Number(_), Number(b))
+            if *b == 0.0 || op == BinOp::Rem && b.trunc() == 0.0 =>
+        {
+            return Err(Raise::new(format!(
+                "{} and {} cannot be divided because the divisor is zero",
+                describe(l),
+                describe(r)
+            )))
+        }
+        (BinOp::Div, Number(a), Number(b)) => Number(a / b),
+        (BinOp::Rem, Number(a), Number(b)) => {
+            Number((a.trunc() as i64).wrapping_rem(b.trunc() as i64) as f64)
+        }
+        (BinOp::Div, String(a), String(b)) => Array(split(a, b)),
+        _ => {
+            let verb = match op {
+                BinOp::Add => "added",
+                BinOp::Sub => "subtracted",
+                BinOp::Mul => "multiplied",
+                _ => "divided",
+            };
+            return Err(Raise::new(format!(
+                "{} and {} cannot be {}",
+                describe(l),
+                describe(r),
+                verb
+            )));
+        }
+    })
+}
+
+fn deep_merge(a: &JsonObject, b: &JsonObject) -> JsonObject {
+    let mut obj = a.clone();
+    for (k, v) in b {
+        let merged = match (obj.get(k), v) {
+            (Some(JsonType::Object(a)), JsonType::Object(b)) => JsonType::Object(deep_merge(a, b)),
+            _ => v.clone(),
+        };
+        obj.inner_map.insert(k.clone(), merged);
+    }
+    obj
+}
+
+fn split(s: &str, sep: &str) -> Vec<JsonType> {
+    if s.is_empty() {
+        return vec![];
+    }
+    if sep.is_empty() {
+        return s.chars().map(|c| JsonType::String(c.to_string())).collect();
+    }
+    s.split(sep)
+        .map(|part| JsonType::String(part.to_string()))
+        .collect()
+}
+
+/// jq's total order: null, false, true, numbers, strings, arrays, objects
+fn compare(a: &JsonType, b: &JsonType) -> Ordering {
+    fn rank(value: &JsonType) -> u8 {
+        match value {
+            JsonType::Null => 0,
+            JsonType::Bool(false) => 1,
+            JsonType::Bool(true) => 2,
+            JsonType::Number(_) => 3,
+            JsonType::String(_) => 4,
+            JsonType::Array(_) => 5,
+            JsonType::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (JsonType::Number(a), JsonType::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (JsonType::String(a), JsonType::String(b)) => a.cmp(b),
+        (JsonType::Array(a), JsonType::Array(b)) => {
+            for (a, b) in a.iter().zip(b) {
+                let ordering = compare(a, b);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        (JsonType::Object(a), JsonType::Object(b)) => {
+            let (keys_a, keys_b) = (sorted_keys(a), sorted_keys(b));
+            keys_a.cmp(&keys_b).then_with(|| {
+                for key in keys_a {
+                    let ordering = compare(
+                        a.get(key).unwrap_or(&JsonType::Null),
+                        b.get(key).unwrap_or(&JsonType::Null),
+                    );
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                Ordering::Equal
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn sorted_keys(obj: &JsonObject) -> Vec<&String> {
+    let mut keys: Vec<_> = obj.keys().collect();
+    keys.sort();
+    keys
+}
+
+/// `f` applied to each element, the outputs collected as a sort key
+fn keyed(
+    f: &Ast,
+    items: Vec<JsonType>,
+    env: Option<&Env>,
+) -> Result<Vec<(JsonType, JsonType)>, Raise> {
+    let mut keyed = vec![];
+    for item in items {
+        let key = JsonType::Array(collect(f, &item, env)?);
+        keyed.push((key, item));
+    }
+    keyed.sort_by(|a, b| compare(&a.0, &b.0));
+    Ok(keyed)
+}
+
+fn expect_array(name: &str, value: &JsonType) -> Result<Vec<JsonType>, Raise> {
+    match value {
+        JsonType::Array(arr) => Ok(arr.clone()),
+        other => Err(Raise::new(format!(
+            "{} cannot be used with {}",
+            describe(other),
+            name
+        ))),
+    }
+}
+
+fn values(value: &JsonType) -> Result<Vec<JsonType>, Raise> {
+    match value {
+        JsonType::Array(arr) => Ok(arr.clone()),
+        JsonType::Object(obj) => Ok(obj.values().cloned().collect()),
+        other => Err(Raise::new(format!(
+            "Cannot iterate over {}",
+            describe(other)
+        ))),
+    }
+}
+
+fn call(
+    builtin: Builtin,
+    args: &[Ast],
+    input: &JsonType,
+    env: Option<&Env>,
+    out: &mut Out,
+) -> Result<(), Raise> {
+    let name = builtin.name();
+    let value = match (name, args) {
+        ("empty", _) => return Ok(()),
+        ("error", []) => return Err(Raise(input.clone())),
+        ("error", [message]) => {
+            let message = collect(message, input, env)?;
+            return Err(Raise(message.into_iter().next().unwrap_or(JsonType::Null)));
+        }
+        ("not", _) => JsonType::Bool(!truthy(input)),
+        ("length", _) => JsonType::Number(match input {
+            JsonType::Null => 0.0,
+            JsonType::Number(n) => n.abs(),
+            JsonType::String(s) => s.chars().count() as f64,
+            JsonType::Array(arr) => arr.len() as f64,
+            JsonType::Object(obj) => obj.len() as f64,
+            other => return Err(Raise::new(format!("{} has no length", describe(other)))),
+        }),
+        ("keys" | "keys_unsorted", _) => match input {
+            JsonType::Object(obj) => {
+                let keys: Vec<_> = match name {
+                    "keys" => sorted_keys(obj),
+                    _ => obj.keys().collect(),
+                };
+                JsonType::Array(keys.into_iter().map(|k| k.as_str().into()).collect())
+            }
+            JsonType::Array(arr) => {
+                JsonType::Array((0..arr.len()).map(|i| (i as f64).into()).collect())
+            }
+            other => return Err(Raise::new(format!("{} has no keys", describe(other)))),
+        },
+        ("has", [key]) => {
+            for key in collect(key, input, env)? {
+                let has = match (input, &key) {
+                    (JsonType::Object(obj), JsonType::String(key)) => obj.contains(key),
+                    (JsonType::Array(arr), JsonType::Number(n)) => {
+                        *n >= 0.0 && (*n as usize) < arr.len()
+                    }
+                    _ => {
+                        return Err(Raise::new(format!(
+                            "Cannot check whether {} has a {} key",
+                            input.type_name(),
+                            key.type_name()
+                        )))
+                    }
+                };
+                out(JsonType::Bool(has))?;
+            }
+            return Ok(());
+        }
+        ("type", _) => input.type_name().into(),
+        ("map", [f]) => {
+            let mut mapped = vec![];
+            for item in values(input)? {
+                mapped.extend(collect(f, &item, env)?);
+            }
+            JsonType::Array(mapped)
+        }
+        ("map_values", [f]) => match input {
+            JsonType::Object(obj) => {
+                let mut mapped = JsonObject::new();
+                for (k, v) in obj {
+                    if let Some(v) = collect(f, v, env)?.into_iter().next() {
+                        mapped.inner_map.insert(k.clone(), v);
+                    }
+                }
+                JsonType::Object(mapped)
+            }
+            other => {
+                let mut mapped = vec![];
+                for item in values(other)? {
+                    mapped.extend(collect(f, &item, env)?.into_iter().next());
+                }
+                JsonType::Array(mapped)
+            }
+        },
+        ("select", [f]) => {
+            for c in collect(f, input, env)? {
+                if truthy(&c) {
+                    out(input.clone())?;
+                }
+            }
+            return Ok(());
+        }
+        ("values", _) => {
+            if !input.is_null() {
+                out(input.clone())?;
+            }
+            return Ok(());
+        }
+        ("recurse", _) => return recurse(input, out),
+        ("to_entries", _) => match input {
+            JsonType::Object(obj) => JsonType::Array(
+                obj.iter()
+                    .map(|(k, v)| {
+                        let mut entry = JsonObject::new();
+                        entry.inner_map.insert("key".to_string(), k.as_str().into());
+                        entry.inner_map.insert("value".to_string(), v.clone());
+                        JsonType::Object(entry)
+                    })
+                    .collect(),
+            ),
+            other => return Err(Raise::new(format!("{} has no keys", describe(other)))),
+        },
+        ("from_entries", _) => from_entries(&expect_array(name, input)?)?,
+        ("with_entries", [f]) => {
+            let entries = match call_simple("to_entries", input)? {
+                JsonType::Array(entries) => entries,
+                _ => vec![],
+            };
+            let mut mapped = vec![];
+            for entry in &entries {
+                mapped.extend(collect(f, entry, env)?);
+            }
+            from_entries(&mapped)?
+        }
+        ("add", _) => {
+            let mut sum = JsonType::Null;
+            for item in values(input)? {
+                sum = binary(BinOp::Add, &sum, &item)?;
+            }
+            sum
+        }
+        ("any" | "all", _) => {
+            let items = values(input)?;
+            let mut results = vec![];
+            for item in &items {
+                match args {
+                    [f] => results.extend(collect(f, item, env)?.iter().map(truthy)),
+                    _ => results.push(truthy(item)),
+                }
+            }
+            JsonType::Bool(match name {
+                "any" => results.into_iter().any(|b| b),
+                _ => results.into_iter().all(|b| b),
+            })
+        }
+        ("range", _) => {
+            let (froms, tos) = match args {
+                [to] => (vec![JsonType::Number(0.0)], collect(to, input, env)?),
+                [from, to] => (collect(from, input, env)?, collect(to, input, env)?),
+                _ => unreachable!(),
+            };
+            for from in &froms {
+                for to in &tos {
+                    match (from, to) {
+                        (JsonType::Number(from), JsonType::Number(to)) => {
+                            let mut i = *from;
+                            while i < *to {
+                                out(JsonType::Number(i))?;
+                                i += 1.0;
+                            }
+                        }
+                        _ => return Err(Raise::new("Range bounds must be numeric".into())),
+                    }
+                }
+            }
+            return Ok(());
+        }
+        ("sort", _) => {
+            let mut items = expect_array(name, input)?;
+            items.sort_by(compare);
+            JsonType::Array(items)
+        }
+        ("sort_by", [f]) => {
+            let items = keyed(f, expect_array(name, input)?, env)?;
+            JsonType::Array(items.into_iter().map(|(_, item)| item).collect())
+        }
+        ("group_by" | "unique_by", [f]) => {
+            let mut groups: Vec<(JsonType, Vec<JsonType>)> = vec![];
+            for (key, item) in keyed(f, expect_array(name, input)?, env)? {
+                match groups.last_mut() {
+                    Some((last, group)) if compare(last, &key) == Ordering::Equal => {
+                        group.push(item)
+                    }
+                    _ => groups.push((key, vec![item])),
+                }
+            }
+            JsonType::Array(
+                groups
+                    .into_iter()
+                    .map(|(_, mut group)| match name {
+                        "group_by" => JsonType::Array(group),
+                        _ => group.swap_remove(0),
+                    })
+                    .collect(),
+            )
+        }
+        ("unique", _) => {
+            let mut items = expect_array(name, input)?;
+            items.sort_by(compare);
+            items.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+            JsonType::Array(items)
+        }
+        ("min" | "max", _) => {
+            let items = expect_array(name, input)?;
+            let found = match name {
+                "min" => items.into_iter().min_by(compare),
+                _ => items.into_iter().rev().max_by(compare),
+            };
+            found.unwrap_or(JsonType::Null)
+        }
+        ("min_by" | "max_by", [f]) => {
+            let items = keyed(f, expect_array(name, input)?, env)?;
+            let found = match name {
+                "min_by" => items.into_iter().next(),
+                _ => items.into_iter().last(),
+            };
+            found.map(|(_, item)| item).unwrap_or(JsonType::Null)
+        }
+        ("reverse", _) => match input {
+            JsonType::Null => JsonType::Array(vec![]),
+            JsonType::String(s) => JsonType::String(s.chars().rev().collect()),
+            other => {
+                let mut items = expect_array(name, other)?;
+                items.reverse();
+                JsonType::Array(items)
+            }
+        },
+        ("flatten", _) => {
+            let depths = match args {
+                [depth] => collect(depth, input, env)?,
+                _ => vec![JsonType::Number(1e9)],
+            };
+            for depth in depths {
+                let depth = match depth {
+                    JsonType::Number(n) if n >= 0.0 => n as usize,
+                    _ => return Err(Raise::new("flatten depth must not be negative".into())),
+                };
+                let mut flat = vec![];
+                flatten(expect_array(name, input)?, depth, &mut flat);
+                out(JsonType::Array(flat))?;
+            }
+            return Ok(());
+        }
+        ("first" | "last", []) => index(
+            input,
+            &JsonType::Number(if name == "first" { 0.0 } else { -1.0 }),
+        )?,
+        ("first", [f]) => return limit(1, f, input, env, out),
+        ("last", [f]) => {
+            if let Some(last) = collect(f, input, env)?.pop() {
+                out(last)?;
+            }
+            return Ok(());
+        }
+        ("limit", [n, f]) => {
+            for n in collect(n, input, env)? {
+                let n = n.as_f64().unwrap_or_default().max(0.0) as usize;
+                limit(n, f, input, env, out)?;
+            }
+            return Ok(());
+        }
+        ("contains", [b]) => {
+            for b in collect(b, input, env)? {
+                if input.type_name() != b.type_name() {
+                    return Err(Raise::new(format!(
+                        "{} and {} cannot have their containment checked",
+                        describe(input),
+                        describe(&b)
+                    )));
+                }
+                out(JsonType::Bool(contains(input, &b)))?;
+            }
+            return Ok(());
+        }
+        ("tostring", _) => JsonType::String(to_string(input)),
+        ("tonumber", _) => match input {
+            JsonType::Number(_) => input.clone(),
+            JsonType::String(s) => match try_parse(s) {
+                Ok(JsonType::Number(n)) => JsonType::Number(n),
+                _ => return Err(Raise::new(format!("Cannot parse '{}' as JSON", s))),
+            },
+            other => {
+                return Err(Raise::new(format!(
+                    "{} cannot be parsed as a number",
+                    describe(other)
+                )))
+            }
+        },
+        ("tojson", _) => JsonType::String(input.serialize()),
+        ("fromjson", _) => match input {
+            JsonType::String(s) => try_parse(s)
+                .map_err(|err| Raise::new(format!("{} (while parsing '{}')", err, s)))?,
+            other => {
+                return Err(Raise::new(format!(
+                    "{} cannot be parsed as JSON",
+                    describe(other)
+                )))
+            }
+        },
+        ("ascii_downcase" | "ascii_upcase", _) => match input {
+            JsonType::String(s) if name == "ascii_downcase" => s.to_ascii_lowercase().into(),
+            JsonType::String(s) => s.to_ascii_uppercase().into(),
+            other => {
+                return Err(Raise::new(format!(
+                    "{} cannot be {}d",
+                    describe(other),
+                    name
+                )))
+            }
+        },
+        ("startswith" | "endswith" | "ltrimstr" | "rtrimstr" | "split" | "join", [arg]) => {
+            for arg in collect(arg, input, env)? {
+                out(string_call(name, input, &arg)?)?;
+            }
+            return Ok(());
+        }
+        ("floor" | "sqrt" | "abs", _) => match input {
+            JsonType::Number(n) => JsonType::Number(match name {
+                "floor" => n.floor(),
+                "sqrt" => n.sqrt(),
+                _ => n.abs(),
+            }),
+            other => return Err(Raise::new(format!("{} number required", describe(other)))),
+        },
+        _ => unreachable!("builtin {} with {} arguments", name, args.len()),
+    };
+    out(value)
+}
+
+/// the first `n` outputs of `f`, which is not evaluated further
+fn limit(
+    n: usize,
+    f: &Ast,
+    input: &JsonType,
+    env: Option<&Env>,
+    out: &mut Out,
+) -> Result<(), Raise> {
+    if n == 0 {
+        return Ok(());
+    }
+    let (mut taken, mut stopped) = (0, false);
+    // the error that stops `f` only passes through evaluation, nothing
+    // between here and the sink catches errors raised by `out`
+    let result = eval(f, input, env, &mut |value| {
+        out(value)?;
+        taken += 1;
+        stopped = taken == n;
+        match stopped {
+            true => Err(Raise(JsonType::Null)),
+            false => Ok(()),
+        }
+    });
+    match stopped {
+        true => Ok(()),
+        false => result,
+    }
+}
+
+/// builtins without arguments, for use by other builtins
+fn call_simple(name: &str, input: &JsonType) -> Result<JsonType, Raise> {
+    let builtin = BUILTINS
+        .iter()
+        .position(|(n, arity)| *n == name && *arity == 0);
+    let mut found = JsonType::Null;
+    call(
+        Builtin(builtin.unwrap_or_default()),
+        &[],
+        input,
+        None,
+        &mut |value| {
+            found = value;
+            Ok(())
+        },
+    )?;
+    Ok(found)
+}
+
+fn from_entries(entries: &[JsonType]) -> Result<JsonType, Raise> {
+    let mut obj = JsonObject::new();
+    for entry in entries {
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| index(entry, &(*name).into()).ok().filter(truthy))
+        };
+        let key = match field(&["key", "k", "name", "Name", "K", "Key"]) {
+            Some(JsonType::String(key)) => key,
+            Some(key @ (JsonType::Number(_) | JsonType::Bool(_))) => key.serialize(),
+            key => {
+                return Err(Raise::new(format!(
+                    "Cannot use {} as object key",
+                    describe(&key.unwrap_or(JsonType::Null))
+                )))
+            }
+        };
+        let value = field(&["value", "v", "Value", "V"]);
+        let value = match value {
+            Some(value) => value,
+            None => index(entry, &"value".into()).unwrap_or(JsonType::Null),
+        };
+        obj.inner_map.insert(key, value);
+    }
+    Ok(JsonType::Object(obj))
+}
+
+fn flatten(items: Vec<JsonType>, depth: usize, out: &mut Vec<JsonType>) {
+    for item in items {
+        match item {
+            JsonType::Array(inner) if depth > 0 => flatten(inner, depth - 1, out),
+            other => out.push(other),
+        }
+    }
+}
+
+fn contains(a: &JsonType, b: &JsonType) -> bool {
+    match (a, b) {
+        (JsonType::String(a), JsonType::String(b)) => a.contains(b.as_str()),
+        (JsonType::Array(a), JsonType::Array(b)) => b.iter().all(|b| {
+            a.iter()
+                .any(|a| a.type_name() == b.type_name() && contains(a, b))
+        }),
+        (JsonType::Object(a), JsonType::Object(b)) => b.iter().all(|(k, b)| {
+            a.get(k)
+                .is_some_and(|a| a.type_name() == b.type_name() && contains(a, b))
+        }),
+        _ => a == b,
+    }
+}
+
+fn string_call(name: &str, input: &JsonType, arg: &JsonType) -> Result<JsonType, Raise> {
+    let fail = || {
+        Raise::new(format!(
+            "{} cannot be used with {} and {}",
+            name,
+            describe(input),
+            describe(arg)
+        ))
+    };
+    Ok(match (name, input, arg) {
+        ("join", JsonType::Array(items), JsonType::String(sep)) => {
+            let mut joined = String::new();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    joined.push_str(sep);
+                }
+                match item {
+                    JsonType::Null => {}
+                    JsonType::Array(_) | JsonType::Object(_) => return Err(fail()),
+                    other => joined.push_str(&to_string(other)),
+                }
+            }
+            joined.into()
+        }
+        (_, JsonType::String(s), JsonType::String(arg)) => match name {
+            "startswith" => JsonType::Bool(s.starts_with(arg.as_str())),
+            "endswith" => JsonType::Bool(s.ends_with(arg.as_str())),
+            "ltrimstr" => s.strip_prefix(arg.as_str()).unwrap_or(s).into(),
+            "rtrimstr" => s.strip_suffix(arg.as_str()).unwrap_or(s).into(),
+            "split" => JsonType::Array(split(s, arg)),
+            _ => return Err(fail()),
+        },
+        ("ltrimstr" | "rtrimstr", ..) => input.clone(),
+        _ => return Err(fail()),
+    })
+}
+
+/// nesting limit of the parser
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+    /// variables in scope
+    vars: Vec<String>,
+    depth: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn error(&self, message: &str) -> QueryError {
+        QueryError::Syntax {
+            message: message.to_string(),
+            offset: self.pos,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.pos + n).copied()
+    }
+
+    /// skip whitespace and `#` comments
+    fn space(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// punctuation followed by optional space
+    fn eat(&mut self, s: &str) -> bool {
+        if self.src[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            self.space();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), QueryError> {
+        if !self.eat(s) {
+            return Err(self.error(&format!("expect `{}`", s)));
+        }
+        Ok(())
+    }
+
+    fn ident_len(&self) -> usize {
+        let rest = &self.src.as_bytes()[self.pos..];
+        match rest.first() {
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') => rest
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count(),
+            _ => 0,
+        }
+    }
+
+    fn peek_ident(&self) -> &'s str {
+        &self.src[self.pos..self.pos + self.ident_len()]
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_ident() == keyword {
+            self.pos += keyword.len();
+            self.space();
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        if !self.keyword(keyword) {
+            return Err(self.error(&format!("expect `{}`", keyword)));
+        }
+        Ok(())
+    }
+
+    fn nest(&mut self) -> Result<(), QueryError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        Ok(())
+    }
+
+    /// `$name` after `as`, the variable is in scope until `unbind`
+    fn bind(&mut self) -> Result<String, QueryError> {
+        if self.peek() != Some(b'$') {
+            return Err(self.error("expect a variable"));
+        }
+        self.pos += 1;
+        let name = self.peek_ident();
+        if name.is_empty() {
+            return Err(self.error("expect a variable name"));
+        }
+        self.pos += name.len();
+        self.space();
+        self.vars.push(name.to_string());
+        Ok(name.to_string())
+    }
+
+    fn pipe(&mut self) -> Result<Ast, QueryError> {
+        self.nest()?;
+        let left = self.comma()?;
+        let ast = match self.eat("|") {
+            true => Ast::Pipe(Box::new(left), Box::new(self.pipe()?)),
+            false => left,
+        };
+        self.depth -= 1;
+        Ok(ast)
+    }
+
+    fn comma(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.alt()?;
+        while self.eat(",") {
+            ast = Ast::Comma(Box::new(ast), Box::new(self.alt()?));
+        }
+        Ok(ast)
+    }
+
+    fn alt(&mut self) -> Result<Ast, QueryError> {
+        let left = self.or()?;
+        if self.eat("//") {
+            return Ok(Ast::Alt(Box::new(left), Box::new(self.alt()?)));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.and()?;
+        while self.keyword("or") {
+            ast = Ast::Or(Box::new(ast), Box::new(self.and()?));
+        }
+        Ok(ast)
+    }
+
+    fn and(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.comparison()?;
+        while self.keyword("and") {
+            ast = Ast::And(Box::new(ast), Box::new(self.comparison()?));
+        }
+        Ok(ast)
+    }
+
+    fn comparison(&mut self) -> Result<Ast, QueryError> {
+        let left = self.additive()?;
+        for (text, op) in [
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+            ("<=", BinOp::Le),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            (">", BinOp::Gt),
+        ] {
+            if self.eat(text) {
+                let right = self.additive()?;
+                return Ok(Ast::Binary(op, Box::new(left), Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => BinOp::Add,
+                Some(b'-') => BinOp::Sub,
+                _ => return Ok(ast),
+            };
+            self.pos += 1;
+            self.space();
+            ast = Ast::Binary(op, Box::new(ast), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.unary()?;
+        loop {
+            let op = match (self.peek(), self.peek_at(1)) {
+                (Some(b'*'), _) => BinOp::Mul,
+                (Some(b'/'), Some(b'/')) => return Ok(ast),
+                (Some(b'/'), _) => BinOp::Div,
+                (Some(b'%'), _) => BinOp::Rem,
+                _ => return Ok(ast),
+            };
+            self.pos += 1;
+            self.space();
+            ast = Ast::Binary(op, Box::new(ast), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Ast, QueryError> {
+        if self.eat("-") {
+            self.nest()?;
+            let ast = Ast::Neg(Box::new(self.unary()?));
+            self.depth -= 1;
+            return Ok(ast);
+        }
+        self.binding()
+    }
+
+    /// a term, or `term as $name | body` binding the rest of the pipe
+    fn binding(&mut self) -> Result<Ast, QueryError> {
+        let term = self.postfix()?;
+        if !self.keyword("as") {
+            return Ok(term);
+        }
+        let name = self.bind()?;
+        self.expect("|")?;
+        let body = self.pipe();
+        self.vars.pop();
+        Ok(Ast::Bind(Box::new(term), name, Box::new(body?)))
+    }
+
+    /// a term followed by `.name`, `[..]` and `?`
+    fn postfix(&mut self) -> Result<Ast, QueryError> {
+        let mut ast = self.term()?;
+        loop {
+            if self.peek() == Some(b'.') && self.peek_at(1) != Some(b'.') {
+                let start = self.pos;
+                self.pos += 1;
+                match self.peek() {
+                    Some(b'"') => {
+                        let key = self.string()?;
+                        ast = Ast::Index(Box::new(ast), Box::new(key));
+                    }
+                    Some(b'[') => {}
+                    _ if self.ident_len() > 0 => {
+                        let name = self.peek_ident();
+                        self.pos += name.len();
+                        self.space();
+                        ast = Ast::Index(Box::new(ast), Box::new(Ast::Literal(name.into())));
+                    }
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expect a field name"));
+                    }
+                }
+            } else if self.eat("[") {
+                ast = self.brackets(ast)?;
+            } else if self.eat("?") {
+                ast = Ast::Try(Box::new(ast), None);
+            } else {
+                return Ok(ast);
+            }
+        }
+    }
+
+    /// after `[`: `]`, an index, or a slice
+    fn brackets(&mut self, target: Ast) -> Result<Ast, QueryError> {
+        let target = Box::new(target);
+        if self.eat("]") {
+            return Ok(Ast::Iterate(target));
+        }
+        if self.eat(":") {
+            let to = self.pipe()?;
+            self.expect("]")?;
+            return Ok(Ast::Slice(target, None, Some(Box::new(to))));
+        }
+        let from = self.pipe()?;
+        if self.eat(":") {
+            let to = match self.eat("]") {
+                true => return Ok(Ast::Slice(target, Some(Box::new(from)), None)),
+                false => self.pipe()?,
+            };
+            self.expect("]")?;
+            return Ok(Ast::Slice(target, Some(Box::new(from)), Some(Box::new(to))));
+        }
+        self.expect("]")?;
+        Ok(Ast::Index(target, Box::new(from)))
+    }
+
+    fn term(&mut self) -> Result<Ast, QueryError> {
+        let ast = match self.peek() {
+            Some(b'.') if self.peek_at(1) == Some(b'.') => {
+                self.pos += 2;
+                Ast::Recurse
+            }
+            Some(b'.') => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(b'"') => Ast::Index(Box::new(Ast::Identity), Box::new(self.string()?)),
+                    _ if self.ident_len() > 0 => {
+                        let name = self.peek_ident();
+                        self.pos += name.len();
+                        Ast::Index(Box::new(Ast::Identity), Box::new(Ast::Literal(name.into())))
+                    }
+                    _ => Ast::Identity,
+                }
+            }
+            Some(b'0'..=b'9') => Ast::Literal(JsonType::Number(self.number()?)),
+            Some(b'"') => self.string()?,
+            Some(b'(') => {
+                self.pos += 1;
+                self.space();
+                let ast = self.pipe()?;
+                if self.peek() != Some(b')') {
+                    return Err(self.error("expect `)`"));
+                }
+                self.pos += 1;
+                ast
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                self.space();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    Ast::Array(None)
+                } else {
+                    let body = self.pipe()?;
+                    if self.peek() != Some(b']') {
+                        return Err(self.error("expect `]`"));
+                    }
+                    self.pos += 1;
+                    Ast::Array(Some(Box::new(body)))
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.space();
+                self.object()?
+            }
+            Some(b'$') => {
+                self.pos += 1;
+                let name = self.peek_ident();
+                if !self.vars.iter().any(|var| var == name) {
+                    return Err(self.error(&format!("${} is not defined", name)));
+                }
+                self.pos += name.len();
+                Ast::Var(name.to_string())
+            }
+            _ => return self.word(),
+        };
+        self.space();
+        Ok(ast)
+    }
+
+    /// keywords, literals and builtin calls
+    fn word(&mut self) -> Result<Ast, QueryError> {
+        let start = self.pos;
+        let name = self.peek_ident();
+        if name.is_empty() {
+            return Err(self.error("expect a filter"));
+        }
+        self.pos += name.len();
+        self.space();
+        Ok(match name {
+            "true" => Ast::Literal(JsonType::Bool(true)),
+            "false" => Ast::Literal(JsonType::Bool(false)),
+            "null" => Ast::Literal(JsonType::Null),
+            "if" => {
+                let mut branches = vec![];
+                loop {
+                    let cond = self.pipe()?;
+                    self.expect_keyword("then")?;
+                    branches.push((cond, self.pipe()?));
+                    if !self.keyword("elif") {
+                        break;
+                    }
+                }
+                let otherwise = match self.keyword("else") {
+                    true => Some(Box::new(self.pipe()?)),
+                    false => None,
+                };
+                self.expect_keyword("end")?;
+                Ast::If(branches, otherwise)
+            }
+            "try" => {
+                let body = self.postfix()?;
+                let catch = match self.keyword("catch") {
+                    true => Some(Box::new(self.postfix()?)),
+                    false => None,
+                };
+                Ast::Try(Box::new(body), catch)
+            }
+            "reduce" => {
+                let source = self.postfix()?;
+                self.expect_keyword("as")?;
+                let name = self.bind()?;
+                // `init` runs outside the scope of the variable
+                self.vars.pop();
+                self.expect("(")?;
+                let init = self.pipe()?;
+                self.expect(";")?;
+                self.vars.push(name.clone());
+                let update = self.pipe();
+                self.vars.pop();
+                let update = update?;
+                if self.peek() != Some(b')') {
+                    return Err(self.error("expect `)`"));
+                }
+                self.pos += 1;
+                self.space();
+                Ast::Reduce(Box::new(source), name, Box::new(init), Box::new(update))
+            }
+            name if KEYWORDS.contains(&name) => {
+                self.pos = start;
+                return Err(self.error(&format!("unexpected keyword `{}`", name)));
+            }
+            name => {
+                let mut args = vec![];
+                if self.eat("(") {
+                    loop {
+                        args.push(self.pipe()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(";")?;
+                    }
+                }
+                let builtin = BUILTINS
+                    .iter()
+                    .position(|(n, arity)| *n == name && *arity == args.len());
+                match builtin {
+                    Some(builtin) => Ast::Call(Builtin(builtin), args),
+                    None => {
+                        self.pos = start;
+                        return Err(self.error(&format!("{}/{} is not defined", name, args.len())));
+                    }
+                }
+            }
+        })
+    }
+
+    /// the entries of `{..}` after `{`
+    fn object(&mut self) -> Result<Ast, QueryError> {
+        let mut entries = vec![];
+        if self.eat("}") {
+            return Ok(Ast::Object(entries));
+        }
+        loop {
+            let (key, shorthand) = match self.peek() {
+                Some(b'$') => {
+                    let var = self.term()?;
+                    let name = match &var {
+                        Ast::Var(name) => name.clone(),
+                        _ => unreachable!(),
+                    };
+                    (Ast::Literal(name.into()), Some(var))
+                }
+                Some(b'"') => {
+                    let key = self.string()?;
+                    self.space();
+                    let value = Ast::Index(Box::new(Ast::Identity), Box::new(key.clone()));
+                    (key, Some(value))
+                }
+                Some(b'(') => {
+                    self.pos += 1;
+                    self.space();
+                    let key = self.pipe()?;
+                    self.expect(")")?;
+                    (key, None)
+                }
+                _ if self.ident_len() > 0 => {
+                    let name = self.peek_ident();
+                    self.pos += name.len();
+                    self.space();
+                    let key = Ast::Literal(name.into());
+                    let value = Ast::Index(Box::new(Ast::Identity), Box::new(key.clone()));
+                    (key, Some(value))
+                }
+                _ => return Err(self.error("expect an object key")),
+            };
+            let value = if self.eat(":") {
+                let mut value = self.alt()?;
+                while self.eat("|") {
+                    value = Ast::Pipe(Box::new(value), Box::new(self.alt()?));
+                }
+                value
+            } else {
+                shorthand.ok_or_else(|| self.error("expect `:`"))?
+            };
+            entries.push((key, value));
+            if self.eat("}") {
+                return Ok(Ast::Object(entries));
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, QueryError> {
+        let start = self.pos;
+        let digits = |p: &mut Self| {
+            let len = p.src[p.pos..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            p.pos += len;
+            len
+        };
+        digits(self);
+        if self.peek() == Some(b'.') && self.peek_at(1).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if digits(self) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        self.src[start..self.pos]
+            .parse()
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    /// a string literal, with `\(..)` it becomes a format
+    fn string(&mut self) -> Result<Ast, QueryError> {
+        self.pos += 1;
+        let mut parts = vec![];
+        let mut text = String::new();
+        loop {
+            let c = match self.src[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => break,
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some(b'(') => {
+                            self.pos += 1;
+                            self.space();
+                            parts.push(Part::Text(std::mem::take(&mut text)));
+                            parts.push(Part::Value(self.pipe()?));
+                            if self.peek() != Some(b')') {
+                                return Err(self.error("expect `)`"));
+                            }
+                            self.pos += 1;
+                            continue;
+                        }
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.pos += 1;
+                            text.push(self.unicode()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.pos += 1;
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        }
+        if parts.is_empty() {
+            return Ok(Ast::Literal(JsonType::String(text)));
+        }
+        parts.push(Part::Text(text));
+        Ok(Ast::Format(parts))
+    }
+
+    fn unicode(&mut self) -> Result<char, QueryError> {
+        let hex4 = |p: &mut Self| {
+            let hex = p.src.get(p.pos..p.pos + 4).unwrap_or_default();
+            let code = u32::from_str_radix(hex, 16).map_err(|_| p.error("invalid unicode escape"));
+            p.pos += 4;
+            code
+        };
+        let high = hex4(self)?;
+        let code = if (0xD800..0xDC00).contains(&high) && self.src[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let low = hex4(self)?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        Ok(char::from_u32(code).unwrap_or('\u{FFFD}'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JqFilter;
+    use crate::{parse, QueryError};
+
+    fn run(filter: &str, input: &str) -> Vec<String> {
+        let filter = JqFilter::compile(filter).unwrap_or_else(|err| panic!("{}: {}", filter, err));
+        let out = filter.apply(&parse(input)).unwrap();
+        out.iter().map(|value| value.serialize()).collect()
+    }
+
+    #[test]
+    fn jq_filters() {
+        let items = r#"{ "items": [
+            { "id": 3, "title": "c", "tag": "x" },
+            { "id": 1, "title": "a", "tag": "y" },
+            { "id": 2, "title": "b", "tag": "x" }
+        ] }"#;
+        let cases: &[(&str, &str, &[&str])] = &[
+            (".", "[1]", &["[1]"]),
+            (".a.b, .a[\"c\"]", r#"{"a":{"b":1,"c":2}}"#, &["1", "2"]),
+            (
+                ".[1:], .[-1], .[]?",
+                "[1,2,3]",
+                &["[2,3]", "3", "1", "2", "3"],
+            ),
+            (
+                ".items | map({id, name: .title}) | sort_by(.id) | map(.name)",
+                items,
+                &[r#"["a","b","c"]"#],
+            ),
+            ("[.items[] | select(.id > 1) | .id]", items, &["[3,2]"]),
+            (
+                ".items | group_by(.tag) | map([.[].id])",
+                items,
+                &["[[3,2],[1]]"],
+            ),
+            (
+                ".items[0] | keys, length, (to_entries | .[0])",
+                items,
+                &[r#"["id","tag","title"]"#, "3", r#"{"key":"id","value":3}"#],
+            ),
+            (
+                "[.items[].id] | add / length, (.[0] * 2 - 1) % 4",
+                items,
+                &["2", "1"],
+            ),
+            (
+                r#".items[] | "\(.title)#\(.id + 1)""#,
+                items,
+                &[r#""c#4""#, r#""a#2""#, r#""b#3""#],
+            ),
+            (
+                "{(.k): .v, n: (1, 2)}",
+                r#"{"k":"x","v":true}"#,
+                &[r#"{"x":true,"n":1}"#, r#"{"x":true,"n":2}"#],
+            ),
+            (". as $x | [$x, $x + 1]", "1", &["[1,2]"]),
+            ("[.[] as $x | $x * 2 | -.]", "[1,2]", &["[-2,-4]"]),
+            ("reduce .[] as $n (0; . + $n)", "[1,2,3]", &["6"]),
+            ("10 as $n | reduce .[] as $n ($n; . + $n)", "[1,2]", &["13"]),
+            ("[7 % -3, (-1e19) % -1]", "null", &["[1,0]"]),
+            ("first(range(1e12))", "null", &["0"]),
+            (
+                "[limit(1; limit(2; 1, 2, 3)), first(1, error(\"x\"))]",
+                "null",
+                &["[1,1]"],
+            ),
+            (
+                "if . > 1 then \"big\" elif . == 1 then \"one\" else empty end",
+                "1",
+                &[r#""one""#],
+            ),
+            (".missing // \"default\"", "{}", &[r#""default""#]),
+            ("try error(\"boom\") catch ., (.a)?", "1", &[r#""boom""#]),
+            ("[..] | length", "[[1],{\"a\":2}]", &["5"]),
+            (
+                "with_entries({key, value: (.value + 1)}) | to_entries | map(.key) | join(\",\")",
+                r#"{"b":1,"a":2}"#,
+                &[r#""b,a""#],
+            ),
+            (
+                "[.[] | tostring], (\"1,2\" | split(\",\") | map(tonumber))",
+                "[1,\"a\",null]",
+                &[r#"["1","a","null"]"#, "[1,2]"],
+            ),
+            (
+                "[range(3)], [limit(2; .[])], first, unique, (sort | reverse)",
+                "[3,1,3]",
+                &["[0,1,2]", "[3,1]", "3", "[1,3]", "[3,3,1]"],
+            ),
+            (
+                "{a: 1} + {b: 2} | has(\"a\"), contains({b: 2}), .c.d",
+                "null",
+                &["true", "true", "null"],
+            ),
+            ("(1, 2) * (10, 20)", "null", &["10", "20", "20", "40"]),
+        ];
+        for (filter, input, espect) in cases {
+            assert_eq!(run(filter, input), *espect, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn jq_errors() {
+        for filter in [
+            "",
+            ".[",
+            "map(",
+            "foo",
+            "map(.; .)",
+            "$x",
+            "{a:}",
+            "if . then 1",
+            ". as x | .",
+            "reduce .[] as $x ($x; .)",
+        ] {
+            assert!(JqFilter::compile(filter).is_err(), "{}", filter);
+        }
+        let filter = JqFilter::compile(".a").unwrap();
+        assert_eq!(
+            filter.apply(&parse("[1]")).unwrap_err(),
+            QueryError::Runtime {
+                message: "Cannot index array with \"a\"".to_string()
+            }
+        );
+        let filter = JqFilter::compile(". * 1e30").unwrap();
+        assert_eq!(
+            filter.apply(&parse("\"a\"")).unwrap_err().to_string(),
+            "Repeat string result too long"
+        );
+        let filter = JqFilter::compile(".[] | 10 / .").unwrap();
+        assert_eq!(
+            filter.apply(&parse("[1, 0]")).unwrap_err().to_string(),
+            "number (10) and number (0) cannot be divided because the divisor is zero"
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+use crate::diff::lcs_pairs;
+use crate::error::{ErrorKind, JsonError, PathError};
+use crate::lexer::Comment;
+use crate::options::ParseOptions;
+use crate::parser::TreeBuilder;
+use crate::path::{JsonPath, Segment, ToPath};
+use crate::reader::{Event, JsonReader};
+use crate::ser::{serialize_key, serialize_with};
+use crate::{JsonType, SerializeOptions};
+
+/// comments attached to one value of a [`JsoncDocument`]
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Comments {
+    /// comments on the lines before the value, or before the key of a member
+    pub before: Vec<String>,
+    /// comments following the value on the same line
+    pub after: Vec<String>,
+    /// comments before the closing bracket of an object or array
+    pub end: Vec<String>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty() && self.end.is_empty()
+    }
+}
+
+/// a json value together with the comments of the document it was parsed from,
+/// such as a VS Code `settings.json`
+///
+/// comments are attached to the nearest member or element and written back by
+/// `pretty_serialize`, comments must include their `//` or `/* */` delimiters
+///
+/// ```
+/// use dynamic_json::{json_obj, JsonObject, JsonType, JsoncDocument};
+/// let mut doc = JsoncDocument::parse(
+///     "{\n  // theme of the editor\n  \"theme\": \"dark\", // or light\n}",
+/// )
+/// .unwrap();
+/// *doc.value_mut() = json_obj! { "theme" => "light" }.into();
+/// assert_eq!(
+///     doc.pretty_serialize(2),
+///     "{\n  // theme of the editor\n  \"theme\": \"light\" // or light\n}"
+/// );
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsoncDocument {
+    value: JsonType,
+    /// comments by the path of the value they belong to
+    comments: HashMap<Vec<Segment>, Comments>,
+    /// comments after the root value
+    footer: Vec<String>,
+}
+
+impl JsoncDocument {
+    /// parse json with comments and trailing commas
+    pub fn parse(s: &str) -> Result<JsoncDocument, JsonError> {
+        let options = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            ..Default::default()
+        };
+        JsoncDocument::parse_with(s, &options)
+    }
+
+    /// parse with the given extensions, comments are always allowed
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<JsoncDocument, JsonError> {
+        let options = ParseOptions {
+            allow_comments: true,
+            ..options.clone()
+        };
+        let mut reader = JsonReader::new(s).with_options(&options);
+        reader.keep_comments();
+        let mut builder = TreeBuilder::default();
+        let mut attach = Attach::default();
+        loop {
+            let event = reader.next_event()?;
+            let step = match event {
+                Some(Event::Key(key)) => Step::Key(key.to_string()),
+                Some(Event::StartObject) => Step::Start(false),
+                Some(Event::StartArray) => Step::Start(true),
+                Some(Event::EndObject | Event::EndArray) => Step::End,
+                Some(Event::Value(_)) => Step::Value,
+                None => Step::Done,
+            };
+            if let Some(event) = event {
+                builder.push(event);
+            }
+            let comments = reader.take_comments();
+            if attach.step(step, comments) {
+                break;
+            }
+        }
+        match builder.take_root() {
+            Some(value) => Ok(JsoncDocument {
+                value,
+                comments: attach.comments,
+                footer: attach.footer,
+            }),
+            None => Err(reader.error(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    pub fn value(&self) -> &JsonType {
+        &self.value
+    }
+
+    /// the value can be changed freely, when the returned guard is dropped
+    /// comments follow their members and elements, matched like
+    /// `ArrayDiff::Lcs`, and comments of removed values are dropped
+    ///
+    /// ```
+    /// use dynamic_json::{JsonType, JsoncDocument};
+    /// let mut doc = JsoncDocument::parse("[\n  1, // one\n  2 // two\n]").unwrap();
+    /// if let JsonType::Array(arr) = &mut *doc.value_mut() {
+    ///     arr.remove(0);
+    /// }
+    /// assert_eq!(doc.pretty_serialize(2), "[\n  2 // two\n]");
+    /// ```
+    pub fn value_mut(&mut self) -> ValueMut<'_> {
+        let old = match self.comments.is_empty() {
+            true => None,
+            false => Some(self.value.clone()),
+        };
+        ValueMut { doc: self, old }
+    }
+
+    pub fn into_value(self) -> JsonType {
+        self.value
+    }
+
+    /// comments of the value at a path like `JsonType::get`, the root
+    /// value has the empty path
+    pub fn comments<P: ToPath + ?Sized>(&self, path: &P) -> Option<&Comments> {
+        let path = self.comment_path(path.to_path().ok()?.as_ref());
+        self.comments.get(&path)
+    }
+
+    pub fn comments_mut<P: ToPath + ?Sized>(
+        &mut self,
+        path: &P,
+    ) -> Result<&mut Comments, PathError> {
+        let path = self.comment_path(path.to_path()?.as_ref());
+        Ok(self.comments.entry(path).or_default())
+    }
+
+    /// comments after the root value
+    pub fn footer(&self) -> &[String] {
+        &self.footer
+    }
+
+    pub fn footer_mut(&mut self) -> &mut Vec<String> {
+        &mut self.footer
+    }
+
+    /// serialize with indent and line wrap, writing the comments back
+    pub fn pretty_serialize(&self, indent: u32) -> String {
+        let options = SerializeOptions {
+            indent,
+            ..Default::default()
+        };
+        self.serialize_with(&options)
+    }
+
+    /// serialize in the format described by `options`, line comments are
+    /// always followed by a line break
+    pub fn serialize_with(&self, options: &SerializeOptions) -> String {
+        let mut writer = Writer {
+            comments: &self.comments,
+            options,
+            out: String::new(),
+            path: vec![],
+        };
+        let root = self.comments.get(&[][..]);
+        for comment in root.map(|c| &c.before[..]).unwrap_or_default() {
+            writer.comment(comment, "\n");
+        }
+        writer.value(&self.value, 0);
+        for comment in root.map(|c| &c.after[..]).unwrap_or_default() {
+            writer.out.push(' ');
+            writer.comment(comment, "");
+        }
+        for comment in &self.footer {
+            writer.line(0);
+            writer.comment(comment, "");
+        }
+        writer.out
+    }
+
+    /// comments are stored by the keys and indices leading to their value,
+    /// indices select keys spelled as numbers in objects
+    fn comment_path(&self, path: &JsonPath) -> Vec<Segment> {
+        let mut node = Some(&self.value);
+        let mut segments = vec![];
+        for segment in path.segments() {
+            let segment = match (node, segment) {
+                (Some(JsonType::Object(_)), Segment::Index(index)) => {
+                    Segment::Key(index.to_string())
+                }
+                (_, segment) => segment.clone(),
+            };
+            node = match (node, &segment) {
+                (Some(JsonType::Object(obj)), Segment::Key(key)) => obj.get(key),
+                (Some(JsonType::Array(arr)), Segment::Index(index)) => arr.get(*index),
+                _ => None,
+            };
+            segments.push(segment);
+        }
+        segments
+    }
+
+    /// move the comments of `old` to where its values are now
+    fn follow(&mut self, old: &JsonType) {
+        let mut comments = std::mem::take(&mut self.comments);
+        let mut moved = HashMap::new();
+        follow(
+            old,
+            &self.value,
+            &mut vec![],
+            &mut vec![],
+            &mut comments,
+            &mut moved,
+        );
+        self.comments = moved;
+    }
+}
+
+/// the value of a [`JsoncDocument`] borrowed by `value_mut`, comments are
+/// moved along with the changes when it is dropped
+pub struct ValueMut<'d> {
+    doc: &'d mut JsoncDocument,
+    /// the value before the changes, `None` without comments to move
+    old: Option<JsonType>,
+}
+
+impl Deref for ValueMut<'_> {
+    type Target = JsonType;
+
+    fn deref(&self) -> &JsonType {
+        &self.doc.value
+    }
+}
+
+impl DerefMut for ValueMut<'_> {
+    fn deref_mut(&mut self) -> &mut JsonType {
+        &mut self.doc.value
+    }
+}
+
+impl Drop for ValueMut<'_> {
+    fn drop(&mut self) {
+        if let Some(old) = self.old.take() {
+            self.doc.follow(&old);
+        }
+    }
+}
+
+/// move the comments at `from` of the `old` value and of its children to `to`
+/// in `moved`, members are matched by key and elements like `lcs_pairs`
+fn follow(
+    old: &JsonType,
+    new: &JsonType,
+    from: &mut Vec<Segment>,
+    to: &mut Vec<Segment>,
+    comments: &mut HashMap<Vec<Segment>, Comments>,
+    moved: &mut HashMap<Vec<Segment>, Comments>,
+) {
+    if let Some(entry) = comments.remove(&from[..]) {
+        moved.insert(to.clone(), entry);
+    }
+    let mut child = |a: &JsonType, b: &JsonType, from_segment, to_segment| {
+        from.push(from_segment);
+        to.push(to_segment);
+        follow(a, b, from, to, comments, moved);
+        from.pop();
+        to.pop();
+    };
+    match (old, new) {
+        (JsonType::Object(a), JsonType::Object(b)) => {
+            for (key, a) in a {
+                if let Some(b) = b.get(key) {
+                    child(a, b, Segment::Key(key.clone()), Segment::Key(key.clone()));
+                }
+            }
+        }
+        (JsonType::Array(a), JsonType::Array(b)) => {
+            for (i, j) in lcs_pairs(a, b) {
+                child(&a[i], &b[j], Segment::Index(i), Segment::Index(j));
+            }
+        }
+        _ => (),
+    }
+}
+
+enum Step {
+    Key(String),
+    /// start of an object or array, `true` for arrays
+    Start(bool),
+    End,
+    Value,
+    Done,
+}
+
+/// decides which value the comments before each event belong to
+#[derive(Default)]
+struct Attach {
+    comments: HashMap<Vec<Segment>, Comments>,
+    footer: Vec<String>,
+    /// path of the open container
+    path: Vec<Segment>,
+    /// next element index of each open container, `None` for objects
+    indices: Vec<Option<usize>>,
+    /// path of the member whose key was read last
+    member: Option<Vec<Segment>>,
+    /// path of the last complete value that can take same line comments
+    last: Option<Vec<Segment>>,
+}
+
+impl Attach {
+    /// returns `true` once the document is complete
+    fn step(&mut self, step: Step, comments: Vec<Comment>) -> bool {
+        match step {
+            Step::Key(key) => {
+                let mut member = self.path.clone();
+                member.push(Segment::Key(key));
+                self.split(comments, member.clone());
+                self.member = Some(member);
+            }
+            Step::Start(_) | Step::Value => {
+                let member = match self.indices.last_mut() {
+                    Some(Some(index)) => {
+                        let mut member = self.path.clone();
+                        member.push(Segment::Index(*index));
+                        *index += 1;
+                        self.split(comments, member.clone());
+                        member
+                    }
+                    _ => {
+                        let member = self.member.take().unwrap_or_default();
+                        self.entry(&member).before.extend(texts(comments));
+                        member
+                    }
+                };
+                if let Step::Start(is_array) = step {
+                    self.path = member;
+                    self.indices.push(if is_array { Some(0) } else { None });
+                    self.last = None;
+                } else {
+                    self.last = Some(member);
+                }
+            }
+            Step::End => {
+                let path = self.path.clone();
+                self.split_end(comments, Some(&path));
+                self.indices.pop();
+                self.path.pop();
+                self.last = Some(path);
+            }
+            Step::Done => {
+                self.split_end(comments, None);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn entry(&mut self, path: &[Segment]) -> &mut Comments {
+        self.comments.entry(path.to_vec()).or_default()
+    }
+
+    /// comments on the line of the previous value belong to it,
+    /// the others come before `next`
+    fn split(&mut self, comments: Vec<Comment>, next: Vec<Segment>) {
+        for comment in comments {
+            match &self.last {
+                Some(last) if !comment.own_line => {
+                    let last = last.clone();
+                    self.entry(&last).after.push(comment.text);
+                }
+                _ => self.entry(&next).before.push(comment.text),
+            }
+        }
+        self.last = None;
+    }
+
+    /// like `split` for comments that are not followed by a value, the others
+    /// end the open `container` or the document
+    fn split_end(&mut self, comments: Vec<Comment>, container: Option<&[Segment]>) {
+        for comment in comments {
+            match &self.last {
+                Some(last) if !comment.own_line => {
+                    let last = last.clone();
+                    self.entry(&last).after.push(comment.text);
+                }
+                _ => match container {
+                    Some(path) => self.entry(path).end.push(comment.text),
+                    None => self.footer.push(comment.text),
+                },
+            }
+        }
+    }
+}
+
+fn texts(comments: Vec<Comment>) -> impl Iterator<Item = String> {
+    comments.into_iter().map(|comment| comment.text)
+}
+
+struct Writer<'a> {
+    comments: &'a HashMap<Vec<Segment>, Comments>,
+    options: &'a SerializeOptions,
+    out: String,
+    /// path of the value being written
+    path: Vec<Segment>,
+}
+
+impl<'a> Writer<'a> {
+    /// line break and indentation of `level`, nothing in compact mode
+    fn line(&mut self, level: u32) {
+        if self.options.indent > 0 {
+            self.out.push('\n');
+            self.out
+                .push_str(&" ".repeat((level * self.options.indent) as usize));
+        }
+    }
+
+    /// a line comment ends the line, in compact mode too
+    fn comment(&mut self, comment: &str, after: &str) {
+        self.out.push_str(comment);
+        if comment.starts_with("//") && self.options.indent == 0 {
+            self.out.push('\n');
+        } else {
+            self.out.push_str(after);
+        }
+    }
+
+    fn current(&self) -> Option<&'a Comments> {
+        self.comments.get(&self.path)
+    }
+
+    fn value(&mut self, json: &JsonType, level: u32) {
+        let (open, close) = match json {
+            JsonType::Object(_) => ('{', '}'),
+            JsonType::Array(_) => ('[', ']'),
+            _ => {
+                self.out
+                    .push_str(&serialize_with(json, self.options, self.options.indent));
+                return;
+            }
+        };
+        let gap = if self.options.indent > 0 { " " } else { "" };
+        let mut items: Vec<(Segment, Option<String>, &JsonType)> = vec![];
+        match json {
+            JsonType::Object(obj) => {
+                for (key, value) in obj {
+                    let written = serialize_key(key, self.options.json5);
+                    items.push((Segment::Key(key.clone()), Some(written), value));
+                }
+            }
+            JsonType::Array(arr) => {
+                for (i, item) in arr.iter().enumerate() {
+                    items.push((Segment::Index(i), None, item));
+                }
+            }
+            _ => (),
+        }
+        let end = self.current().map(|c| &c.end[..]).unwrap_or_default();
+        self.out.push(open);
+        let count = items.len();
+        for (i, (segment, key, value)) in items.into_iter().enumerate() {
+            self.path.push(segment);
+            let comments = self.current();
+            for comment in comments.map(|c| &c.before[..]).unwrap_or_default() {
+                self.line(level + 1);
+                self.comment(comment, "");
+            }
+            self.line(level + 1);
+            if let Some(key) = key {
+                self.out.push_str(&key);
+                self.out.push(':');
+                self.out.push_str(gap);
+            }
+            self.value(value, level + 1);
+            if i + 1 < count {
+                self.out.push(',');
+            }
+            for comment in comments.map(|c| &c.after[..]).unwrap_or_default() {
+                self.out.push(' ');
+                self.comment(comment, "");
+            }
+            self.path.pop();
+        }
+        for comment in end {
+            self.line(level + 1);
+            self.comment(comment, "");
+        }
+        if count > 0 || !end.is_empty() {
+            self.line(level);
+        }
+        self.out.push(close);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsoncDocument;
+    use crate::{json_arr, JsonType};
+
+    const SETTINGS: &str = r#"// user settings
+{
+  /* editor */
+  "editor": {
+    "fontSize": 14, // pixels
+    "rulers": [
+      80, // commit messages
+      // code
+      100,
+    ],
+  },
+  "files": [], /* none yet */
+  // "telemetry": false,
+}
+// end
+"#;
+
+    #[test]
+    fn attach_comments() {
+        let doc = JsoncDocument::parse(SETTINGS).unwrap();
+        assert_eq!(doc.value()["editor"]["fontSize"], JsonType::from(14));
+
+        let root = doc.comments("").unwrap();
+        assert_eq!(root.before, ["// user settings"]);
+        assert_eq!(root.end, ["// \"telemetry\": false,"]);
+        assert_eq!(doc.footer(), ["// end"]);
+        assert_eq!(doc.comments("editor").unwrap().before, ["/* editor */"]);
+        let font = doc.comments("editor.fontSize").unwrap();
+        assert_eq!(font.after, ["// pixels"]);
+        assert_eq!(
+            doc.comments("editor.rulers.0").unwrap().after,
+            ["// commit messages"]
+        );
+        assert_eq!(doc.comments("editor.rulers.1").unwrap().before, ["// code"]);
+        assert_eq!(doc.comments("files").unwrap().after, ["/* none yet */"]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let doc = JsoncDocument::parse(SETTINGS).unwrap();
+        let out = doc.pretty_serialize(2);
+        assert_eq!(JsoncDocument::parse(&out).unwrap(), doc);
+
+        let s = r#"// user settings
+{
+  "editor": {
+    "rulers": [
+      80, // commit messages
+      // code
+      100
+    ]
+    // more to come
+  } /* editor */
+}
+// end"#;
+        let doc = JsoncDocument::parse(s).unwrap();
+        assert_eq!(doc.pretty_serialize(2), s);
+
+        let mut doc = JsoncDocument::parse("[1, /* one */ 2] // two").unwrap();
+        doc.comments_mut("[0]")
+            .unwrap()
+            .before
+            .push("// first".to_string());
+        assert_eq!(doc.value(), &json_arr![1, 2]);
+        assert_eq!(
+            doc.serialize_with(&Default::default()),
+            "[// first\n1, /* one */2] // two\n"
+        );
+    }
+
+    #[test]
+    fn comments_follow_values() {
+        let s = "{\n  \"0\": 1, // key\n  \"list\": [\n    \"a\", // first\n    \"b\" // second\n  ]\n}";
+        let mut doc = JsoncDocument::parse(s).unwrap();
+        assert_eq!(doc.comments("0").unwrap().after, ["// key"]);
+        assert_eq!(doc.comments(r#"["0"]"#).unwrap().after, ["// key"]);
+        assert_eq!(doc.comments("list.1").unwrap().after, ["// second"]);
+
+        let mut doc2 = JsoncDocument::parse("[1, 2] // end").unwrap();
+        doc2.comments_mut("0")
+            .unwrap()
+            .after
+            .push("/* one */".to_string());
+        assert!(doc2.comments(r#"["0"]"#).is_none());
+        assert_eq!(
+            doc2.serialize_with(&Default::default()),
+            "[1, /* one */2] // end\n"
+        );
+
+        if let JsonType::Array(list) = &mut doc.value_mut()["list"] {
+            list.insert(0, "z".into());
+        }
+        assert_eq!(doc.comments("list.1").unwrap().after, ["// first"]);
+        assert_eq!(
+            doc.pretty_serialize(2),
+            "{\n  \"0\": 1, // key\n  \"list\": [\n    \"z\",\n    \"a\", // first\n    \"b\" // second\n  ]\n}"
+        );
+
+        if let JsonType::Array(list) = &mut doc.value_mut()["list"] {
+            list.remove(1);
+        }
+        doc.value_mut()["0"] = 2.into();
+        assert_eq!(
+            doc.pretty_serialize(2),
+            "{\n  \"0\": 2, // key\n  \"list\": [\n    \"z\",\n    \"b\" // second\n  ]\n}"
+        );
+    }
+}
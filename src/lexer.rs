@@ -0,0 +1,630 @@
+use crate::error::{ErrorKind, JsonError};
+use crate::options::ParseOptions;
+use crate::read::Source;
+
+/// splits the input into tokens, decoding strings and numbers
+pub struct Lexer<S> {
+    source: S,
+    pub options: ParseOptions,
+    /// the bytes of the number being scanned
+    scratch: Vec<u8>,
+    /// the decoded content of the last string
+    string: String,
+    /// comments skipped since they were last taken, `None` unless they are kept
+    comments: Option<Vec<Comment>>,
+    /// whether a line break was skipped since the last token
+    newline: bool,
+    /// the token that was being read at the last checkpoint
+    pending: Option<Pending>,
+}
+
+/// a string or number that a source may run out of input in, with the
+/// length of the content decoded up to the checkpoint
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Pending {
+    String(u8, usize),
+    Number(usize),
+}
+
+/// a scalar read by `parse_scalar`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Token {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String,
+}
+
+/// a comment with its delimiters, as written in the input
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub text: String,
+    /// whether the comment starts on a new line rather than after a token
+    pub own_line: bool,
+}
+
+impl<S: Source> Lexer<S> {
+    pub fn new(source: S) -> Self {
+        Lexer {
+            source,
+            options: ParseOptions::default(),
+            scratch: vec![],
+            string: String::new(),
+            comments: None,
+            newline: false,
+            pending: None,
+        }
+    }
+
+    /// a point the token can be continued from, a source that receives its
+    /// input in pieces goes back here when it runs out instead of to the
+    /// start of the token
+    fn checkpoint(&mut self, pending: Pending) {
+        self.pending = Some(pending);
+        self.source.mark();
+    }
+
+    /// the first byte of a string or number that is continued by the next
+    /// `parse_string` or `parse_number`, which then skip what was read before
+    pub fn resumes(&self) -> Option<u8> {
+        match self.pending? {
+            Pending::String(quote, _) => Some(quote),
+            Pending::Number(_) => Some(b'0'),
+        }
+    }
+
+    /// record skipped comments until they are taken with `take_comments`
+    pub fn keep_comments(&mut self) {
+        self.comments.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        match &mut self.comments {
+            Some(comments) => std::mem::take(comments),
+            None => vec![],
+        }
+    }
+
+    fn peek_byte(&mut self) -> Result<u8, JsonError> {
+        match self.source.peek()? {
+            Some(b) => Ok(b),
+            None => Err(self.source.error(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    /// skip whitespace, and comments if they are allowed
+    pub fn skip_spaces(&mut self) -> Result<(), JsonError> {
+        self.newline = false;
+        loop {
+            self.source.mark();
+            match self.source.peek()? {
+                Some(b'\n') => {
+                    self.newline = true;
+                    self.source.bump();
+                }
+                Some(b' ' | b'\t' | b'\r') => self.source.bump(),
+                Some(b'/') if self.options.allow_comments => self.skip_comment()?,
+                Some(0x0B | 0x0C) if self.options.json5 => self.source.bump(),
+                Some(0x80..) if self.options.json5 => match self.source.peek_char()? {
+                    Some(c) if is_json5_space(c) => self.bump_char(c),
+                    _ => return Ok(()),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn bump_char(&mut self, c: char) {
+        for _ in 0..c.len_utf8() {
+            self.source.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), JsonError> {
+        let mut text = vec![b'/'];
+        self.source.bump();
+        match self.source.peek()? {
+            Some(b'/') => {
+                while let Some(b) = self.source.peek()? {
+                    if b == b'\n' {
+                        break;
+                    }
+                    text.push(b);
+                    self.source.bump();
+                }
+                if text.last() == Some(&b'\r') {
+                    text.pop();
+                }
+            }
+            Some(b'*') => {
+                text.push(b'*');
+                self.source.bump();
+                loop {
+                    let b = self.peek_byte()?;
+                    text.push(b);
+                    self.source.bump();
+                    if b == b'*' && self.source.peek()? == Some(b'/') {
+                        text.push(b'/');
+                        self.source.bump();
+                        break;
+                    }
+                }
+            }
+            _ => return Err(self.unexpected()),
+        }
+        if let Some(comments) = &mut self.comments {
+            comments.push(Comment {
+                text: String::from_utf8_lossy(&text).into_owned(),
+                own_line: self.newline,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn peek(&mut self) -> Result<Option<u8>, JsonError> {
+        self.source.peek()
+    }
+
+    pub fn bump(&mut self) {
+        self.source.bump();
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// error located at the next unconsumed byte
+    pub fn error(&self, kind: ErrorKind) -> JsonError {
+        self.source.error(kind)
+    }
+
+    /// error for the next byte, which can not appear at this position
+    pub fn unexpected(&mut self) -> JsonError {
+        match self.source.peek() {
+            Ok(Some(_)) => self.source.unexpected_char(),
+            Ok(None) => self.source.error(ErrorKind::UnexpectedEof),
+            Err(err) => err,
+        }
+    }
+
+    /// the content of the last string read by `parse_string` or `parse_identifier`
+    pub fn string(&self) -> &str {
+        &self.string
+    }
+
+    pub fn parse_literal(&mut self, literal: &[u8]) -> Result<(), JsonError> {
+        for expected in literal {
+            if self.source.peek()? != Some(*expected) {
+                return Err(self.source.error(ErrorKind::InvalidLiteral));
+            }
+            self.source.bump();
+        }
+        Ok(())
+    }
+
+    fn is_digit(&mut self) -> Result<bool, JsonError> {
+        Ok(matches!(self.source.peek()?, Some(b'0'..=b'9')))
+    }
+
+    fn push_digits(&mut self) -> Result<(), JsonError> {
+        if !self.is_digit()? {
+            return Err(self.source.error(ErrorKind::InvalidNumber));
+        }
+        self.more_digits()
+    }
+
+    /// the digits of the current part of a number, each one a checkpoint
+    fn more_digits(&mut self) -> Result<(), JsonError> {
+        while let Some(b @ b'0'..=b'9') = self.source.peek()? {
+            self.scratch.push(b);
+            self.source.bump();
+            self.checkpoint(Pending::Number(self.scratch.len()));
+        }
+        Ok(())
+    }
+
+    /// scan a number following the RFC 8259 grammar:
+    /// `[ minus ] int [ frac ] [ exp ]`,
+    /// in json5 mode also hex integers, a leading `+` and leading or trailing `.`
+    pub fn parse_number(&mut self) -> Result<f64, JsonError> {
+        match self.pending {
+            // the scanned part is in `scratch`, continue with its last digits
+            Some(Pending::Number(len)) => self.scratch.truncate(len),
+            _ => {
+                self.pending = None;
+                self.scratch.clear();
+                if let Some(num) = self.parse_number_start()? {
+                    return Ok(num);
+                }
+            }
+        }
+        let json5 = self.options.json5;
+        let scanned = |scratch: &[u8], bytes: &[u8]| scratch.iter().any(|b| bytes.contains(b));
+        self.more_digits()?;
+        if !scanned(&self.scratch, b".eE") && self.source.peek()? == Some(b'.') {
+            let int_digits = scanned(&self.scratch, b"0123456789");
+            self.scratch.push(b'.');
+            self.source.bump();
+            // json5 allows `1.` but not a lone `.`
+            if !int_digits || !json5 || self.is_digit()? {
+                self.push_digits()?;
+            }
+        }
+        if !scanned(&self.scratch, b"eE") {
+            if let Some(b @ (b'e' | b'E')) = self.source.peek()? {
+                self.scratch.push(b);
+                self.source.bump();
+                if let Some(b @ (b'+' | b'-')) = self.source.peek()? {
+                    self.scratch.push(b);
+                    self.source.bump();
+                }
+                self.push_digits()?;
+            }
+        }
+        // reject things like `1.2.3` or `1e5e5` instead of silently truncating them
+        if let Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') = self.source.peek()? {
+            return Err(self.source.error(ErrorKind::InvalidNumber));
+        }
+        self.pending = None;
+
+        let (sign, digits) = match self.scratch.split_first() {
+            Some((b'-', digits)) => (-1.0, digits),
+            _ => (1.0, &self.scratch[..]),
+        };
+        // plain integers that fit in the f64 mantissa don't need the float parser
+        if !scanned(digits, b".eE") && digits.len() <= 15 {
+            let num = digits
+                .iter()
+                .fold(0u64, |acc, d| acc * 10 + u64::from(d - b'0')) as f64;
+            return Ok(sign * num);
+        }
+        // the scanned bytes are ascii only
+        let s = std::str::from_utf8(&self.scratch).unwrap_or_default();
+        // literals like `1e400` overflow to infinity, which only json5 can write back
+        let infinity_allowed = self.options.json5 || self.options.allow_nan_infinity;
+        match s.parse::<f64>() {
+            Ok(num) if num.is_finite() || infinity_allowed => Ok(num),
+            _ => Err(self.source.error(ErrorKind::InvalidNumber)),
+        }
+    }
+
+    /// the sign and the start of the integer part, which go to `scratch`,
+    /// or the whole value of numbers that are not decimal
+    fn parse_number_start(&mut self) -> Result<Option<f64>, JsonError> {
+        let json5 = self.options.json5;
+        let sign = match self.source.peek()? {
+            Some(b'-') => {
+                self.scratch.push(b'-');
+                self.source.bump();
+                -1.0
+            }
+            Some(b'+') if json5 => {
+                self.source.bump();
+                1.0
+            }
+            _ => 1.0,
+        };
+        match self.source.peek()? {
+            Some(b'I') if self.options.allow_nan_infinity => {
+                self.parse_literal(b"Infinity")?;
+                Ok(Some(sign * f64::INFINITY))
+            }
+            Some(b'N') if self.options.allow_nan_infinity && json5 => {
+                self.parse_literal(b"NaN")?;
+                Ok(Some(f64::NAN))
+            }
+            Some(b'0') => {
+                self.scratch.push(b'0');
+                self.source.bump();
+                match self.source.peek()? {
+                    Some(b'x' | b'X') if json5 => {
+                        self.source.bump();
+                        Ok(Some(sign * self.parse_hex_number()?))
+                    }
+                    Some(b'0'..=b'9') => Err(self.source.error(ErrorKind::InvalidNumber)),
+                    _ => Ok(None),
+                }
+            }
+            // the integer part is empty, `parse_number` requires a fraction
+            Some(b'.') if json5 => Ok(None),
+            _ if self.is_digit()? => Ok(None),
+            _ => Err(self.source.error(ErrorKind::InvalidNumber)),
+        }
+    }
+
+    /// the digits of a json5 hex integer after `0x`
+    fn parse_hex_number(&mut self) -> Result<f64, JsonError> {
+        let mut num = 0.0;
+        let mut digits = 0;
+        while let Some(d) = self.source.peek()?.and_then(|b| (b as char).to_digit(16)) {
+            num = num * 16.0 + f64::from(d);
+            digits += 1;
+            self.source.bump();
+        }
+        if digits == 0 || matches!(self.source.peek()?, Some(b'.' | b'+' | b'-')) {
+            return Err(self.source.error(ErrorKind::InvalidNumber));
+        }
+        Ok(num)
+    }
+
+    /// read a string quoted with the next byte into `self.string`, decoding escape
+    /// sequences including utf-16 surrogate pairs like `\ud83d\ude00`
+    pub fn parse_string(&mut self) -> Result<(), JsonError> {
+        let mut s = std::mem::take(&mut self.string);
+        let result = self.parse_string_into(&mut s);
+        self.string = s;
+        result
+    }
+
+    fn parse_string_into(&mut self, s: &mut String) -> Result<(), JsonError> {
+        let quote = match self.pending.take() {
+            // `s` still holds the string, continue after what was decoded
+            Some(Pending::String(quote, len)) => {
+                s.truncate(len);
+                quote
+            }
+            _ => {
+                s.clear();
+                let quote = self.peek_byte()?;
+                self.source.bump();
+                quote
+            }
+        };
+        loop {
+            self.source.read_plain(s, quote)?;
+            self.checkpoint(Pending::String(quote, s.len()));
+            match self.peek_byte()? {
+                b if b == quote => {
+                    self.source.bump();
+                    self.pending = None;
+                    return Ok(());
+                }
+                b'\\' => {
+                    self.source.bump();
+                    let escaped = self.peek_byte()?;
+                    match escaped {
+                        b'"' => s.push('"'),
+                        b'\'' if self.options.allow_single_quotes => s.push('\''),
+                        _ if self.options.json5 => {
+                            self.parse_json5_escape(s)?;
+                            continue;
+                        }
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{08}'),
+                        b'f' => s.push('\u{0c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => {
+                            self.source.bump();
+                            s.push(self.parse_unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.source.error(ErrorKind::InvalidEscape)),
+                    }
+                    self.source.bump();
+                }
+                b if b < 0x20 => return Err(self.source.error(ErrorKind::ControlCharacter)),
+                // `read_plain` stopped at a character split by the end of a
+                // chunk, `peek_char` waits for the rest of it
+                _ => {
+                    self.source.peek_char()?;
+                    return Err(self.source.error(ErrorKind::InvalidUtf8));
+                }
+            }
+        }
+    }
+
+    /// read a string, number or literal starting with `first`,
+    /// strings are left in `self.string`
+    pub fn parse_scalar(&mut self, first: u8) -> Result<Token, JsonError> {
+        Ok(match first {
+            b'"' => {
+                self.parse_string()?;
+                Token::String
+            }
+            b'\'' if self.options.allow_single_quotes => {
+                self.parse_string()?;
+                Token::String
+            }
+            b'N' if self.options.allow_nan_infinity => {
+                self.parse_literal(b"NaN")?;
+                Token::Number(f64::NAN)
+            }
+            b'I' if self.options.allow_nan_infinity => {
+                self.parse_literal(b"Infinity")?;
+                Token::Number(f64::INFINITY)
+            }
+            b'-' | b'0'..=b'9' => Token::Number(self.parse_number()?),
+            b'+' | b'.' if self.options.json5 => Token::Number(self.parse_number()?),
+            b'n' => {
+                self.parse_literal(b"null")?;
+                Token::Null
+            }
+            b't' => {
+                self.parse_literal(b"true")?;
+                Token::Bool(true)
+            }
+            b'f' => {
+                self.parse_literal(b"false")?;
+                Token::Bool(false)
+            }
+            _ => return Err(self.unexpected()),
+        })
+    }
+
+    /// whether an unquoted key may start with this byte
+    pub fn starts_identifier(&self, b: u8) -> bool {
+        is_identifier_start(b) || (self.options.json5 && (b == b'\\' || b >= 0x80))
+    }
+
+    /// the escapes json5 adds to json: `\v`, `\0`, `\xHH`, line continuations
+    /// and any other character standing for itself
+    fn parse_json5_escape(&mut self, s: &mut String) -> Result<(), JsonError> {
+        match self.peek_byte()? {
+            b'v' => s.push('\u{0b}'),
+            b'0' => {
+                self.source.bump();
+                if self.is_digit()? {
+                    return Err(self.source.error(ErrorKind::InvalidEscape));
+                }
+                s.push('\0');
+                return Ok(());
+            }
+            b'x' => {
+                self.source.bump();
+                let mut code = 0;
+                for _ in 0..2 {
+                    match (self.peek_byte()? as char).to_digit(16) {
+                        Some(d) => code = code * 16 + d,
+                        None => return Err(self.source.error(ErrorKind::InvalidEscape)),
+                    }
+                    self.source.bump();
+                }
+                s.push(char::from_u32(code).unwrap_or_default());
+                return Ok(());
+            }
+            b'\r' => {
+                self.source.bump();
+                if self.source.peek()? == Some(b'\n') {
+                    self.source.bump();
+                }
+                return Ok(());
+            }
+            b'\n' => (),
+            b'1'..=b'9' => return Err(self.source.error(ErrorKind::InvalidEscape)),
+            b if b < 0x80 => s.push(b as char),
+            _ => match self.source.peek_char()? {
+                Some(c) => {
+                    // an escaped line or paragraph separator is a line continuation
+                    if c != '\u{2028}' && c != '\u{2029}' {
+                        s.push(c);
+                    }
+                    self.bump_char(c);
+                    return Ok(());
+                }
+                None => return Err(self.source.unexpected_char()),
+            },
+        }
+        self.source.bump();
+        Ok(())
+    }
+
+    /// read an unquoted object key like `key_1` or `$ref` into `self.string`,
+    /// json5 also allows unicode letters and `\uXXXX` escapes
+    pub fn parse_identifier(&mut self) -> Result<(), JsonError> {
+        self.string.clear();
+        loop {
+            match self.source.peek()? {
+                Some(b)
+                    if is_identifier_start(b)
+                        || (b.is_ascii_digit() && !self.string.is_empty()) =>
+                {
+                    self.string.push(b as char);
+                    self.source.bump();
+                }
+                Some(b'\\') if self.options.json5 => {
+                    self.source.bump();
+                    if self.source.peek()? != Some(b'u') {
+                        return Err(self.source.error(ErrorKind::InvalidEscape));
+                    }
+                    self.source.bump();
+                    let c = self.parse_unicode_escape()?;
+                    self.string.push(c);
+                }
+                Some(0x80..) if self.options.json5 => match self.source.peek_char()? {
+                    Some(c) if is_identifier_char(c, self.string.is_empty()) => {
+                        self.string.push(c);
+                        self.bump_char(c);
+                    }
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+        if self.string.is_empty() {
+            return Err(self.unexpected());
+        }
+        Ok(())
+    }
+
+    /// the part of a `\uXXXX` escape after the `u`, may be followed by the low half
+    /// of a surrogate pair
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.parse_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.source.peek()? != Some(b'\\') {
+                return Err(self.source.error(ErrorKind::InvalidUnicode));
+            }
+            self.source.bump();
+            if self.source.peek()? != Some(b'u') {
+                return Err(self.source.error(ErrorKind::InvalidUnicode));
+            }
+            self.source.bump();
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.source.error(ErrorKind::InvalidUnicode));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        match char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => Err(self.source.error(ErrorKind::InvalidUnicode)),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match (self.peek_byte()? as char).to_digit(16) {
+                Some(d) => code = code * 16 + d,
+                None => return Err(self.source.error(ErrorKind::InvalidEscape)),
+            }
+            self.source.bump();
+        }
+        Ok(code)
+    }
+}
+
+fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'$'
+}
+
+/// whether `s` can be written as an unquoted json5 key
+pub fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    let first = match chars.next() {
+        Some(c) if c.is_ascii() => is_identifier_start(c as u8),
+        Some(c) => is_identifier_char(c, true),
+        None => false,
+    };
+    first
+        && chars.all(|c| {
+            if c.is_ascii() {
+                is_identifier_start(c as u8) || c.is_ascii_digit()
+            } else {
+                is_identifier_char(c, false)
+            }
+        })
+}
+
+/// an identifier character that is not ascii, approximating ID_Start and ID_Continue
+fn is_identifier_char(c: char, first: bool) -> bool {
+    c.is_alphabetic() || (!first && (c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'))
+}
+
+/// whitespace characters that json5 allows besides the json ones
+fn is_json5_space(c: char) -> bool {
+    matches!(
+        c,
+        '\u{a0}' | '\u{feff}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200a}' | '\u{2028}' | '\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+    )
+}
@@ -1,19 +1,61 @@
+mod access;
 mod convert;
+mod diff;
+mod document;
+mod error;
+mod jmespath;
+mod jq;
+mod jsonc;
+mod lexer;
 mod macros;
+mod map;
+mod merge;
+mod options;
+mod parser;
+mod patch;
+mod path;
+mod pointer;
+mod push;
+mod query;
+mod read;
+mod reader;
+mod regex;
+mod ser;
 mod utils;
 
+use map::Map;
+use parser::build_json;
 use std::{
-    collections::{
-        hash_map::{IntoIter, Iter},
-        HashMap,
-    },
-    ops::Index,
+    collections::VecDeque,
+    io,
+    ops::{Index, IndexMut},
 };
-use utils::*;
+
+pub use diff::{diff, diff_with, unified_diff, ArrayDiff, Change, DiffOptions};
+pub use document::JsonDocument;
+pub use error::{ErrorKind, JsonError, PatchError, PathError, QueryError};
+pub use jmespath::JmesPath;
+pub use jq::JqFilter;
+pub use jsonc::{Comments, JsoncDocument, ValueMut};
+pub use map::{
+    Drain, Entry, ObjectIntoIter, ObjectIter, ObjectIterMut, OccupiedEntry, VacantEntry,
+};
+pub use merge::{merge_layers, ArrayMerge, MergeOptions, MergeReport, Provenance, TypeConflict};
+pub use options::ParseOptions;
+pub use patch::{apply_patch, create_merge_patch, merge_patch, JsonPatch, PatchOperation};
+pub use path::{JsonPath, Segment, ToPath};
+pub use pointer::JsonPointer;
+pub use push::JsonPushParser;
+pub use query::JsonQuery;
+pub use read::{IoSource, PushSource, SliceSource};
+pub use reader::{Event, JsonReader, Scalar};
+pub use ser::{serialize, SerializeOptions};
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::useless_vec)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn parse_json() {
@@ -27,6 +69,124 @@ mod tests {
         assert_eq!(json, espect);
     }
 
+    #[test]
+    fn try_parse_json() {
+        let json = try_parse(r#"{ "a": [1, true, null] }"#).unwrap();
+        assert_eq!(json, parse(r#"{"a":[1,true,null]}"#));
+
+        let err = try_parse(r#"{ "a": [1, 2"#).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedEof));
+
+        let err = "[1, nul]".try_to_json().unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidLiteral));
+        assert_eq!(err.offset(), Some(7));
+
+        let err = try_parse("{\n  \"a\": @\n}").unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedChar('@')));
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.column(), Some(8));
+
+        for truncated in ["", "{", "[", "\"abc", "{\"a\"", "{\"a\":", "tr"] {
+            assert!(try_parse(truncated).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_numbers() {
+        let cases = [
+            ("0", 0.0),
+            ("-0", -0.0),
+            ("-1", -1.0),
+            ("12.5", 12.5),
+            ("1e10", 1e10),
+            ("2.5E-3", 2.5e-3),
+            ("-3.25e+2", -325.0),
+        ];
+        for (s, espect) in cases {
+            assert_eq!(parse(s), JsonType::Number(espect));
+        }
+        let json = parse("[-1, 1e10, 2.5E-3, 1e300, -4.5e-12]");
+        assert_eq!(json.serialize(), "[-1,10000000000,0.0025,1e300,-4.5e-12]");
+        assert_eq!(parse(&json.serialize()), json);
+
+        for s in [
+            "01", "-", "-a", "1.", "1.2.3", "1e", "1e+", "+1", ".5", "1ee5", "--1",
+        ] {
+            let err = try_parse(s).unwrap_err();
+            assert!(
+                matches!(
+                    err.kind(),
+                    Some(ErrorKind::InvalidNumber | ErrorKind::UnexpectedChar(_))
+                ),
+                "{} should be rejected",
+                s
+            );
+        }
+        assert_eq!(JsonType::Number(f64::NAN).serialize(), "null");
+    }
+
+    #[test]
+    fn overflowing_numbers() {
+        for s in ["1e400", "-1e400", "[1, 2e999]"] {
+            let err = try_parse(s).unwrap_err();
+            assert_eq!(err.kind(), Some(&ErrorKind::InvalidNumber), "{}", s);
+        }
+        assert_eq!(parse("1e308"), JsonType::Number(1e308));
+        assert_eq!(parse("1e-400"), JsonType::Number(0.0));
+
+        let options = ParseOptions {
+            json5: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with("1e400", &options).unwrap(),
+            JsonType::Number(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let json = parse(r#"["a\nb", "\"quoted\" \\ \/", "é😀", "tab\there"]"#);
+        let espect = json_arr!["a\nb", "\"quoted\" \\ /", "é😀", "tab\there"];
+        assert_eq!(json, espect);
+        assert_eq!(
+            json.serialize(),
+            r#"["a\nb","\"quoted\" \\ /","é😀","tab\there"]"#
+        );
+        assert_eq!(parse(&json.serialize()), json);
+
+        let json = parse(r#"{ "k\"eyA": "\u0001" }"#);
+        assert_eq!(json["k\"eyA"], JsonType::from("\u{1}"));
+        assert_eq!(json.serialize(), r#"{"k\"eyA":"\u0001"}"#);
+
+        // a quote is only escaped by an odd number of backslashes
+        assert_eq!(parse(r#""aaaa\"""#), JsonType::from("aaaa\""));
+        assert_eq!(parse(r#""aaa\\""#), JsonType::from("aaa\\"));
+
+        let err = try_parse(r#""\x""#).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidEscape));
+        let err = try_parse(r#""\ud83d""#).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidUnicode));
+        let err = try_parse(r#""\ude00""#).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidUnicode));
+        let err = try_parse("\"a\nb\"").unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::ControlCharacter));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 10_000;
+        let s = "[".repeat(depth) + &"]".repeat(depth);
+        let mut json = parse(&s);
+        for _ in 1..depth {
+            json = match json {
+                JsonType::Array(mut arr) => arr.pop().unwrap(),
+                _ => panic!("expect nested arrays"),
+            };
+        }
+        assert_eq!(json, JsonType::Array(vec![]));
+    }
+
     #[test]
     fn get_path() {
         let s = r#"{ "a": { "b": 123, "c": [1, 2, 3] } }"#;
@@ -65,6 +225,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn index_or_null() {
+        let json = parse(r#"{ "a": { "b": 123, "c": [1, 2, 3] } }"#);
+        assert!(json["a"]["c"][3].is_null());
+        assert!(json["a"]["b"]["c"][0].is_null());
+        assert!(json[0].is_null());
+    }
+
+    #[test]
+    fn index_mut() {
+        let mut json = parse(r#"{ "a": { "b": 123, "c": [1, 2, 3] } }"#);
+        json["a"]["c"][1] = "two".into();
+        json["a"]["c"][3] = json_arr![];
+        json["a"]["c"][3][0]["k"] = 1.into();
+        json["new"]["x"] = json_null!();
+        assert_eq!(
+            json.serialize(),
+            r#"{"a":{"b":123,"c":[1,"two",3,[{"k":1}]]},"new":{"x":null}}"#
+        );
+
+        let result = std::panic::catch_unwind(|| {
+            let mut json = JsonType::from(1);
+            json["a"] = JsonType::Null;
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn index_past_the_end() {
+        let mut json = json_arr![1];
+        json[1] = 2.into();
+        assert_eq!(json.serialize(), "[1,2]");
+
+        let result = std::panic::catch_unwind(move || {
+            json[usize::MAX / 2] = 3.into();
+        });
+        assert!(result.is_err());
+    }
+
     #[test]
     fn serialize_json() {
         let s = r#"{ "a": { "c": [1, 2, "3"] } }"#;
@@ -135,6 +334,77 @@ mod tests {
             println!("{}: {}", key, value.serialize());
         }
     }
+
+    #[test]
+    fn object_map_api() {
+        let mut obj = JsonObject::with_capacity(4);
+        assert!(obj.is_empty());
+        obj.extend(vec![("c", 3), ("a", 1)]);
+        obj.extend(vec![("b".to_string(), 2)]);
+        assert_eq!(obj.len(), 3);
+        assert_eq!(obj.keys().collect::<Vec<_>>(), ["c", "a", "b"]);
+
+        *obj.get_mut("a").unwrap() = JsonType::from("one");
+        for value in obj.values_mut() {
+            if let JsonType::Number(num) = value {
+                *num *= 10.0;
+            }
+        }
+        for (key, value) in &mut obj {
+            if key == "b" {
+                *value = JsonType::Null;
+            }
+        }
+        let values: Vec<_> = obj.values().cloned().collect();
+        assert_eq!(
+            values,
+            [JsonType::from(30), JsonType::from("one"), JsonType::Null]
+        );
+
+        obj.entry("d").or_insert(4);
+        obj.entry("d")
+            .and_modify(|v| *v = JsonType::from(40))
+            .or_default();
+        obj.entry("e").or_default();
+        match obj.entry("c") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(false), JsonType::from(30));
+                assert_eq!(entry.remove(), JsonType::Bool(false));
+            }
+            Entry::Vacant(_) => panic!("c exists"),
+        }
+        obj.sort_keys();
+        let pairs: Vec<_> = obj.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        assert_eq!(pairs, ["a=\"one\"", "b=null", "d=40", "e=null"]);
+
+        obj.retain(|_, v| *v != JsonType::Null);
+        let drained: Vec<_> = obj.drain().map(|(k, _)| k).collect();
+        assert_eq!(drained, ["a", "d"]);
+        assert!(obj.is_empty());
+        assert_eq!(JsonObject::default(), obj);
+    }
+
+    #[test]
+    fn keep_key_order() {
+        let s = r#"{"z":1,"a":{"y":2,"b":3},"m":[{"k":4,"c":5}]}"#;
+        let json = parse(s);
+        assert_eq!(json.serialize(), s);
+        let keys: Vec<String> = json.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["z", "a", "m", "y", "b", "0", "k", "c"]);
+
+        let json = json_obj! {
+            "a" => json_arr![1, 2, 3],
+            "b" => json_obj! {
+                "c" => 4
+            }
+        };
+        let items: Vec<String> = json
+            .into_iter()
+            .map(|(key, value)| format!("{}: {}", key, value.serialize()))
+            .collect();
+        let espect = ["a: [1,2,3]", "b: {\"c\":4}", "0: 1", "1: 2", "2: 3", "c: 4"];
+        assert_eq!(items, espect);
+    }
 }
 
 /// JsonType can be use for defined any structure of json
@@ -194,52 +464,177 @@ pub enum JsonType {
     String(String),
 }
 
+/// returned by indexing when there is no value
+static NULL: JsonType = JsonType::Null;
+
+/// `json["key"]` is the member of an object, or `null` when the member does not
+/// exist or `json` is not an object, so lookups can be chained safely
+///
+/// ```
+/// use dynamic_json::{parse, JsonType};
+/// let json = parse(r#"{ "a": { "b": [1] } }"#);
+/// assert_eq!(json["a"]["b"][0], JsonType::from(1));
+/// assert_eq!(json["a"]["x"]["y"][3], JsonType::Null);
+/// ```
 impl Index<&str> for JsonType {
     type Output = JsonType;
     fn index(&self, index: &str) -> &Self::Output {
-        if let JsonType::Object(obj) = self {
-            obj.get(index).unwrap()
-        } else {
-            panic!("can not index by {}", index);
+        match self {
+            JsonType::Object(obj) => obj.get(index).unwrap_or(&NULL),
+            _ => &NULL,
         }
     }
 }
 
+/// `json[i]` is an element of an array, or `null` when it is out of range or
+/// `json` is not an array
 impl Index<usize> for JsonType {
     type Output = JsonType;
     fn index(&self, index: usize) -> &Self::Output {
-        if let JsonType::Array(arr) = self {
-            &arr[index]
-        } else {
-            panic!("can not index by {}", index);
+        match self {
+            JsonType::Array(arr) => arr.get(index).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+/// writing to `json["key"]` inserts the member when it is missing, a `null`
+/// value becomes an empty object first
+///
+/// ```
+/// use dynamic_json::{parse, JsonType};
+/// let mut json = JsonType::Null;
+/// json["a"]["b"] = 1.into();
+/// json["list"][0] = true.into();
+/// assert_eq!(json, parse(r#"{ "a": { "b": 1 }, "list": [true] }"#));
+/// ```
+///
+/// panics when `json` is neither an object nor `null`
+impl IndexMut<&str> for JsonType {
+    fn index_mut(&mut self, index: &str) -> &mut Self::Output {
+        if self.is_null() {
+            *self = JsonType::Object(JsonObject::new());
+        }
+        match self {
+            JsonType::Object(obj) => obj.entry(index).or_default(),
+            _ => panic!("can not index {} by {}", self.type_name(), index),
+        }
+    }
+}
+
+/// writing to `json[i]` at the length of an array appends, a `null` value
+/// becomes an empty array first
+///
+/// panics when `json` is neither an array nor `null`, or when `i` is past
+/// the length, as `Vec` does
+impl IndexMut<usize> for JsonType {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        if self.is_null() {
+            *self = JsonType::Array(vec![]);
+        }
+        match self {
+            JsonType::Array(arr) => {
+                let len = arr.len();
+                if index == len {
+                    arr.push(JsonType::Null);
+                } else if index > len {
+                    panic!(
+                        "index out of bounds: the len is {} but the index is {}",
+                        len, index
+                    );
+                }
+                &mut arr[index]
+            }
+            _ => panic!("can not index {} by {}", self.type_name(), index),
         }
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct JsonObject {
-    inner_map: HashMap<String, JsonType>,
+    inner_map: Map,
 }
 
 pub trait InsertJsonObject<T> {
     fn insert(&mut self, k: &str, v: T) -> Option<JsonType>;
 }
 
+impl Default for JsonObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl JsonObject {
     pub fn new() -> Self {
         JsonObject {
-            inner_map: HashMap::new(),
+            inner_map: Map::default(),
         }
     }
+    pub fn with_capacity(capacity: usize) -> Self {
+        JsonObject {
+            inner_map: Map::with_capacity(capacity),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.inner_map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inner_map.len() == 0
+    }
     pub fn get(&self, k: &str) -> Option<&JsonType> {
         self.inner_map.get(k)
     }
+    pub fn get_mut(&mut self, k: &str) -> Option<&mut JsonType> {
+        self.inner_map.get_mut(k)
+    }
+    /// remove a member, the members after it keep their order
     pub fn remove(&mut self, k: &str) -> Option<JsonType> {
         self.inner_map.remove(k)
     }
     pub fn contains(&self, k: &str) -> bool {
         self.inner_map.contains_key(k)
     }
+    /// the member with key `k` for in-place manipulation
+    ///
+    /// ```
+    /// use dynamic_json::{JsonObject, JsonType};
+    /// let mut counts = JsonObject::new();
+    /// for word in ["a", "b", "a"] {
+    ///     *counts.entry(word).or_insert(0).as_f64_mut().unwrap() += 1.0;
+    /// }
+    /// assert_eq!(counts.get("a"), Some(&JsonType::from(2)));
+    /// ```
+    pub fn entry<K: Into<String>>(&mut self, k: K) -> Entry<'_> {
+        self.inner_map.entry(k.into())
+    }
+    /// keep only the members for which `f` returns `true`
+    pub fn retain<F: FnMut(&String, &mut JsonType) -> bool>(&mut self, f: F) {
+        self.inner_map.retain(f)
+    }
+    /// remove all members, returning them in order
+    pub fn drain(&mut self) -> Drain<'_> {
+        self.inner_map.drain()
+    }
+    /// order the members by key
+    pub fn sort_keys(&mut self) {
+        self.inner_map.sort_keys()
+    }
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &String> + ExactSizeIterator {
+        self.inner_map.iter().map(|(k, _)| k)
+    }
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &JsonType> + ExactSizeIterator {
+        self.inner_map.iter().map(|(_, v)| v)
+    }
+    pub fn values_mut(&mut self) -> impl ExactSizeIterator<Item = &mut JsonType> {
+        self.inner_map.iter_mut().map(|(_, v)| v)
+    }
+    pub fn iter(&self) -> ObjectIter<'_> {
+        self.inner_map.iter()
+    }
+    pub fn iter_mut(&mut self) -> ObjectIterMut<'_> {
+        self.inner_map.iter_mut()
+    }
 }
 
 impl<T: Into<JsonType>> InsertJsonObject<T> for JsonObject {
@@ -250,7 +645,7 @@ impl<T: Into<JsonType>> InsertJsonObject<T> for JsonObject {
 
 impl IntoIterator for JsonObject {
     type Item = (String, JsonType);
-    type IntoIter = IntoIter<String, JsonType>;
+    type IntoIter = ObjectIntoIter;
     fn into_iter(self) -> Self::IntoIter {
         self.inner_map.into_iter()
     }
@@ -258,73 +653,59 @@ impl IntoIterator for JsonObject {
 
 impl<'a> IntoIterator for &'a JsonObject {
     type Item = (&'a String, &'a JsonType);
-    type IntoIter = Iter<'a, String, JsonType>;
+    type IntoIter = ObjectIter<'a>;
     fn into_iter(self) -> Self::IntoIter {
         self.inner_map.iter()
     }
 }
 
+impl<'a> IntoIterator for &'a mut JsonObject {
+    type Item = (&'a String, &'a mut JsonType);
+    type IntoIter = ObjectIterMut<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner_map.iter_mut()
+    }
+}
+
 enum InnerMap {
-    Map(HashMap<String, JsonType>),
-    List((Vec<JsonType>, usize)),
+    Map(ObjectIntoIter),
+    List(std::iter::Enumerate<std::vec::IntoIter<JsonType>>),
 }
 
+/// iterates over all members and elements in the tree, the entries of a
+/// container come in order before the entries of the containers inside it
 pub struct JsonIter {
-    nodes: Vec<InnerMap>,
+    nodes: VecDeque<InnerMap>,
 }
 
 impl Iterator for JsonIter {
     type Item = (String, JsonType);
     fn next(&mut self) -> Option<Self::Item> {
-        let last_node = self.nodes.pop();
-        if let Some(mut n) = last_node {
-            match n {
-                InnerMap::Map(ref mut hm) => {
-                    if hm.is_empty() {
-                        return self.next();
-                    }
-                    let (key, _) = hm.iter().next().unwrap();
-                    let key = key.clone();
-                    let item = hm.remove(&key).unwrap();
-                    match item {
-                        JsonType::Object(ref obj) => {
-                            self.nodes.push(InnerMap::Map(obj.inner_map.clone()));
-                        }
-                        JsonType::Array(ref arr) => {
-                            self.nodes.push(InnerMap::List((arr.clone(), 0)));
-                        }
-                        _ => (),
-                    }
-                    if !hm.is_empty() {
-                        self.nodes.push(n);
-                    }
-                    Some((key, item))
+        loop {
+            let node = self.nodes.front_mut()?;
+            let item = match node {
+                InnerMap::Map(iter) => iter.next(),
+                InnerMap::List(iter) => iter.next().map(|(idx, item)| (idx.to_string(), item)),
+            };
+            let (key, item) = match item {
+                Some(item) => item,
+                None => {
+                    self.nodes.pop_front();
+                    continue;
                 }
-                InnerMap::List((ref mut arr, ref mut idx)) => {
-                    if arr.is_empty() {
-                        return self.next();
-                    }
-                    arr.rotate_right(1);
-                    let item = arr.pop().unwrap();
-                    let old_idx = *idx;
-                    *idx += 1;
-                    match item {
-                        JsonType::Object(ref obj) => {
-                            self.nodes.push(InnerMap::Map(obj.inner_map.clone()));
-                        }
-                        JsonType::Array(ref arr) => {
-                            self.nodes.push(InnerMap::List((arr.clone(), 0)));
-                        }
-                        _ => (),
-                    }
-                    if !arr.is_empty() {
-                        self.nodes.push(n);
-                    }
-                    Some((old_idx.to_string(), item))
+            };
+            match item {
+                JsonType::Object(ref obj) => {
+                    self.nodes
+                        .push_back(InnerMap::Map(obj.inner_map.clone().into_iter()));
                 }
+                JsonType::Array(ref arr) => {
+                    self.nodes
+                        .push_back(InnerMap::List(arr.clone().into_iter().enumerate()));
+                }
+                _ => (),
             }
-        } else {
-            None
+            return Some((key, item));
         }
     }
 }
@@ -333,22 +714,41 @@ impl IntoIterator for JsonType {
     type Item = (String, JsonType);
     type IntoIter = JsonIter;
     fn into_iter(self) -> Self::IntoIter {
-        let nodes = match self {
-            JsonType::Object(obj) => {
-                vec![InnerMap::Map(obj.inner_map)]
-            }
-            JsonType::Array(arr) => {
-                vec![InnerMap::List((arr, 0))]
-            }
-            _ => vec![]
+        let mut nodes = VecDeque::new();
+        match self {
+            JsonType::Object(obj) => nodes.push_back(InnerMap::Map(obj.inner_map.into_iter())),
+            JsonType::Array(arr) => nodes.push_back(InnerMap::List(arr.into_iter().enumerate())),
+            _ => (),
         };
-        JsonIter {
-            nodes
-        }
+        JsonIter { nodes }
     }
 }
 
 impl JsonType {
+    /// parse json from a reader such as a file or stdin, the input is consumed
+    /// through a fixed size buffer instead of being read into memory first
+    ///
+    /// ```
+    /// use dynamic_json::{ErrorKind, JsonType};
+    /// let input = std::io::Cursor::new(r#"{ "a": [1, 2, 3] }"#);
+    /// let json = JsonType::from_reader(input).unwrap();
+    /// assert_eq!(json["a"][2], JsonType::Number(3.0));
+    ///
+    /// let err = JsonType::from_reader(&b"[1, 2"[..]).unwrap_err();
+    /// assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedEof));
+    /// ```
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<JsonType, JsonError> {
+        build_json(&mut JsonReader::from_reader(reader))
+    }
+
+    /// same as [`JsonType::from_reader`] with extensions to strict json enabled
+    pub fn from_reader_with<R: io::Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<JsonType, JsonError> {
+        build_json(&mut JsonReader::from_reader(reader).with_options(options))
+    }
+
     /// serialize a Json Object to string
     pub fn serialize(&self) -> String {
         serialize(self, 0, 0)
@@ -359,113 +759,9 @@ impl JsonType {
         serialize(self, indent, indent)
     }
 
-    /// get sub item inside a JsonType
-    ///
-    /// samples:
-    ///
-    /// ```
-    /// use dynamic_json::{JsonType, parse};
-    /// let s = r#"{ "a": { "b": 123, "c": [1, 2, 3] } }"#;
-    /// let json = parse(&s);
-    /// let b = json.get("a.b").unwrap();
-    /// if let JsonType::Number(num) = b {
-    ///     assert_eq!(*num, 123f64);
-    /// } else {
-    ///     panic!("get item error");
-    /// }
-
-    /// let value = json.get("a.c.1").unwrap();
-    /// if let JsonType::Number(num) = value {
-    ///     assert_eq!(*num, 2f64);
-    /// } else {
-    ///     panic!("get item error");
-    /// }
-    /// ```
-    pub fn get(&self, path: &str) -> Result<&JsonType, String> {
-        let path_list: Vec<&str> = path.split('.').into_iter().collect();
-        let mut node = self;
-        let mut deep = 0;
-        for key in path_list.iter() {
-            deep += 1;
-            match node {
-                JsonType::Object(obj) => {
-                    if let Some(value) = obj.get(*key) {
-                        node = value;
-                        continue;
-                    } else {
-                        let p = path_list.clone();
-                        let s = p[0..deep].join(".");
-                        return Err(format!("can not found item {}", &s));
-                    }
-                }
-                JsonType::Array(arr) => {
-                    if let Ok(idx) = key.parse::<usize>() {
-                        node = &arr[idx];
-                    } else {
-                        let p = path_list.clone();
-                        let s = p[0..deep].join(".");
-                        return Err(format!("can not found item {}", &s));
-                    }
-                }
-                _ => {
-                    let p = path_list.clone();
-                    let s = p[0..deep].join(".");
-                    return Err(format!("can not found item {}", &s));
-                }
-            }
-        }
-
-        Ok(node)
-    }
-}
-
-pub fn serialize(json: &JsonType, indent: u32, acc_indent: u32) -> String {
-    let wrap = if indent > 0 {
-        " ".repeat(acc_indent as usize)
-    } else {
-        "".to_string()
-    };
-    let wrap_start = if indent > 0 { "\n" } else { "" };
-    let wrap_end = if indent > 0 {
-        "\n".to_string() + &" ".repeat((acc_indent - indent) as usize)
-    } else {
-        "".to_string()
-    };
-    let gap = if indent > 0 { " " } else { "" };
-    let sep = format!(",{}", wrap_start);
-    match json {
-        JsonType::Null => "null".to_string(),
-        JsonType::Number(num) => num.to_string(),
-        JsonType::String(s) => {
-            format!(r#""{}""#, s)
-        }
-        JsonType::Bool(boolean) => boolean.to_string(),
-        JsonType::Object(obj) => {
-            let mut items: Vec<String> = vec![];
-            for (key, value) in obj {
-                items.push(format!(
-                    r#"{}"{}":{}{}"#,
-                    wrap,
-                    key,
-                    gap,
-                    serialize(&value, indent, acc_indent + indent),
-                ));
-            }
-
-            let s = format!("{{{}{}{}}}", wrap_start, items.join(&sep), wrap_end);
-            s
-        }
-        JsonType::Array(arr) => {
-            let mut items = vec![];
-            for item in arr {
-                items.push(format!(
-                    "{}{}",
-                    wrap,
-                    serialize(item, indent, acc_indent + indent)
-                ));
-            }
-            format!("[{}{}{}]", wrap_start, items.join(&sep), wrap_end)
-        }
+    /// serialize a Json Object to string in the format described by `options`
+    pub fn serialize_with(&self, options: &SerializeOptions) -> String {
+        ser::serialize_with(self, options, options.indent)
     }
 }
 
@@ -481,107 +777,88 @@ impl ToJson for String {
 
 impl ToJson for &str {
     fn to_json(&self) -> JsonType {
-        parse(*self)
+        parse(self)
     }
 }
 
-pub fn parse(s: &str) -> JsonType {
-    let mut chars: Vec<char> = s.chars().collect();
-    let (json, _) = dynamic_json(&mut chars, 0);
-    json
+/// fallible version of [`ToJson`]
+pub trait TryToJson {
+    fn try_to_json(&self) -> Result<JsonType, JsonError>;
 }
 
-fn dynamic_json(chars: &mut Vec<char>, start: usize) -> (JsonType, usize) {
-    let mut idx = start;
+impl TryToJson for String {
+    fn try_to_json(&self) -> Result<JsonType, JsonError> {
+        try_parse(self)
+    }
+}
 
-    skip_spaces(chars, &mut idx);
+impl TryToJson for &str {
+    fn try_to_json(&self) -> Result<JsonType, JsonError> {
+        try_parse(self)
+    }
+}
 
-    let c = chars[idx];
+/// parse a string to JsonType
+///
+/// panics if the string is not valid json, use [`try_parse`] for untrusted input
+pub fn parse(s: &str) -> JsonType {
+    match try_parse(s) {
+        Ok(json) => json,
+        Err(err) => panic!("{}", err),
+    }
+}
 
-    match c {
-        '{' => {
-            let mut obj = JsonObject::new();
+/// parse a string to JsonType, returns a [`JsonError`] instead of panicking
+///
+/// ```
+/// use dynamic_json::{try_parse, ErrorKind};
+/// let err = try_parse(r#"{ "a": [1, 2"#).unwrap_err();
+/// assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedEof));
+/// assert_eq!(err.offset(), Some(12));
+/// ```
+pub fn try_parse(s: &str) -> Result<JsonType, JsonError> {
+    parse_bytes(s.as_bytes())
+}
 
-            idx += 1;
+/// parse utf-8 encoded bytes to JsonType without copying them into a `String` first,
+/// only the content of strings is validated as utf-8
+///
+/// ```
+/// use dynamic_json::{parse_bytes, ErrorKind, JsonType};
+/// let json = parse_bytes(b"{ \"a\": \"caf\xc3\xa9\" }").unwrap();
+/// assert_eq!(json["a"], JsonType::from("café"));
+///
+/// let err = parse_bytes(b"[\"\xff\"]").unwrap_err();
+/// assert_eq!(err.kind(), Some(&ErrorKind::InvalidUtf8));
+/// ```
+pub fn parse_bytes(bytes: &[u8]) -> Result<JsonType, JsonError> {
+    build_json(&mut JsonReader::from_bytes(bytes))
+}
 
-            while chars[idx] != '}' {
-                skip_spaces(chars, &mut idx);
-                if chars[idx] == '}' {
-                    break;
-                }
-                skip_chars(chars, &mut idx, &['"']);
-                let mut key = String::new();
-                while !is_valid_ending_quote(&chars, idx) {
-                    key.push(chars[idx]);
-                    idx += 1;
-                }
-                idx += 1;
-                skip_util_char(chars, &mut idx, &[':']);
-                idx += 1;
-                let (child, new_idx) = dynamic_json(chars, idx);
-                idx = new_idx;
-                obj.insert(&key, child);
-                skip_chars(chars, &mut idx, &[' ', '\r', '\n', ',', '"']);
-            }
-            return (JsonType::Object(obj), idx + 1);
-        }
-        '[' => {
-            let mut arr = Vec::<JsonType>::new();
-            idx += 1;
-            while chars[idx] != ']' {
-                skip_spaces(chars, &mut idx);
-                if chars[idx] == ']' {
-                    break;
-                }
-                let (child, new_idx) = dynamic_json(chars, idx);
-                idx = new_idx;
-                arr.push(child);
-                skip_chars(chars, &mut idx, &[',', ' ', '\r', '\n']);
-            }
-            (JsonType::Array(arr), idx + 1)
-        }
-        '0'..='9' => {
-            let mut i = idx;
-            while (chars[i] <= '9' && chars[i] >= '0') || chars[i] == '.' {
-                i += 1;
-            }
-            let s: String = chars[idx..i].iter().collect();
-            let num: f64 = s.trim().parse().unwrap();
-            return (JsonType::Number(num), i);
-        }
-        '"' => {
-            let mut i = idx + 1;
-            while !is_valid_ending_quote(chars, i) {
-                i += 1;
-            }
-            let s: String = chars[idx + 1..i].iter().collect();
-            i += 1;
-            return (JsonType::String(s), i);
-        }
-        'n' => {
-            if match_literal(chars, idx, "null") {
-                idx += 4;
-                skip_spaces(chars, &mut idx);
-                return (JsonType::Null, idx);
-            }
-            parse_error(chars, idx);
-        }
-        't' => {
-            if match_literal(chars, idx, "true") {
-                idx += 4;
-                skip_spaces(chars, &mut idx);
-                return (JsonType::Bool(true), idx);
-            }
-            parse_error(chars, idx);
-        }
-        'f' => {
-            if match_literal(chars, idx, "false") {
-                idx += 5;
-                skip_spaces(chars, &mut idx);
-                return (JsonType::Bool(false), idx);
-            }
-            parse_error(chars, idx);
-        }
-        _ => parse_error(chars, idx),
-    }
+/// parse a string with the extensions to strict json enabled in `options`
+///
+/// ```
+/// use dynamic_json::{parse_with, ParseOptions, JsonType};
+/// let json = parse_with("{ name: 'x', /* ok */ }", &ParseOptions::lenient()).unwrap();
+/// assert_eq!(json["name"], JsonType::from("x"));
+/// ```
+pub fn parse_with(s: &str, options: &ParseOptions) -> Result<JsonType, JsonError> {
+    parse_bytes_with(s.as_bytes(), options)
+}
+
+/// parse a JSON5 document, see [`ParseOptions::json5`]
+///
+/// ```
+/// use dynamic_json::{parse_json5, JsonType};
+/// let json = parse_json5("{ hex: 0xFF, half: .5, 'quoted': 'it\\'s', list: [1, 2,], }").unwrap();
+/// assert_eq!(json["hex"], JsonType::from(255));
+/// assert_eq!(json["quoted"], JsonType::from("it's"));
+/// ```
+pub fn parse_json5(s: &str) -> Result<JsonType, JsonError> {
+    parse_with(s, &ParseOptions::json5())
+}
+
+/// parse bytes with the extensions to strict json enabled in `options`
+pub fn parse_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<JsonType, JsonError> {
+    build_json(&mut JsonReader::from_bytes(bytes).with_options(options))
 }
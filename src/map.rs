@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::JsonType;
+
+/// objects with fewer members are searched linearly, which is faster than
+/// hashing for the small objects most documents consist of
+const INDEX_FROM: usize = 8;
+
+/// the storage of a `JsonObject`, entries stay in the order their keys were
+/// first inserted while lookups go through a hash index of their positions
+#[derive(Clone, Default)]
+pub(crate) struct Map {
+    entries: Vec<(String, JsonType)>,
+    /// empty until the map has `INDEX_FROM` entries
+    indices: HashMap<String, usize>,
+}
+
+impl Map {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn index_of(&self, k: &str) -> Option<usize> {
+        if self.entries.len() < INDEX_FROM {
+            self.entries.iter().position(|(key, _)| key == k)
+        } else {
+            self.indices.get(k).copied()
+        }
+    }
+
+    /// rebuild the hash index after the entries moved
+    fn reindex(&mut self) {
+        self.indices.clear();
+        if self.entries.len() >= INDEX_FROM {
+            for (i, (key, _)) in self.entries.iter().enumerate() {
+                self.indices.insert(key.clone(), i);
+            }
+        }
+    }
+
+    pub fn get(&self, k: &str) -> Option<&JsonType> {
+        let index = self.index_of(k)?;
+        Some(&self.entries[index].1)
+    }
+
+    pub fn get_key_value(&self, k: &str) -> Option<(&String, &JsonType)> {
+        let (key, value) = &self.entries[self.index_of(k)?];
+        Some((key, value))
+    }
+
+    pub fn get_mut(&mut self, k: &str) -> Option<&mut JsonType> {
+        let index = self.index_of(k)?;
+        Some(&mut self.entries[index].1)
+    }
+
+    pub fn contains_key(&self, k: &str) -> bool {
+        self.index_of(k).is_some()
+    }
+
+    /// a new key is appended, the value of an existing key is replaced in place
+    pub fn insert(&mut self, k: String, v: JsonType) -> Option<JsonType> {
+        if let Some(index) = self.index_of(&k) {
+            return Some(std::mem::replace(&mut self.entries[index].1, v));
+        }
+        self.entries.push((k, v));
+        let len = self.entries.len();
+        if len == INDEX_FROM {
+            self.reindex();
+        } else if len > INDEX_FROM {
+            self.indices
+                .insert(self.entries[len - 1].0.clone(), len - 1);
+        }
+        None
+    }
+
+    /// remove a key, the entries after it move up so the order is kept
+    pub fn remove(&mut self, k: &str) -> Option<JsonType> {
+        let index = self.index_of(k)?;
+        let (_, value) = self.entries.remove(index);
+        if self.entries.len() < INDEX_FROM {
+            self.indices.clear();
+        } else {
+            self.indices.remove(k);
+            for (key, _) in &self.entries[index..] {
+                if let Some(i) = self.indices.get_mut(key) {
+                    *i -= 1;
+                }
+            }
+        }
+        Some(value)
+    }
+
+    pub fn entry(&mut self, k: String) -> Entry<'_> {
+        match self.index_of(&k) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key: k }),
+        }
+    }
+
+    pub fn retain<F: FnMut(&String, &mut JsonType) -> bool>(&mut self, mut f: F) {
+        let len = self.entries.len();
+        self.entries.retain_mut(|(k, v)| f(k, v));
+        if self.entries.len() != len {
+            self.reindex();
+        }
+    }
+
+    pub fn drain(&mut self) -> Drain<'_> {
+        self.indices.clear();
+        Drain {
+            inner: self.entries.drain(..),
+        }
+    }
+
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.reindex();
+    }
+
+    pub fn iter(&self) -> ObjectIter<'_> {
+        ObjectIter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> ObjectIterMut<'_> {
+        ObjectIterMut {
+            inner: self.entries.iter_mut(),
+        }
+    }
+}
+
+/// a member of a `JsonObject` that may or may not exist, see `JsonObject::entry`
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+pub struct OccupiedEntry<'a> {
+    map: &'a mut Map,
+    index: usize,
+}
+
+pub struct VacantEntry<'a> {
+    map: &'a mut Map,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &String {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert<T: Into<JsonType>>(self, default: T) -> &'a mut JsonType {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<T: Into<JsonType>, F: FnOnce() -> T>(
+        self,
+        default: F,
+    ) -> &'a mut JsonType {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// insert `null` if the member does not exist
+    pub fn or_default(self) -> &'a mut JsonType {
+        self.or_insert(JsonType::Null)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut JsonType)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn key(&self) -> &String {
+        &self.map.entries[self.index].0
+    }
+
+    pub fn get(&self) -> &JsonType {
+        &self.map.entries[self.index].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut JsonType {
+        &mut self.map.entries[self.index].1
+    }
+
+    pub fn into_mut(self) -> &'a mut JsonType {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// replace the value, returning the old one
+    pub fn insert<T: Into<JsonType>>(&mut self, value: T) -> JsonType {
+        std::mem::replace(self.get_mut(), value.into())
+    }
+
+    pub fn remove(self) -> JsonType {
+        let key = self.map.entries[self.index].0.clone();
+        self.map.remove(&key).unwrap_or(JsonType::Null)
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+
+    pub fn insert<T: Into<JsonType>>(self, value: T) -> &'a mut JsonType {
+        self.map.insert(self.key, value.into());
+        let last = self.map.entries.len() - 1;
+        &mut self.map.entries[last].1
+    }
+}
+
+impl PartialEq for Map {
+    /// objects are equal when they have the same members in any order
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, JsonType);
+    type IntoIter = ObjectIntoIter;
+    fn into_iter(self) -> Self::IntoIter {
+        ObjectIntoIter {
+            inner: self.entries.into_iter(),
+        }
+    }
+}
+
+/// iterator over the members of a `JsonObject` in insertion order
+pub struct ObjectIter<'a> {
+    inner: std::slice::Iter<'a, (String, JsonType)>,
+}
+
+impl<'a> Iterator for ObjectIter<'a> {
+    type Item = (&'a String, &'a JsonType);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ObjectIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl ExactSizeIterator for ObjectIter<'_> {}
+
+/// iterator over the members of a `JsonObject` with mutable values
+pub struct ObjectIterMut<'a> {
+    inner: std::slice::IterMut<'a, (String, JsonType)>,
+}
+
+impl<'a> Iterator for ObjectIterMut<'a> {
+    type Item = (&'a String, &'a mut JsonType);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ObjectIterMut<'_> {}
+
+/// removes and yields every member of a `JsonObject` in insertion order
+pub struct Drain<'a> {
+    inner: std::vec::Drain<'a, (String, JsonType)>,
+}
+
+impl Iterator for Drain<'_> {
+    type Item = (String, JsonType);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Drain<'_> {}
+
+/// owning iterator over the members of a `JsonObject` in insertion order
+pub struct ObjectIntoIter {
+    inner: std::vec::IntoIter<(String, JsonType)>,
+}
+
+impl Iterator for ObjectIntoIter {
+    type Item = (String, JsonType);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ObjectIntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl ExactSizeIterator for ObjectIntoIter {}
+
+#[cfg(test)]
+mod test {
+    use super::Map;
+    use crate::JsonType;
+
+    #[test]
+    fn keep_insertion_order() {
+        let mut map = Map::default();
+        for (i, k) in ["z", "a", "m", "b"].iter().enumerate() {
+            map.insert(k.to_string(), JsonType::from(i as i32));
+        }
+        map.insert("a".to_string(), JsonType::Null);
+        assert_eq!(map.remove("z"), Some(JsonType::from(0)));
+        assert_eq!(map.remove("z"), None);
+        map.insert("z".to_string(), JsonType::Bool(true));
+
+        let keys: Vec<_> = map.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["a", "m", "b", "z"]);
+        assert_eq!(map.get("a"), Some(&JsonType::Null));
+        assert_eq!(map.get("b"), Some(&JsonType::from(3)));
+        assert_eq!(map.get("z"), Some(&JsonType::Bool(true)));
+
+        // large enough to be hashed
+        for i in 0..20 {
+            map.insert(i.to_string(), JsonType::from(i));
+        }
+        for i in (0..20).step_by(3) {
+            assert_eq!(map.remove(&i.to_string()), Some(JsonType::from(i)));
+        }
+        for i in 0..20 {
+            let espect = if i % 3 == 0 {
+                None
+            } else {
+                Some(JsonType::from(i))
+            };
+            assert_eq!(map.get(&i.to_string()), espect.as_ref());
+        }
+        assert_eq!(
+            map.iter().nth(4),
+            Some((&"1".to_string(), &JsonType::from(1)))
+        );
+        while map.len() > 2 {
+            let key = map.iter().next().unwrap().0.clone();
+            map.remove(&key);
+        }
+        assert_eq!(map.get("19"), Some(&JsonType::from(19)));
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+
+use crate::error::PathError;
+use crate::path::{JsonPath, Segment, ToPath};
+use crate::JsonType;
+
+/// how `deep_merge` combines two arrays
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum ArrayMerge {
+    /// the array of the other value replaces the existing one
+    #[default]
+    Replace,
+    /// the elements of the other array are appended
+    Append,
+    /// like `Append`, without the elements that are already there
+    Union,
+    /// elements at the same index are merged, extra elements are appended
+    ByIndex,
+    /// objects with the same value of this member, like `"id"`, are merged,
+    /// the other elements are appended
+    ByKey(String),
+}
+
+/// what `deep_merge` does when the two values at a path have different types
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum TypeConflict {
+    /// the other value wins
+    #[default]
+    Replace,
+    /// the existing value is kept
+    Keep,
+    /// the merge fails with `PathError::TypeMismatch`
+    Error,
+}
+
+/// strategies of `deep_merge`, the rules for a path override the global
+/// strategy for the value at exactly that path
+///
+/// ```
+/// use dynamic_json::{parse, ArrayMerge, MergeOptions, TypeConflict};
+/// let options = MergeOptions {
+///     arrays: ArrayMerge::Union,
+///     conflicts: TypeConflict::Error,
+///     array_rules: vec![("servers".parse().unwrap(), ArrayMerge::ByKey("name".to_string()))],
+///     ..Default::default()
+/// };
+/// let mut config = parse(r#"{ "servers": [{ "name": "a", "port": 80 }], "tags": ["x"] }"#);
+/// let cli = parse(r#"{ "servers": [{ "name": "a", "port": 8080 }], "tags": ["x", "y"] }"#);
+/// config.deep_merge(&cli, &options).unwrap();
+/// assert_eq!(config, parse(r#"{ "servers": [{ "name": "a", "port": 8080 }], "tags": ["x", "y"] }"#));
+/// assert!(config.deep_merge(&parse(r#"{ "tags": "z" }"#), &options).is_err());
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub conflicts: TypeConflict,
+    pub array_rules: Vec<(JsonPath, ArrayMerge)>,
+    pub conflict_rules: Vec<(JsonPath, TypeConflict)>,
+}
+
+impl MergeOptions {
+    fn arrays_at(&self, path: &JsonPath) -> &ArrayMerge {
+        let rule = self.array_rules.iter().find(|(p, _)| p == path);
+        rule.map_or(&self.arrays, |(_, merge)| merge)
+    }
+
+    fn conflicts_at(&self, path: &JsonPath) -> TypeConflict {
+        let rule = self.conflict_rules.iter().find(|(p, _)| p == path);
+        rule.map_or(self.conflicts, |(_, conflict)| *conflict)
+    }
+}
+
+/// the result of a `deep_merge`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MergeReport {
+    merged: Vec<JsonPath>,
+}
+
+impl MergeReport {
+    /// the paths of the scalars and empty containers that hold a value of
+    /// the other document after the merge
+    pub fn paths(&self) -> &[JsonPath] {
+        &self.merged
+    }
+}
+
+impl JsonType {
+    /// merge `other` into this value: objects are merged member by member,
+    /// arrays and type conflicts follow `options` and any other value of
+    /// `other` replaces the existing one. if the merge fails the value is
+    /// left unchanged
+    ///
+    /// ```
+    /// use dynamic_json::{parse, MergeOptions};
+    /// let mut json = parse(r#"{ "log": { "level": "info", "file": "a.log" }, "ports": [80] }"#);
+    /// let other = parse(r#"{ "log": { "level": "debug" }, "ports": [8080] }"#);
+    /// let report = json.deep_merge(&other, &MergeOptions::default()).unwrap();
+    /// assert_eq!(json, parse(r#"{ "log": { "level": "debug", "file": "a.log" }, "ports": [8080] }"#));
+    /// let paths: Vec<String> = report.paths().iter().map(|p| p.to_string()).collect();
+    /// assert_eq!(paths, ["log.level", "ports[0]"]);
+    /// ```
+    pub fn deep_merge(
+        &mut self,
+        other: &JsonType,
+        options: &MergeOptions,
+    ) -> Result<MergeReport, PathError> {
+        let mut merged = self.clone();
+        let mut report = MergeReport::default();
+        merge_into(
+            &mut merged,
+            other,
+            &mut JsonPath::root(),
+            options,
+            &mut report.merged,
+        )?;
+        *self = merged;
+        Ok(report)
+    }
+}
+
+fn merge_into(
+    base: &mut JsonType,
+    other: &JsonType,
+    path: &mut JsonPath,
+    options: &MergeOptions,
+    merged: &mut Vec<JsonPath>,
+) -> Result<(), PathError> {
+    match (base, other) {
+        (JsonType::Object(a), JsonType::Object(b)) => {
+            for (key, value) in b {
+                path.push(Segment::Key(key.clone()));
+                match a.get_mut(key) {
+                    Some(existing) => merge_into(existing, value, path, options, merged)?,
+                    None => {
+                        a.inner_map.insert(key.clone(), value.clone());
+                        leaves(value, path, merged);
+                    }
+                }
+                path.pop();
+            }
+        }
+        (JsonType::Array(a), JsonType::Array(b)) => match options.arrays_at(path) {
+            ArrayMerge::Replace => {
+                *a = b.clone();
+                leaves(other, path, merged);
+            }
+            ArrayMerge::Append => append(a, b.iter(), path, merged),
+            ArrayMerge::Union => {
+                let mut added = vec![];
+                for value in b {
+                    if !a.contains(value) && !added.contains(&value) {
+                        added.push(value);
+                    }
+                }
+                append(a, added.into_iter(), path, merged);
+            }
+            ArrayMerge::ByIndex => {
+                for (i, value) in b.iter().enumerate().take(a.len()) {
+                    path.push(Segment::Index(i));
+                    merge_into(&mut a[i], value, path, options, merged)?;
+                    path.pop();
+                }
+                let len = a.len();
+                append(a, b.iter().skip(len), path, merged);
+            }
+            ArrayMerge::ByKey(key) => {
+                let key_of = |value: &JsonType| value.as_object()?.get(key).cloned();
+                let mut added = vec![];
+                for value in b {
+                    let found = key_of(value)
+                        .and_then(|k| a.iter().position(|item| key_of(item).as_ref() == Some(&k)));
+                    match found {
+                        Some(i) => {
+                            path.push(Segment::Index(i));
+                            merge_into(&mut a[i], value, path, options, merged)?;
+                            path.pop();
+                        }
+                        None => added.push(value),
+                    }
+                }
+                append(a, added.into_iter(), path, merged);
+            }
+        },
+        (base, other) if base.type_name() != other.type_name() => {
+            match options.conflicts_at(path) {
+                TypeConflict::Replace => {
+                    *base = other.clone();
+                    leaves(other, path, merged);
+                }
+                TypeConflict::Keep => {}
+                TypeConflict::Error => {
+                    return Err(PathError::TypeMismatch {
+                        path: path.to_string(),
+                        expected: base.type_name(),
+                        found: other.type_name(),
+                    })
+                }
+            }
+        }
+        (base, other) => {
+            *base = other.clone();
+            leaves(other, path, merged);
+        }
+    }
+    Ok(())
+}
+
+fn append<'a, I: Iterator<Item = &'a JsonType>>(
+    a: &mut Vec<JsonType>,
+    values: I,
+    path: &mut JsonPath,
+    merged: &mut Vec<JsonPath>,
+) {
+    for value in values {
+        path.push(Segment::Index(a.len()));
+        leaves(value, path, merged);
+        path.pop();
+        a.push(value.clone());
+    }
+}
+
+/// the paths of the scalars and empty containers in `value`
+fn leaves(value: &JsonType, path: &mut JsonPath, out: &mut Vec<JsonPath>) {
+    match value {
+        JsonType::Object(obj) if !obj.is_empty() => {
+            for (key, value) in obj {
+                path.push(Segment::Key(key.clone()));
+                leaves(value, path, out);
+                path.pop();
+            }
+        }
+        JsonType::Array(arr) if !arr.is_empty() => {
+            for (i, value) in arr.iter().enumerate() {
+                path.push(Segment::Index(i));
+                leaves(value, path, out);
+                path.pop();
+            }
+        }
+        _ => out.push(path.clone()),
+    }
+}
+
+/// the source every value of a merged document came from, see `merge_layers`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Provenance {
+    sources: Vec<(JsonPath, String)>,
+}
+
+impl Provenance {
+    /// the source of the value at `path`, for an object or array the source
+    /// all of its values came from, `None` if they came from different sources
+    /// or there is no value at `path`
+    pub fn source<P: ToPath + ?Sized>(&self, path: &P) -> Option<&str> {
+        let path = path.to_path().ok()?;
+        let mut sources = self
+            .sources
+            .iter()
+            .filter(|(p, _)| p.segments().starts_with(path.segments()))
+            .map(|(_, source)| source.as_str());
+        let first = sources.next()?;
+        sources.all(|source| source == first).then_some(first)
+    }
+
+    /// every scalar and empty container with its source, in document order
+    pub fn iter(&self) -> impl Iterator<Item = (&JsonPath, &str)> {
+        self.sources
+            .iter()
+            .map(|(path, source)| (path, source.as_str()))
+    }
+}
+
+/// deep merge named layers in order, later layers win, and report which
+/// layer every value came from
+///
+/// ```
+/// use dynamic_json::{merge_layers, parse, MergeOptions};
+/// let defaults = parse(r#"{ "host": "localhost", "port": 80, "log": { "level": "info" } }"#);
+/// let env = parse(r#"{ "port": 8080 }"#);
+/// let cli = parse(r#"{ "log": { "level": "debug" } }"#);
+/// let layers = [("defaults", &defaults), ("env", &env), ("cli", &cli)];
+/// let (config, provenance) = merge_layers(&layers, &MergeOptions::default()).unwrap();
+/// assert_eq!(config, parse(r#"{ "host": "localhost", "port": 8080, "log": { "level": "debug" } }"#));
+/// assert_eq!(provenance.source("host"), Some("defaults"));
+/// assert_eq!(provenance.source("port"), Some("env"));
+/// assert_eq!(provenance.source("log"), Some("cli"));
+/// assert_eq!(provenance.source(""), None);
+/// ```
+pub fn merge_layers(
+    layers: &[(&str, &JsonType)],
+    options: &MergeOptions,
+) -> Result<(JsonType, Provenance), PathError> {
+    let mut json = JsonType::Null;
+    let mut origins = HashMap::new();
+    for (i, (_, layer)) in layers.iter().enumerate() {
+        let report = match i {
+            0 => {
+                json = (*layer).clone();
+                let mut paths = vec![];
+                leaves(layer, &mut JsonPath::root(), &mut paths);
+                MergeReport { merged: paths }
+            }
+            _ => json.deep_merge(layer, options)?,
+        };
+        for path in report.merged {
+            origins.insert(path, i);
+        }
+    }
+    let mut paths = vec![];
+    leaves(&json, &mut JsonPath::root(), &mut paths);
+    let sources = paths
+        .into_iter()
+        .filter_map(|path| {
+            let layer = *origins.get(&path)?;
+            Some((path, layers[layer].0.to_string()))
+        })
+        .collect();
+    Ok((json, Provenance { sources }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merge_layers, ArrayMerge, MergeOptions, TypeConflict};
+    use crate::{parse, PathError};
+
+    #[test]
+    fn array_strategies() {
+        let base =
+            r#"{ "a": [1, 2, { "id": 1, "v": 1 }], "b": [{ "id": 1, "v": 1 }, { "id": 2 }] }"#;
+        let other =
+            r#"{ "a": [2, 3, { "id": 1, "w": 2 }], "b": [{ "id": 2, "v": 2 }, { "id": 3 }, 4] }"#;
+        let cases = [
+            (
+                ArrayMerge::Replace,
+                r#"{ "a": [2, 3, { "id": 1, "w": 2 }], "b": [{ "id": 2, "v": 2 }, { "id": 3 }, 4] }"#,
+            ),
+            (
+                ArrayMerge::Append,
+                r#"{ "a": [1, 2, { "id": 1, "v": 1 }, 2, 3, { "id": 1, "w": 2 }],
+                     "b": [{ "id": 1, "v": 1 }, { "id": 2 }, { "id": 2, "v": 2 }, { "id": 3 }, 4] }"#,
+            ),
+            (
+                ArrayMerge::Union,
+                r#"{ "a": [1, 2, { "id": 1, "v": 1 }, 3, { "id": 1, "w": 2 }],
+                     "b": [{ "id": 1, "v": 1 }, { "id": 2 }, { "id": 2, "v": 2 }, { "id": 3 }, 4] }"#,
+            ),
+            (
+                ArrayMerge::ByIndex,
+                r#"{ "a": [2, 3, { "id": 1, "v": 1, "w": 2 }],
+                     "b": [{ "id": 2, "v": 2 }, { "id": 3 }, 4] }"#,
+            ),
+            (
+                ArrayMerge::ByKey("id".to_string()),
+                r#"{ "a": [1, 2, { "id": 1, "v": 1, "w": 2 }, 2, 3],
+                     "b": [{ "id": 1, "v": 1 }, { "id": 2, "v": 2 }, { "id": 3 }, 4] }"#,
+            ),
+        ];
+        for (arrays, espect) in cases {
+            let options = MergeOptions {
+                arrays: arrays.clone(),
+                ..Default::default()
+            };
+            let mut json = parse(base);
+            json.deep_merge(&parse(other), &options).unwrap();
+            assert_eq!(json, parse(espect), "{:?}", arrays);
+        }
+
+        // a rule for one path, the global strategy everywhere else
+        let options = MergeOptions {
+            arrays: ArrayMerge::Append,
+            array_rules: vec![("b".parse().unwrap(), ArrayMerge::ByKey("id".to_string()))],
+            ..Default::default()
+        };
+        let mut json = parse(base);
+        let report = json.deep_merge(&parse(other), &options).unwrap();
+        assert_eq!(json["a"].as_array().unwrap().len(), 6);
+        assert_eq!(
+            json["b"],
+            parse(r#"[{ "id": 1, "v": 1 }, { "id": 2, "v": 2 }, { "id": 3 }, 4]"#)
+        );
+        let paths: Vec<String> = report.paths().iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            paths,
+            ["a[3]", "a[4]", "a[5].id", "a[5].w", "b[1].id", "b[1].v", "b[2].id", "b[3]"]
+        );
+    }
+
+    #[test]
+    fn conflicts_and_provenance() {
+        let base = parse(r#"{ "a": { "b": 1 }, "c": [1], "d": "x" }"#);
+        let other = parse(r#"{ "a": 2, "c": { "e": 3 }, "d": null }"#);
+        let mut json = base.clone();
+        json.deep_merge(&other, &MergeOptions::default()).unwrap();
+        assert_eq!(json, other);
+
+        let options = MergeOptions {
+            conflicts: TypeConflict::Keep,
+            conflict_rules: vec![("d".parse().unwrap(), TypeConflict::Replace)],
+            ..Default::default()
+        };
+        let mut json = base.clone();
+        json.deep_merge(&other, &options).unwrap();
+        assert_eq!(json, parse(r#"{ "a": { "b": 1 }, "c": [1], "d": null }"#));
+
+        let options = MergeOptions {
+            conflicts: TypeConflict::Error,
+            ..Default::default()
+        };
+        let mut json = base.clone();
+        let err = json.deep_merge(&parse(r#"{ "d": "y", "a": { "b": [] } }"#), &options);
+        assert_eq!(
+            err.unwrap_err(),
+            PathError::TypeMismatch {
+                path: "a.b".to_string(),
+                expected: "number",
+                found: "array"
+            }
+        );
+        // nothing is merged when the merge fails
+        assert_eq!(json, base);
+
+        let defaults = parse(r#"{ "db": { "host": "h", "port": 1 }, "list": [1, 2], "x": {} }"#);
+        let file = parse(r#"{ "db": { "port": 2 }, "list": [3] }"#);
+        let cli = parse(r#"{ "db": { "port": 3, "user": "u" }, "x": { "y": true } }"#);
+        let options = MergeOptions {
+            arrays: ArrayMerge::Append,
+            ..Default::default()
+        };
+        let layers = [("defaults", &defaults), ("file", &file), ("cli", &cli)];
+        let (json, provenance) = merge_layers(&layers, &options).unwrap();
+        assert_eq!(
+            json,
+            parse(
+                r#"{ "db": { "host": "h", "port": 3, "user": "u" }, "list": [1, 2, 3], "x": { "y": true } }"#
+            )
+        );
+        let sources: Vec<String> = provenance
+            .iter()
+            .map(|(path, source)| format!("{}={}", path, source))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "db.host=defaults",
+                "db.port=cli",
+                "db.user=cli",
+                "list[0]=defaults",
+                "list[1]=defaults",
+                "list[2]=file",
+                "x.y=cli",
+            ]
+        );
+        assert_eq!(provenance.source("list"), None);
+        assert_eq!(provenance.source("x"), Some("cli"));
+        assert_eq!(provenance.source("missing"), None);
+        assert_eq!(merge_layers(&[], &options).unwrap().0, parse("null"));
+    }
+}
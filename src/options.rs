@@ -0,0 +1,186 @@
+/// controls which extensions to RFC 8259 the parser accepts,
+/// the default is strict json with every extension turned off
+///
+/// ```
+/// use dynamic_json::{parse_with, ParseOptions};
+/// let options = ParseOptions {
+///     allow_comments: true,
+///     allow_trailing_commas: true,
+///     ..Default::default()
+/// };
+/// let json = parse_with("[1, 2, /* three */ 3,]", &options).unwrap();
+/// assert_eq!(json.serialize(), "[1,2,3]");
+///
+/// assert!(parse_with("[1, 2, 3,]", &ParseOptions::default()).is_err());
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ParseOptions {
+    /// `[1, 2,]` and `{"a": 1,}`
+    pub allow_trailing_commas: bool,
+    /// `// line` and `/* block */` comments wherever whitespace is allowed
+    pub allow_comments: bool,
+    /// strings and keys quoted with `'`, in which `\'` is a valid escape
+    pub allow_single_quotes: bool,
+    /// object keys written as identifiers, like `{ key: 1 }`
+    pub allow_unquoted_keys: bool,
+    /// the literals `NaN`, `Infinity` and `-Infinity`
+    pub allow_nan_infinity: bool,
+    /// anything after the root value is ignored instead of being an error
+    pub allow_trailing_content: bool,
+    /// the parts of the JSON5 grammar not covered by the other options: hex numbers,
+    /// a leading `+` or leading/trailing `.` in numbers, `\v`, `\0`, `\xHH` and line
+    /// continuations in strings, unicode identifiers and unicode whitespace
+    pub json5: bool,
+}
+
+impl ParseOptions {
+    /// RFC 8259, same as `ParseOptions::default()`
+    pub fn strict() -> Self {
+        Self::default()
+    }
+
+    /// every extension turned on
+    pub fn lenient() -> Self {
+        ParseOptions {
+            allow_trailing_content: true,
+            ..Self::json5()
+        }
+    }
+
+    /// the complete JSON5 grammar, see <https://spec.json5.org>
+    pub fn json5() -> Self {
+        ParseOptions {
+            allow_trailing_commas: true,
+            allow_comments: true,
+            allow_single_quotes: true,
+            allow_unquoted_keys: true,
+            allow_nan_infinity: true,
+            allow_trailing_content: false,
+            json5: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParseOptions;
+    use crate::{json_arr, parse_with, ErrorKind, JsonObject, JsonPushParser, JsonType};
+
+    type Case = (&'static str, fn(&mut ParseOptions), JsonType);
+
+    fn only(set: fn(&mut ParseOptions)) -> ParseOptions {
+        let mut options = ParseOptions::default();
+        set(&mut options);
+        options
+    }
+
+    #[test]
+    fn each_extension() {
+        let cases: [Case; 6] = [
+            (
+                "[1, [2,],]",
+                |o| o.allow_trailing_commas = true,
+                json_arr![1, json_arr![2]],
+            ),
+            (
+                "// a\n[1, /* b */ 2] /**/",
+                |o| o.allow_comments = true,
+                json_arr![1, 2],
+            ),
+            (
+                r#"['a"b\'c', "d'"]"#,
+                |o| o.allow_single_quotes = true,
+                json_arr!["a\"b'c", "d'"],
+            ),
+            (
+                "[{ a_1: 1, $b: 2 }]",
+                |o| o.allow_unquoted_keys = true,
+                json_arr![vec![("a_1", 1), ("$b", 2)]
+                    .into_iter()
+                    .collect::<JsonObject>()],
+            ),
+            (
+                "[Infinity, -Infinity]",
+                |o| o.allow_nan_infinity = true,
+                json_arr![f64::INFINITY, f64::NEG_INFINITY],
+            ),
+            ("[1] [2]", |o| o.allow_trailing_content = true, json_arr![1]),
+        ];
+        for (input, set, espect) in cases {
+            assert!(
+                parse_with(input, &ParseOptions::strict()).is_err(),
+                "{}",
+                input
+            );
+            assert_eq!(parse_with(input, &only(set)).unwrap(), espect, "{}", input);
+            assert_eq!(
+                parse_with(input, &ParseOptions::lenient()).unwrap(),
+                espect,
+                "{}",
+                input
+            );
+        }
+        let nan = parse_with("NaN", &only(|o| o.allow_nan_infinity = true)).unwrap();
+        assert!(matches!(nan, JsonType::Number(num) if num.is_nan()));
+    }
+
+    #[test]
+    fn json5_syntax() {
+        let json5 = ParseOptions::json5();
+        let cases = [
+            ("0x1F", 31.0),
+            ("-0XaB", -171.0),
+            ("+1", 1.0),
+            (".5", 0.5),
+            ("5.", 5.0),
+            ("+.5e1", 5.0),
+            ("+Infinity", f64::INFINITY),
+            ("-Infinity", f64::NEG_INFINITY),
+        ];
+        for (input, espect) in cases {
+            assert_eq!(parse_with(input, &json5).unwrap(), JsonType::from(espect));
+            assert!(
+                parse_with(input, &ParseOptions::strict()).is_err(),
+                "{}",
+                input
+            );
+        }
+        let json = parse_with("-NaN", &json5).unwrap();
+        assert!(matches!(json, JsonType::Number(num) if num.is_nan()));
+
+        let json = parse_with(
+            "\u{feff}{\u{a0}ünïcode: 1,\u{2028}\u{0b}\\u0061b: 'x\\x41\\\n\\0\\v',}",
+            &json5,
+        )
+        .unwrap();
+        assert_eq!(json["ünïcode"], JsonType::from(1));
+        assert_eq!(json["ab"], JsonType::from("xA\0\u{b}"));
+
+        for input in ["01", ".", "1..", "0x", "'\\1'", "{ 1a: 1 }"] {
+            assert!(parse_with(input, &json5).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn strict_errors() {
+        let strict = ParseOptions::strict();
+        let err = parse_with("{} x", &strict).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::TrailingCharacters));
+        assert_eq!(err.offset(), Some(3));
+        assert!(parse_with("[1] \n\t ", &strict).is_ok());
+
+        let comments = only(|o| o.allow_comments = true);
+        let err = parse_with("[1 /* never closed ]", &comments).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedEof));
+        let err = parse_with("[1 / 2]", &comments).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedChar(' ')));
+
+        let err = JsonType::from_reader(&b"[1]]"[..]).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::TrailingCharacters));
+
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"[1] ").unwrap();
+        let err = parser.feed(b" 2").unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::TrailingCharacters));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::error::{ErrorKind, JsonError};
+use crate::read::Source;
+use crate::reader::{Event, JsonReader};
+use crate::{JsonObject, JsonType};
+
+/// assembles a JsonType from a stream of events, nesting is tracked on the heap
+/// so deep documents can not overflow the stack
+#[derive(Default)]
+pub struct TreeBuilder {
+    /// open containers together with the key they will be stored under
+    stack: Vec<(JsonType, Option<String>)>,
+    key: Option<String>,
+    root: Option<JsonType>,
+}
+
+impl TreeBuilder {
+    pub fn push(&mut self, event: Event<'_>) {
+        let value = match event {
+            Event::StartObject => {
+                let key = self.key.take();
+                self.stack.push((JsonType::Object(JsonObject::new()), key));
+                return;
+            }
+            Event::StartArray => {
+                let key = self.key.take();
+                self.stack.push((JsonType::Array(vec![]), key));
+                return;
+            }
+            Event::Key(k) => {
+                self.key = Some(k.to_string());
+                return;
+            }
+            Event::Value(scalar) => JsonType::from(scalar),
+            Event::EndObject | Event::EndArray => match self.stack.pop() {
+                Some((container, parent_key)) => {
+                    self.key = parent_key;
+                    container
+                }
+                None => return,
+            },
+        };
+        match self.stack.last_mut() {
+            Some((JsonType::Object(obj), _)) => {
+                obj.inner_map
+                    .insert(self.key.take().unwrap_or_default(), value);
+            }
+            Some((JsonType::Array(arr), _)) => arr.push(value),
+            _ => self.root = Some(value),
+        }
+    }
+
+    /// the root value once its last event was pushed
+    pub fn take_root(&mut self) -> Option<JsonType> {
+        self.root.take()
+    }
+}
+
+/// build a JsonType from all events of `reader`
+pub fn build_json<S: Source>(reader: &mut JsonReader<S>) -> Result<JsonType, JsonError> {
+    let mut builder = TreeBuilder::default();
+    while let Some(event) = reader.next_event()? {
+        builder.push(event);
+    }
+    match builder.take_root() {
+        Some(root) => Ok(root),
+        None => Err(reader.error(ErrorKind::UnexpectedEof)),
+    }
+}
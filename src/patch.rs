@@ -0,0 +1,759 @@
+use crate::error::{PatchError, PathError};
+use crate::pointer::JsonPointer;
+use crate::{InsertJsonObject, JsonObject, JsonType};
+
+/// one operation of a JSON Patch
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchOperation {
+    Add {
+        path: JsonPointer,
+        value: JsonType,
+    },
+    Remove {
+        path: JsonPointer,
+    },
+    Replace {
+        path: JsonPointer,
+        value: JsonType,
+    },
+    Move {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Copy {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Test {
+        path: JsonPointer,
+        value: JsonType,
+    },
+}
+
+impl PatchOperation {
+    /// the `op` member, like `"add"`
+    pub fn op(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+
+    fn parse(json: &JsonType) -> Result<PatchOperation, String> {
+        let obj = json.as_object().ok_or("an operation must be an object")?;
+        let pointer = |name: &str| match obj.get(name) {
+            Some(JsonType::String(s)) => {
+                JsonPointer::parse(s).map_err(|_| format!("`{}` is not a JSON Pointer", name))
+            }
+            Some(_) => Err(format!("`{}` must be a string", name)),
+            None => Err(format!("missing `{}`", name)),
+        };
+        let value = || obj.get("value").cloned().ok_or("missing `value`");
+        Ok(match obj.get("op").and_then(|op| op.as_str()) {
+            Some("add") => PatchOperation::Add {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some("remove") => PatchOperation::Remove {
+                path: pointer("path")?,
+            },
+            Some("replace") => PatchOperation::Replace {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some("move") => PatchOperation::Move {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            Some("copy") => PatchOperation::Copy {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            Some("test") => PatchOperation::Test {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some(op) => return Err(format!("unknown op `{}`", op)),
+            None => return Err("missing `op`".to_string()),
+        })
+    }
+
+    pub fn to_json(&self) -> JsonType {
+        let mut obj = JsonObject::new();
+        obj.insert("op", self.op());
+        if let PatchOperation::Move { from, .. } | PatchOperation::Copy { from, .. } = self {
+            obj.insert("from", from.to_string());
+        }
+        obj.insert("path", self.path().to_string());
+        if let PatchOperation::Add { value, .. }
+        | PatchOperation::Replace { value, .. }
+        | PatchOperation::Test { value, .. } = self
+        {
+            obj.insert("value", value.clone());
+        }
+        JsonType::Object(obj)
+    }
+}
+
+/// a JSON Patch document as defined by RFC 6902
+///
+/// ```
+/// use dynamic_json::{apply_patch, parse, JsonPatch};
+/// let mut json = parse(r#"{ "a": 1, "list": [1, 2] }"#);
+/// let patch = JsonPatch::parse(&parse(r#"[
+///     { "op": "test", "path": "/a", "value": 1 },
+///     { "op": "add", "path": "/list/-", "value": 3 },
+///     { "op": "move", "from": "/a", "path": "/b" }
+/// ]"#)).unwrap();
+/// apply_patch(&mut json, &patch).unwrap();
+/// assert_eq!(json, parse(r#"{ "list": [1, 2, 3], "b": 1 }"#));
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct JsonPatch {
+    operations: Vec<PatchOperation>,
+}
+
+impl JsonPatch {
+    pub fn new() -> Self {
+        JsonPatch::default()
+    }
+
+    /// read a patch document, an array of operation objects
+    pub fn parse(json: &JsonType) -> Result<JsonPatch, PatchError> {
+        let operations = json.as_array().ok_or_else(|| PatchError::Invalid {
+            index: 0,
+            message: "a patch must be an array".to_string(),
+        })?;
+        let operations = operations.iter().enumerate().map(|(index, op)| {
+            PatchOperation::parse(op).map_err(|message| PatchError::Invalid { index, message })
+        });
+        operations.collect()
+    }
+
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    pub fn push(&mut self, operation: PatchOperation) {
+        self.operations.push(operation);
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn to_json(&self) -> JsonType {
+        self.operations
+            .iter()
+            .map(PatchOperation::to_json)
+            .collect()
+    }
+
+    /// a patch that turns `from` into `to`
+    ///
+    /// objects are compared member by member, arrays after dropping their
+    /// common head and tail, anything else that differs is replaced
+    ///
+    /// ```
+    /// use dynamic_json::{parse, JsonPatch};
+    /// let from = parse(r#"{ "a": 1, "b": [1, 2, 3], "c": true }"#);
+    /// let to = parse(r#"{ "a": 2, "b": [1, 3], "d": null }"#);
+    /// let patch = JsonPatch::diff(&from, &to);
+    /// assert_eq!(patch.to_json().serialize(), concat!(
+    ///     r#"[{"op":"remove","path":"/c"},{"op":"replace","path":"/a","value":2},"#,
+    ///     r#"{"op":"remove","path":"/b/1"},{"op":"add","path":"/d","value":null}]"#
+    /// ));
+    /// ```
+    pub fn diff(from: &JsonType, to: &JsonType) -> JsonPatch {
+        let mut patch = JsonPatch::new();
+        diff_values(from, to, &mut JsonPointer::root(), &mut patch.operations);
+        patch
+    }
+}
+
+impl FromIterator<PatchOperation> for JsonPatch {
+    fn from_iter<T: IntoIterator<Item = PatchOperation>>(iter: T) -> Self {
+        JsonPatch {
+            operations: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl TryFrom<&JsonType> for JsonPatch {
+    type Error = PatchError;
+    fn try_from(json: &JsonType) -> Result<Self, Self::Error> {
+        JsonPatch::parse(json)
+    }
+}
+
+/// apply every operation of `patch` in order, if one fails `json` is left
+/// unchanged
+pub fn apply_patch(json: &mut JsonType, patch: &JsonPatch) -> Result<(), PatchError> {
+    let mut patched = json.clone();
+    for (index, operation) in patch.operations.iter().enumerate() {
+        apply(&mut patched, operation, index)?;
+    }
+    *json = patched;
+    Ok(())
+}
+
+/// apply a JSON Merge Patch (RFC 7386), members of an object patch are
+/// merged recursively and `null` removes them, any other patch replaces
+/// the value
+///
+/// ```
+/// use dynamic_json::{merge_patch, parse};
+/// let mut json = parse(r#"{ "title": "Goodbye!", "author": { "givenName": "John", "familyName": "Doe" } }"#);
+/// merge_patch(&mut json, &parse(r#"{ "title": "Hello!", "author": { "familyName": null } }"#));
+/// assert_eq!(json, parse(r#"{ "title": "Hello!", "author": { "givenName": "John" } }"#));
+/// ```
+pub fn merge_patch(json: &mut JsonType, patch: &JsonType) {
+    let members = match patch {
+        JsonType::Object(members) => members,
+        _ => {
+            *json = patch.clone();
+            return;
+        }
+    };
+    if !json.is_object() {
+        *json = JsonType::Object(JsonObject::new());
+    }
+    let obj = json.as_object_mut().unwrap();
+    for (key, value) in members {
+        match value {
+            JsonType::Null => {
+                obj.remove(key);
+            }
+            value => merge_patch(obj.entry(key.as_str()).or_insert(JsonType::Null), value),
+        }
+    }
+}
+
+/// the smallest merge patch that turns `old` into `new`
+///
+/// a merge patch can not set a member to `null` or keep `null` members in
+/// an added object, `null` always means removal
+///
+/// ```
+/// use dynamic_json::{create_merge_patch, merge_patch, parse};
+/// let old = parse(r#"{ "a": 1, "b": { "c": 2, "d": 3 }, "e": [1] }"#);
+/// let new = parse(r#"{ "a": 1, "b": { "c": 4 }, "e": [1, 2] }"#);
+/// let patch = create_merge_patch(&old, &new);
+/// assert_eq!(patch.serialize(), r#"{"b":{"d":null,"c":4},"e":[1,2]}"#);
+/// let mut json = old.clone();
+/// merge_patch(&mut json, &patch);
+/// assert_eq!(json, new);
+/// ```
+pub fn create_merge_patch(old: &JsonType, new: &JsonType) -> JsonType {
+    let (old, new) = match (old, new) {
+        (JsonType::Object(old), JsonType::Object(new)) => (old, new),
+        _ => return new.clone(),
+    };
+    let mut patch = JsonObject::new();
+    for key in old.keys().filter(|key| !new.contains(key)) {
+        patch.insert(key, JsonType::Null);
+    }
+    for (key, value) in new {
+        match old.get(key) {
+            Some(old) if old == value => {}
+            Some(old) => {
+                patch.insert(key, create_merge_patch(old, value));
+            }
+            None => {
+                patch.insert(key, value.clone());
+            }
+        }
+    }
+    JsonType::Object(patch)
+}
+
+impl JsonType {
+    /// see `apply_patch`
+    pub fn apply_patch(&mut self, patch: &JsonPatch) -> Result<(), PatchError> {
+        apply_patch(self, patch)
+    }
+
+    /// see `merge_patch`
+    pub fn merge_patch(&mut self, patch: &JsonType) {
+        merge_patch(self, patch)
+    }
+}
+
+fn apply(json: &mut JsonType, operation: &PatchOperation, index: usize) -> Result<(), PatchError> {
+    let path_error = |error| PatchError::Path { index, error };
+    match operation {
+        PatchOperation::Add { path, value } => add(json, path, value.clone()).map_err(path_error),
+        PatchOperation::Remove { path } => remove(json, path).map(drop).map_err(path_error),
+        PatchOperation::Replace { path, value } => {
+            *resolve_mut(json, path).map_err(path_error)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if from == path {
+                return resolve(json, from).map(drop).map_err(path_error);
+            }
+            if path.tokens().starts_with(from.tokens()) {
+                return Err(PatchError::Invalid {
+                    index,
+                    message: "can not move a value into itself".to_string(),
+                });
+            }
+            let value = remove(json, from).map_err(path_error)?;
+            add(json, path, value).map_err(path_error)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = resolve(json, from).map_err(path_error)?.clone();
+            add(json, path, value).map_err(path_error)
+        }
+        PatchOperation::Test { path, value } => match resolve(json, path).map_err(path_error)? {
+            found if found == value => Ok(()),
+            _ => Err(PatchError::TestFailed {
+                index,
+                path: path.to_string(),
+            }),
+        },
+    }
+}
+
+/// the first `len` tokens of `pointer` as a string
+fn prefix(pointer: &JsonPointer, len: usize) -> String {
+    let tokens = pointer.tokens()[..len].iter().cloned();
+    tokens.collect::<JsonPointer>().to_string()
+}
+
+fn child<'a>(node: &'a JsonType, token: &str) -> Option<&'a JsonType> {
+    match node {
+        JsonType::Object(obj) => obj.get(token),
+        JsonType::Array(arr) => arr.get(JsonPointer::index(token)?),
+        _ => None,
+    }
+}
+
+fn child_mut<'a>(node: &'a mut JsonType, token: &str) -> Option<&'a mut JsonType> {
+    match node {
+        JsonType::Object(obj) => obj.get_mut(token),
+        JsonType::Array(arr) => arr.get_mut(JsonPointer::index(token)?),
+        _ => None,
+    }
+}
+
+/// why token `i` of `pointer` does not resolve in `node`
+fn step_error(node: &JsonType, pointer: &JsonPointer, i: usize) -> PathError {
+    let path = prefix(pointer, i + 1);
+    match node {
+        JsonType::Object(_) => PathError::NotFound { path },
+        JsonType::Array(arr) => match JsonPointer::index(&pointer.tokens()[i]) {
+            Some(index) => PathError::IndexOutOfRange {
+                path,
+                index,
+                len: arr.len(),
+            },
+            None => PathError::InvalidIndex { path },
+        },
+        other => PathError::TypeMismatch {
+            path: prefix(pointer, i),
+            expected: "object or array",
+            found: other.type_name(),
+        },
+    }
+}
+
+fn resolve<'a>(json: &'a JsonType, pointer: &JsonPointer) -> Result<&'a JsonType, PathError> {
+    let mut node = json;
+    for (i, token) in pointer.tokens().iter().enumerate() {
+        node = child(node, token).ok_or_else(|| step_error(node, pointer, i))?;
+    }
+    Ok(node)
+}
+
+fn resolve_mut<'a>(
+    json: &'a mut JsonType,
+    pointer: &JsonPointer,
+) -> Result<&'a mut JsonType, PathError> {
+    let mut node = json;
+    for (i, token) in pointer.tokens().iter().enumerate() {
+        if child(node, token).is_none() {
+            return Err(step_error(node, pointer, i));
+        }
+        node = child_mut(node, token).unwrap();
+    }
+    Ok(node)
+}
+
+/// insert into an object or array, `-` appends to an array
+fn add(json: &mut JsonType, pointer: &JsonPointer, value: JsonType) -> Result<(), PathError> {
+    let (last, parent) = match pointer.tokens().split_last() {
+        Some(split) => split,
+        None => {
+            *json = value;
+            return Ok(());
+        }
+    };
+    let parent_pointer: JsonPointer = parent.iter().cloned().collect();
+    match resolve_mut(json, &parent_pointer)? {
+        JsonType::Object(obj) => {
+            obj.insert(last, value);
+        }
+        JsonType::Array(arr) if last == "-" => arr.push(value),
+        JsonType::Array(arr) => {
+            let path = pointer.to_string();
+            let index = JsonPointer::index(last).ok_or(PathError::InvalidIndex { path })?;
+            if index > arr.len() {
+                return Err(PathError::IndexOutOfRange {
+                    path: pointer.to_string(),
+                    index,
+                    len: arr.len(),
+                });
+            }
+            arr.insert(index, value);
+        }
+        other => {
+            return Err(PathError::TypeMismatch {
+                path: parent_pointer.to_string(),
+                expected: "object or array",
+                found: other.type_name(),
+            })
+        }
+    }
+    Ok(())
+}
+
+fn remove(json: &mut JsonType, pointer: &JsonPointer) -> Result<JsonType, PathError> {
+    let last = match pointer.tokens().len() {
+        0 => {
+            return Err(PathError::NotFound {
+                path: String::new(),
+            })
+        }
+        len => len - 1,
+    };
+    let parent: JsonPointer = pointer.tokens()[..last].iter().cloned().collect();
+    let node = resolve_mut(json, &parent)?;
+    if child(node, &pointer.tokens()[last]).is_none() {
+        return Err(step_error(node, pointer, last));
+    }
+    Ok(match node {
+        JsonType::Object(obj) => obj.remove(&pointer.tokens()[last]).unwrap(),
+        JsonType::Array(arr) => arr.remove(JsonPointer::index(&pointer.tokens()[last]).unwrap()),
+        _ => unreachable!(),
+    })
+}
+
+fn diff_values(
+    from: &JsonType,
+    to: &JsonType,
+    path: &mut JsonPointer,
+    operations: &mut Vec<PatchOperation>,
+) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (JsonType::Object(a), JsonType::Object(b)) => {
+            for key in a.keys().filter(|key| !b.contains(key)) {
+                operations.push(PatchOperation::Remove {
+                    path: path.child(key.as_str()),
+                });
+            }
+            for (key, value) in b {
+                match a.get(key) {
+                    Some(old) => {
+                        path.push(key.as_str());
+                        diff_values(old, value, path, operations);
+                        path.pop();
+                    }
+                    None => operations.push(PatchOperation::Add {
+                        path: path.child(key.as_str()),
+                        value: value.clone(),
+                    }),
+                }
+            }
+        }
+        (JsonType::Array(a), JsonType::Array(b)) => diff_arrays(a, b, path, operations),
+        _ => operations.push(PatchOperation::Replace {
+            path: path.clone(),
+            value: to.clone(),
+        }),
+    }
+}
+
+/// pairs up the elements between the common head and tail, the rest of the
+/// longer side is removed or added
+fn diff_arrays(
+    a: &[JsonType],
+    b: &[JsonType],
+    path: &mut JsonPointer,
+    operations: &mut Vec<PatchOperation>,
+) {
+    let head = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let tail = a[head..]
+        .iter()
+        .rev()
+        .zip(b[head..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[head..a.len() - tail], &b[head..b.len() - tail]);
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        path.push((head + i).to_string());
+        diff_values(x, y, path, operations);
+        path.pop();
+    }
+    for i in (b.len()..a.len()).rev() {
+        operations.push(PatchOperation::Remove {
+            path: path.child((head + i).to_string()),
+        });
+    }
+    for (i, value) in b.iter().enumerate().skip(a.len()) {
+        operations.push(PatchOperation::Add {
+            path: path.child((head + i).to_string()),
+            value: value.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply_patch, create_merge_patch, merge_patch, JsonPatch};
+    use crate::{parse, PatchError, PathError};
+
+    fn patch(s: &str) -> JsonPatch {
+        JsonPatch::parse(&parse(s)).unwrap()
+    }
+
+    #[test]
+    fn rfc6902_examples() {
+        let cases = [
+            (
+                r#"{ "foo": "bar" }"#,
+                r#"[{ "op": "add", "path": "/baz", "value": "qux" }]"#,
+                r#"{ "baz": "qux", "foo": "bar" }"#,
+            ),
+            (
+                r#"{ "foo": [ "bar", "baz" ] }"#,
+                r#"[{ "op": "add", "path": "/foo/1", "value": "qux" }]"#,
+                r#"{ "foo": [ "bar", "qux", "baz" ] }"#,
+            ),
+            (
+                r#"{ "baz": "qux", "foo": "bar" }"#,
+                r#"[{ "op": "remove", "path": "/baz" }]"#,
+                r#"{ "foo": "bar" }"#,
+            ),
+            (
+                r#"{ "foo": [ "bar", "qux", "baz" ] }"#,
+                r#"[{ "op": "remove", "path": "/foo/1" }]"#,
+                r#"{ "foo": [ "bar", "baz" ] }"#,
+            ),
+            (
+                r#"{ "baz": "qux", "foo": "bar" }"#,
+                r#"[{ "op": "replace", "path": "/baz", "value": "boo" }]"#,
+                r#"{ "baz": "boo", "foo": "bar" }"#,
+            ),
+            (
+                r#"{ "foo": { "bar": "baz", "waldo": "fred" }, "qux": { "corge": "grault" } }"#,
+                r#"[{ "op": "move", "from": "/foo/waldo", "path": "/qux/thud" }]"#,
+                r#"{ "foo": { "bar": "baz" }, "qux": { "corge": "grault", "thud": "fred" } }"#,
+            ),
+            (
+                r#"{ "foo": [ "all", "grass", "cows", "eat" ] }"#,
+                r#"[{ "op": "move", "from": "/foo/1", "path": "/foo/3" }]"#,
+                r#"{ "foo": [ "all", "cows", "eat", "grass" ] }"#,
+            ),
+            (
+                r#"{ "baz": "qux", "foo": [ "a", 2, "c" ] }"#,
+                r#"[
+                    { "op": "test", "path": "/baz", "value": "qux" },
+                    { "op": "test", "path": "/foo/1", "value": 2 }
+                ]"#,
+                r#"{ "baz": "qux", "foo": [ "a", 2, "c" ] }"#,
+            ),
+            (
+                r#"{ "foo": "bar" }"#,
+                r#"[{ "op": "add", "path": "/child", "value": { "grandchild": { } } }]"#,
+                r#"{ "foo": "bar", "child": { "grandchild": {} } }"#,
+            ),
+            (
+                r#"{ "foo": "bar" }"#,
+                r#"[{ "op": "add", "path": "/baz", "value": "qux", "xyz": 123 }]"#,
+                r#"{ "foo": "bar", "baz": "qux" }"#,
+            ),
+            (
+                r#"{ "foo": ["bar"] }"#,
+                r#"[{ "op": "add", "path": "/foo/-", "value": ["abc", "def"] }]"#,
+                r#"{ "foo": ["bar", ["abc", "def"]] }"#,
+            ),
+            (
+                r#"{ "/": 9, "~1": 10 }"#,
+                r#"[{ "op": "test", "path": "/~01", "value": 10 }, { "op": "copy", "from": "/~1", "path": "" }]"#,
+                "9",
+            ),
+        ];
+        for (doc, ops, espect) in cases {
+            let mut json = parse(doc);
+            apply_patch(&mut json, &patch(ops)).unwrap_or_else(|err| panic!("{}: {}", ops, err));
+            assert_eq!(json, parse(espect), "{}", ops);
+        }
+
+        // all or nothing
+        let mut json = parse(r#"{ "baz": "qux", "foo": [1] }"#);
+        let before = json.clone();
+        let err = apply_patch(
+            &mut json,
+            &patch(
+                r#"[
+                    { "op": "remove", "path": "/foo/0" },
+                    { "op": "test", "path": "/baz", "value": "bar" }
+                ]"#,
+            ),
+        );
+        assert_eq!(
+            err,
+            Err(PatchError::TestFailed {
+                index: 1,
+                path: "/baz".to_string()
+            })
+        );
+        assert_eq!(json, before);
+        let err = json.apply_patch(&patch(
+            r#"[{ "op": "add", "path": "/baz/bat", "value": 1 }]"#,
+        ));
+        assert_eq!(
+            err.unwrap_err(),
+            PatchError::Path {
+                index: 0,
+                error: PathError::TypeMismatch {
+                    path: "/baz".to_string(),
+                    expected: "object or array",
+                    found: "string"
+                }
+            }
+        );
+        let err = json.apply_patch(&patch(r#"[{ "op": "add", "path": "/foo/2", "value": 1 }]"#));
+        assert!(matches!(
+            err,
+            Err(PatchError::Path {
+                error: PathError::IndexOutOfRange {
+                    index: 2,
+                    len: 1,
+                    ..
+                },
+                ..
+            })
+        ));
+        let err = json.apply_patch(&patch(
+            r#"[{ "op": "move", "from": "/foo", "path": "/foo/0" }]"#,
+        ));
+        assert!(matches!(err, Err(PatchError::Invalid { index: 0, .. })));
+        for invalid in [
+            r#"{}"#,
+            r#"[{ "op": "add", "path": "/a" }]"#,
+            r#"[{ "op": "jump", "path": "/a" }]"#,
+            r#"[{ "op": "remove", "path": "a" }]"#,
+            r#"[{ "op": "copy", "path": "/a" }]"#,
+        ] {
+            assert!(JsonPatch::parse(&parse(invalid)).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn diff_roundtrip() {
+        let pairs = [
+            ("1", "1"),
+            ("1", r#""x""#),
+            (r#"{"a":1}"#, "[1]"),
+            (r#"{"a":1,"b":{"c":[1,2]}}"#, r#"{"b":{"c":[1,2,3]},"d":4}"#),
+            ("[1,2,3,4,5]", "[1,9,3,5]"),
+            ("[1,2,3]", "[0,1,2,3]"),
+            ("[1,2,3]", "[]"),
+            ("[[1],[2],[3]]", "[[1],[2,2],[3],[4]]"),
+            (r#"[{"id":1},{"id":2}]"#, r#"[{"id":2}]"#),
+            (r#"{"a/b":{"~":1}}"#, r#"{"a/b":{"~":2}}"#),
+        ];
+        for (from, to) in pairs {
+            let (from, to) = (parse(from), parse(to));
+            let patch = JsonPatch::diff(&from, &to);
+            let mut json = from.clone();
+            apply_patch(&mut json, &patch).unwrap();
+            assert_eq!(json, to, "{}", patch.to_json());
+            // the serialized patch reads back to the same operations
+            assert_eq!(JsonPatch::parse(&patch.to_json()).unwrap(), patch);
+        }
+        let patch = JsonPatch::diff(&parse("[1,2,3]"), &parse("[0,1,2,3]"));
+        assert_eq!(
+            patch.to_json().serialize(),
+            r#"[{"op":"add","path":"/0","value":0}]"#
+        );
+        assert!(JsonPatch::diff(&parse(r#"{"a":1,"b":2}"#), &parse(r#"{"b":2,"a":1}"#)).is_empty());
+    }
+
+    #[test]
+    fn rfc7386_examples() {
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+        for (target, patch, espect) in cases {
+            let mut json = parse(target);
+            merge_patch(&mut json, &parse(patch));
+            assert_eq!(json, parse(espect), "{} + {}", target, patch);
+        }
+
+        let pairs = [
+            (
+                r#"{"a":1,"b":{"c":[1],"d":{"e":1}}}"#,
+                r#"{"b":{"c":[2],"d":{}},"f":"x"}"#,
+            ),
+            (r#"{"a":1}"#, r#"{"a":1}"#),
+            (r#"{"a":1}"#, "[1]"),
+            ("1", r#"{"a":{"b":2}}"#),
+        ];
+        for (old, new) in pairs {
+            let (old, new) = (parse(old), parse(new));
+            let patch = create_merge_patch(&old, &new);
+            let mut json = old.clone();
+            json.merge_patch(&patch);
+            assert_eq!(json, new, "{}", patch);
+        }
+        let patch = create_merge_patch(&parse(r#"{"a":1,"b":2}"#), &parse(r#"{"a":1,"b":3}"#));
+        assert_eq!(patch.serialize(), r#"{"b":3}"#);
+    }
+}
@@ -0,0 +1,618 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::PathError;
+use crate::lexer::Lexer;
+use crate::options::ParseOptions;
+use crate::read::SliceSource;
+use crate::ser::serialize_string;
+use crate::{JsonObject, JsonType};
+
+/// one step of a [`JsonPath`]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Segment {
+    /// a member of an object, written `key` or `["key"]`
+    Key(String),
+    /// an element of an array, written `[0]` or `0` after a dot; against an
+    /// object it looks up the key spelled the same way, as dotted paths always did
+    Index(usize),
+}
+
+/// a parsed path that can be reused for many lookups
+///
+/// the grammar extends the dotted paths of `JsonType::get`: segments are
+/// separated by `.`, brackets hold an array index or a quoted key, and `\`
+/// escapes a character in an unquoted key. the empty path is the root value
+///
+/// ```
+/// use dynamic_json::{parse, JsonPath};
+/// let json = parse(r#"{ "hosts": { "example.com": [80, 443] } }"#);
+/// let path: JsonPath = r#"hosts["example.com"][1]"#.parse().unwrap();
+/// assert_eq!(json.get(&path).unwrap().as_u64(), Some(443));
+/// assert_eq!(path.to_string(), r#"hosts["example.com"][1]"#);
+/// assert_eq!(JsonPath::parse("a.b\\.c.0").unwrap().to_string(), r#"a["b.c"][0]"#);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// the path of the root value
+    pub fn root() -> Self {
+        JsonPath::default()
+    }
+
+    pub fn parse(s: &str) -> Result<JsonPath, PathError> {
+        let bytes = s.as_bytes();
+        let error = |offset: usize| PathError::Syntax {
+            path: s.to_string(),
+            offset,
+        };
+        let mut segments = vec![];
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'[' {
+                i += 1;
+                match bytes.get(i) {
+                    Some(b'"' | b'\'') => {
+                        let mut lexer = Lexer::new(SliceSource::new(&bytes[i..]));
+                        lexer.options = ParseOptions {
+                            allow_single_quotes: true,
+                            ..Default::default()
+                        };
+                        lexer
+                            .parse_string()
+                            .map_err(|err| error(i + err.offset().unwrap_or(0)))?;
+                        segments.push(Segment::Key(lexer.string().to_string()));
+                        i += lexer.source().offset();
+                    }
+                    Some(b'0'..=b'9') => {
+                        let len = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+                        let index = s[i..i + len].parse().map_err(|_| error(i))?;
+                        segments.push(Segment::Index(index));
+                        i += len;
+                    }
+                    _ => return Err(error(i)),
+                }
+                if bytes.get(i) != Some(&b']') {
+                    return Err(error(i));
+                }
+                i += 1;
+            } else {
+                if !segments.is_empty() {
+                    if bytes[i] != b'.' {
+                        return Err(error(i));
+                    }
+                    i += 1;
+                }
+                let start = i;
+                let mut key = String::new();
+                let mut chars = s[i..].char_indices();
+                while let Some((offset, c)) = chars.next() {
+                    let c = match c {
+                        '.' | '[' => break,
+                        ']' | '"' | '\'' => return Err(error(start + offset)),
+                        '\\' => match chars.next() {
+                            Some((_, escaped)) => escaped,
+                            None => return Err(error(start + offset)),
+                        },
+                        c => c,
+                    };
+                    key.push(c);
+                    i = start + chars.offset();
+                }
+                if i == start {
+                    return Err(error(i));
+                }
+                // only canonical integers are indices, `01` stays a key
+                let canonical = key.bytes().all(|b| b.is_ascii_digit())
+                    && (key == "0" || !key.starts_with('0'));
+                segments.push(match key.parse() {
+                    Ok(index) if canonical => Segment::Index(index),
+                    _ => Segment::Key(key),
+                });
+            }
+        }
+        Ok(JsonPath { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<Segment> {
+        self.segments.pop()
+    }
+
+    /// the path with `segment` appended
+    pub fn child(&self, segment: Segment) -> JsonPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    /// the path without its last segment, `None` for the root
+    pub fn parent(&self) -> Option<JsonPath> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(JsonPath {
+            segments: parent.to_vec(),
+        })
+    }
+
+    /// the path up to and including segment `i` as a string
+    fn prefix(&self, i: usize) -> String {
+        JsonPath {
+            segments: self.segments[..=i].to_vec(),
+        }
+        .to_string()
+    }
+
+    fn not_found(&self, i: usize) -> PathError {
+        PathError::NotFound {
+            path: self.prefix(i),
+        }
+    }
+
+    fn invalid_index(&self, i: usize) -> PathError {
+        PathError::InvalidIndex {
+            path: self.prefix(i),
+        }
+    }
+
+    fn mismatch(&self, i: usize, expected: &'static str, found: &JsonType) -> PathError {
+        PathError::TypeMismatch {
+            path: self.parent_of(i),
+            expected,
+            found: found.type_name(),
+        }
+    }
+
+    fn out_of_range(&self, i: usize, index: usize, len: usize) -> PathError {
+        PathError::IndexOutOfRange {
+            path: self.parent_of(i),
+            index,
+            len,
+        }
+    }
+
+    /// the path of the value segment `i` is looked up in
+    fn parent_of(&self, i: usize) -> String {
+        match i {
+            0 => String::new(),
+            i => self.prefix(i - 1),
+        }
+    }
+
+    /// the child of `node` at segment `i`
+    fn child_of<'j>(&self, node: &'j JsonType, i: usize) -> Result<&'j JsonType, PathError> {
+        let child = match (node, &self.segments[i]) {
+            (JsonType::Object(obj), Segment::Key(key)) => obj.get(key),
+            (JsonType::Object(obj), Segment::Index(index)) => obj.get(&index.to_string()),
+            (JsonType::Array(arr), Segment::Index(index)) => arr.get(*index),
+            (JsonType::Array(_), Segment::Key(_)) => return Err(self.invalid_index(i)),
+            _ => None,
+        };
+        child.ok_or_else(|| self.not_found(i))
+    }
+
+    fn child_of_mut<'j>(
+        &self,
+        node: &'j mut JsonType,
+        i: usize,
+    ) -> Result<&'j mut JsonType, PathError> {
+        let child = match (node, &self.segments[i]) {
+            (JsonType::Object(obj), Segment::Key(key)) => obj.get_mut(key),
+            (JsonType::Object(obj), Segment::Index(index)) => obj.get_mut(&index.to_string()),
+            (JsonType::Array(arr), Segment::Index(index)) => arr.get_mut(*index),
+            (JsonType::Array(_), Segment::Key(_)) => return Err(self.invalid_index(i)),
+            _ => None,
+        };
+        child.ok_or_else(|| self.not_found(i))
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = PathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(s)
+    }
+}
+
+impl FromIterator<Segment> for JsonPath {
+    fn from_iter<T: IntoIterator<Item = Segment>>(iter: T) -> Self {
+        JsonPath {
+            segments: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// keys are written without quotes when that parses back to the same key
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if is_plain_key(key) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(key)?;
+                }
+                Segment::Key(key) => write!(f, "[{}]", serialize_string(key))?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.bytes().all(|b| b.is_ascii_digit())
+        && !key
+            .chars()
+            .any(|c| matches!(c, '.' | '[' | ']' | '"' | '\'' | '\\') || c.is_control())
+}
+
+/// a path string or a parsed [`JsonPath`], accepted by the path operations
+pub trait ToPath {
+    fn to_path(&self) -> Result<Cow<'_, JsonPath>, PathError>;
+}
+
+impl ToPath for str {
+    fn to_path(&self) -> Result<Cow<'_, JsonPath>, PathError> {
+        JsonPath::parse(self).map(Cow::Owned)
+    }
+}
+
+impl ToPath for String {
+    fn to_path(&self) -> Result<Cow<'_, JsonPath>, PathError> {
+        JsonPath::parse(self).map(Cow::Owned)
+    }
+}
+
+impl ToPath for JsonPath {
+    fn to_path(&self) -> Result<Cow<'_, JsonPath>, PathError> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl JsonType {
+    /// get a value by path, see [`JsonPath`] for the syntax
+    ///
+    /// ```
+    /// use dynamic_json::parse;
+    /// let json = parse(r#"{ "a": { "b": 123, "c": [1, 2, 3] } }"#);
+    /// assert_eq!(json.get("a.b").unwrap().as_f64(), Some(123f64));
+    /// assert_eq!(json.get("a.c.1").unwrap().as_f64(), Some(2f64));
+    /// assert!(json.get("a.c[5]").is_err());
+    /// ```
+    pub fn get<P: ToPath + ?Sized>(&self, path: &P) -> Result<&JsonType, String> {
+        let path = path.to_path().map_err(|err| err.to_string())?;
+        let mut node = self;
+        for i in 0..path.segments.len() {
+            node = path.child_of(node, i).map_err(|err| err.to_string())?;
+        }
+        Ok(node)
+    }
+
+    /// mutable reference to the value at a path
+    pub fn get_mut<P: ToPath + ?Sized>(&mut self, path: &P) -> Result<&mut JsonType, PathError> {
+        let path = path.to_path()?;
+        let mut node = self;
+        for i in 0..path.segments.len() {
+            node = path.child_of_mut(node, i)?;
+        }
+        Ok(node)
+    }
+
+    /// set the value at a path, returning the value it replaced unless
+    /// that was missing or `null`
+    ///
+    /// missing or `null` values on the way become objects, or arrays when the
+    /// next segment is an index, and an index equal to the length of an
+    /// array appends to it
+    ///
+    /// ```
+    /// use dynamic_json::{parse, JsonType};
+    /// let mut json = parse(r#"{ "a": 1 }"#);
+    /// json.set("b.list[0].c", true).unwrap();
+    /// assert_eq!(json.serialize(), r#"{"a":1,"b":{"list":[{"c":true}]}}"#);
+    /// assert!(json.set("a.x", 2).is_err());
+    /// ```
+    pub fn set<P: ToPath + ?Sized, T: Into<JsonType>>(
+        &mut self,
+        path: &P,
+        value: T,
+    ) -> Result<Option<JsonType>, PathError> {
+        let path = path.to_path()?;
+        self.check_set(&path)?;
+        let mut node = self;
+        for (i, segment) in path.segments.iter().enumerate() {
+            if node.is_null() {
+                *node = match segment {
+                    Segment::Index(_) => JsonType::Array(vec![]),
+                    Segment::Key(_) => JsonType::Object(JsonObject::new()),
+                };
+            }
+            node = match (node, segment) {
+                (JsonType::Object(obj), Segment::Key(key)) => obj.entry(key.as_str()).or_default(),
+                (JsonType::Object(obj), Segment::Index(index)) => {
+                    obj.entry(index.to_string()).or_default()
+                }
+                (JsonType::Array(arr), Segment::Index(index)) => {
+                    let len = arr.len();
+                    if *index == len {
+                        arr.push(JsonType::Null);
+                    } else if *index > len {
+                        return Err(path.out_of_range(i, *index, len));
+                    }
+                    &mut arr[*index]
+                }
+                (JsonType::Array(_), Segment::Key(_)) => return Err(path.invalid_index(i)),
+                (other, _) => return Err(path.mismatch(i, "object or array", other)),
+            };
+        }
+        let old = std::mem::replace(node, value.into());
+        Ok(Some(old).filter(|old| !old.is_null()))
+    }
+
+    /// the error `set` would fail with, checked before anything is created
+    fn check_set(&self, path: &JsonPath) -> Result<(), PathError> {
+        let mut node = Some(self);
+        for (i, segment) in path.segments.iter().enumerate() {
+            node = match (node, segment) {
+                (None | Some(JsonType::Null), Segment::Index(index)) if *index > 0 => {
+                    return Err(path.out_of_range(i, *index, 0))
+                }
+                (None | Some(JsonType::Null), _) => None,
+                (Some(JsonType::Object(obj)), Segment::Key(key)) => obj.get(key),
+                (Some(JsonType::Object(obj)), Segment::Index(index)) => obj.get(&index.to_string()),
+                (Some(JsonType::Array(arr)), Segment::Index(index)) if *index > arr.len() => {
+                    return Err(path.out_of_range(i, *index, arr.len()))
+                }
+                (Some(JsonType::Array(arr)), Segment::Index(index)) => arr.get(*index),
+                (Some(JsonType::Array(_)), Segment::Key(_)) => return Err(path.invalid_index(i)),
+                (Some(other), _) => return Err(path.mismatch(i, "object or array", other)),
+            };
+        }
+        Ok(())
+    }
+
+    /// remove the value at a path and return it, later array elements
+    /// move up
+    pub fn remove<P: ToPath + ?Sized>(&mut self, path: &P) -> Result<JsonType, PathError> {
+        let path = path.to_path()?;
+        let last = match path.segments.len() {
+            0 => {
+                return Err(PathError::NotFound {
+                    path: String::new(),
+                })
+            }
+            len => len - 1,
+        };
+        let mut node = self;
+        for i in 0..last {
+            node = path.child_of_mut(node, i)?;
+        }
+        let removed = match (node, &path.segments[last]) {
+            (JsonType::Object(obj), Segment::Key(key)) => obj.remove(key),
+            (JsonType::Object(obj), Segment::Index(index)) => obj.remove(&index.to_string()),
+            (JsonType::Array(arr), Segment::Index(index)) if *index < arr.len() => {
+                Some(arr.remove(*index))
+            }
+            (JsonType::Array(_), Segment::Index(_)) => None,
+            (JsonType::Array(_), Segment::Key(_)) => return Err(path.invalid_index(last)),
+            (other, _) => return Err(path.mismatch(last, "object or array", other)),
+        };
+        removed.ok_or_else(|| path.not_found(last))
+    }
+
+    /// insert into the array at a path before `index`, an `index` equal
+    /// to the length appends
+    ///
+    /// ```
+    /// use dynamic_json::parse;
+    /// let mut json = parse(r#"{ "list": [1, 3] }"#);
+    /// json.insert_at("list", 1, 2).unwrap();
+    /// assert_eq!(json.serialize(), r#"{"list":[1,2,3]}"#);
+    /// ```
+    pub fn insert_at<P: ToPath + ?Sized, T: Into<JsonType>>(
+        &mut self,
+        path: &P,
+        index: usize,
+        value: T,
+    ) -> Result<(), PathError> {
+        let path = path.to_path()?;
+        let arr = self.get_mut(path.as_ref())?;
+        match arr {
+            JsonType::Array(arr) if index <= arr.len() => {
+                arr.insert(index, value.into());
+                Ok(())
+            }
+            JsonType::Array(arr) => Err(PathError::IndexOutOfRange {
+                path: path.to_string(),
+                index,
+                len: arr.len(),
+            }),
+            other => Err(PathError::TypeMismatch {
+                path: path.to_string(),
+                expected: "array",
+                found: other.type_name(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JsonPath, Segment};
+    use crate::{parse, JsonType, PathError};
+
+    #[test]
+    fn mutate_by_path() {
+        let mut json = parse(r#"{ "a": { "b": [1, 2] }, "s": "x" }"#);
+        *json.get_mut("a.b.0").unwrap() = JsonType::from(10);
+        assert_eq!(json.set("a.b.1", 20).unwrap(), Some(JsonType::from(2)));
+        assert_eq!(json.set("a.c.x", 1).unwrap(), None);
+        assert_eq!(json.remove("a.b.0").unwrap(), JsonType::from(10));
+        json.insert_at("a.b", 0, "first").unwrap();
+        assert_eq!(
+            json.serialize(),
+            r#"{"a":{"b":["first",20],"c":{"x":1}},"s":"x"}"#
+        );
+        assert_eq!(json.remove("s").unwrap(), JsonType::from("x"));
+
+        assert_eq!(
+            json.get("a.b.9"),
+            Err("can not found item a.b[9]".to_string())
+        );
+        assert_eq!(
+            json.get_mut("a.b.x").unwrap_err(),
+            PathError::InvalidIndex {
+                path: "a.b.x".to_string()
+            }
+        );
+        assert_eq!(
+            json.set("a.b.0.k", 1).unwrap_err(),
+            PathError::TypeMismatch {
+                path: "a.b[0]".to_string(),
+                expected: "object or array",
+                found: "string",
+            }
+        );
+        assert_eq!(
+            json.insert_at("a.b", 5, 1).unwrap_err(),
+            PathError::IndexOutOfRange {
+                path: "a.b".to_string(),
+                index: 5,
+                len: 2,
+            }
+        );
+        assert_eq!(
+            json.insert_at("a.c", 0, 1).unwrap_err().to_string(),
+            "expect array at a.c but found object"
+        );
+        assert_eq!(json.remove("a.z").unwrap_err().path(), "a.z");
+        assert!(json.remove("a.b.2").is_err());
+    }
+
+    #[test]
+    fn set_past_the_end() {
+        let mut json = parse(r#"{ "a": [1] }"#);
+        assert_eq!(json.set("a.1", 2).unwrap(), None);
+        assert_eq!(json.set("b.0.0", 3).unwrap(), None);
+        assert_eq!(json.serialize(), r#"{"a":[1,2],"b":[[3]]}"#);
+        assert_eq!(
+            json.set(&format!("a.{}", usize::MAX / 2), 1).unwrap_err(),
+            PathError::IndexOutOfRange {
+                path: "a".to_string(),
+                index: usize::MAX / 2,
+                len: 2,
+            }
+        );
+
+        let before = json.clone();
+        assert_eq!(
+            json.set("c.1", 1).unwrap_err(),
+            PathError::IndexOutOfRange {
+                path: "c".to_string(),
+                index: 1,
+                len: 0,
+            }
+        );
+        assert!(json.set("d.e.5", 1).is_err());
+        assert!(json.set("a.0.x.y", 1).is_err());
+        assert_eq!(json, before);
+    }
+
+    #[test]
+    fn escaped_non_ascii() {
+        let path = JsonPath::parse("a\\é.b").unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                Segment::Key("aé".to_string()),
+                Segment::Key("b".to_string())
+            ]
+        );
+        assert_eq!(JsonPath::parse("\\ключ").unwrap().to_string(), "ключ");
+    }
+
+    #[test]
+    fn leading_zero_keys() {
+        let json = parse(r#"{ "a": { "01": "zero-one", "1": "one" } }"#);
+        assert_eq!(json.get("a.01"), Ok(&JsonType::from("zero-one")));
+        assert_eq!(json.get("a.1"), Ok(&JsonType::from("one")));
+        let path = JsonPath::parse("a.01").unwrap();
+        assert_eq!(path.segments()[1], Segment::Key("01".to_string()));
+        assert_eq!(path.to_string(), r#"a["01"]"#);
+    }
+
+    #[test]
+    fn path_syntax() {
+        let cases = [
+            ("", ""),
+            ("a", "a"),
+            ("a.b.0", "a.b[0]"),
+            ("[0][1]", "[0][1]"),
+            (r#"a["b.c"]['d"e']"#, r#"a["b.c"]["d\"e"]"#),
+            (r#"a\.b.c\[0\]"#, r#"["a.b"]["c[0]"]"#),
+            (r#"["1"].x"#, r#"["1"].x"#),
+            (r#"[""]"#, r#"[""]"#),
+            ("ключ.é", "ключ.é"),
+        ];
+        for (input, espect) in cases {
+            let path = JsonPath::parse(input).unwrap();
+            assert_eq!(path.to_string(), espect, "{}", input);
+            assert_eq!(JsonPath::parse(espect).unwrap(), path, "{}", input);
+        }
+        for (input, offset) in [
+            ("a..b", 2),
+            ("a.", 2),
+            ("a[", 2),
+            ("a[x]", 2),
+            ("a[0", 3),
+            ("a]", 1),
+            (r#"a["b"#, 4),
+            ("a\\", 1),
+            ("[0]b", 3),
+        ] {
+            let err = JsonPath::parse(input).unwrap_err();
+            assert_eq!(
+                err,
+                PathError::Syntax {
+                    path: input.to_string(),
+                    offset
+                },
+                "{}",
+                input
+            );
+        }
+
+        let json = parse(r#"{ "1.0": { "x": [true] }, "1": "one", "": 0 }"#);
+        let path = JsonPath::parse(r#"["1.0"].x[0]"#).unwrap();
+        assert_eq!(json.get(&path), Ok(&JsonType::Bool(true)));
+        assert_eq!(json.get("1").unwrap().as_str(), Some("one"));
+        assert_eq!(json.get(r#"[""]"#).unwrap().as_f64(), Some(0.0));
+        assert_eq!(json.get(""), Ok(&json));
+        assert_eq!(
+            json.get(r#"["1.0"].x["0"]"#).unwrap_err(),
+            r#"invalid array index at ["1.0"].x["0"]"#
+        );
+
+        let parent = path.parent().unwrap();
+        assert_eq!(parent.to_string(), r#"["1.0"].x"#);
+        assert_eq!(parent.child(Segment::Index(0)), path);
+        assert!(JsonPath::root().parent().is_none());
+    }
+}
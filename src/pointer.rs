@@ -0,0 +1,261 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::PathError;
+use crate::path::{JsonPath, Segment, ToPath};
+use crate::JsonType;
+
+/// a JSON Pointer as defined by RFC 6901, like `/a/b~1c/0`
+///
+/// `~1` stands for `/` and `~0` for `~` inside a reference token, the empty
+/// pointer is the root value
+///
+/// ```
+/// use dynamic_json::{parse, JsonPath, JsonPointer};
+/// let json = parse(r#"{ "a/b": { "list": [1, 2] } }"#);
+/// let pointer: JsonPointer = "/a~1b/list/1".parse().unwrap();
+/// assert_eq!(json.get(&pointer).unwrap().as_f64(), Some(2.0));
+/// assert_eq!(JsonPath::from(pointer).to_string(), "a/b.list[1]");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /// the pointer to the root value
+    pub fn root() -> Self {
+        JsonPointer::default()
+    }
+
+    pub fn parse(s: &str) -> Result<JsonPointer, PathError> {
+        let error = |offset: usize| PathError::Syntax {
+            path: s.to_string(),
+            offset,
+        };
+        if s.is_empty() {
+            return Ok(JsonPointer::root());
+        }
+        if !s.starts_with('/') {
+            return Err(error(0));
+        }
+        let mut tokens = vec![];
+        let mut offset = 1;
+        for raw in s[1..].split('/') {
+            let mut token = String::with_capacity(raw.len());
+            let mut chars = raw.char_indices();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some((_, '0')) => token.push('~'),
+                        Some((_, '1')) => token.push('/'),
+                        _ => return Err(error(offset + i)),
+                    },
+                    c => token.push(c),
+                }
+            }
+            offset += raw.len() + 1;
+            tokens.push(token);
+        }
+        Ok(JsonPointer { tokens })
+    }
+
+    /// the unescaped reference tokens
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn push<T: Into<String>>(&mut self, token: T) {
+        self.tokens.push(token.into());
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.tokens.pop()
+    }
+
+    /// the pointer with `token` appended
+    pub fn child<T: Into<String>>(&self, token: T) -> JsonPointer {
+        let mut pointer = self.clone();
+        pointer.push(token);
+        pointer
+    }
+
+    /// the pointer without its last token, `None` for the root
+    pub fn parent(&self) -> Option<JsonPointer> {
+        let (_, parent) = self.tokens.split_last()?;
+        Some(JsonPointer {
+            tokens: parent.to_vec(),
+        })
+    }
+
+    /// the array index a token stands for, RFC 6901 allows no leading zeros
+    pub(crate) fn index(token: &str) -> Option<usize> {
+        match token.as_bytes() {
+            [b'0'] => Some(0),
+            [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => token.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = PathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(s)
+    }
+}
+
+impl FromIterator<String> for JsonPointer {
+    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+        JsonPointer {
+            tokens: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<JsonPath> for JsonPointer {
+    fn from(path: JsonPath) -> Self {
+        let segments = path.segments().iter();
+        segments
+            .map(|segment| match segment {
+                Segment::Key(key) => key.clone(),
+                Segment::Index(index) => index.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// tokens that are valid array indices become index segments, which still
+/// find keys spelled the same way in objects
+impl From<JsonPointer> for JsonPath {
+    fn from(pointer: JsonPointer) -> Self {
+        pointer
+            .tokens
+            .into_iter()
+            .map(|token| match JsonPointer::index(&token) {
+                Some(index) => Segment::Index(index),
+                None => Segment::Key(token),
+            })
+            .collect()
+    }
+}
+
+impl ToPath for JsonPointer {
+    fn to_path(&self) -> Result<Cow<'_, JsonPath>, PathError> {
+        Ok(Cow::Owned(self.clone().into()))
+    }
+}
+
+impl JsonType {
+    /// the value a JSON Pointer refers to, `None` if the pointer is invalid
+    /// or does not resolve
+    ///
+    /// ```
+    /// use dynamic_json::parse;
+    /// let json = parse(r#"{ "a": { "b~c": [1, 2] } }"#);
+    /// assert_eq!(json.pointer("/a/b~0c/1").unwrap().as_f64(), Some(2.0));
+    /// assert!(json.pointer("/a/b~0c/01").is_none());
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonType> {
+        let pointer = JsonPointer::parse(pointer).ok()?;
+        let mut node = self;
+        for token in &pointer.tokens {
+            node = match node {
+                JsonType::Object(obj) => obj.get(token)?,
+                JsonType::Array(arr) => arr.get(JsonPointer::index(token)?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonType> {
+        let pointer = JsonPointer::parse(pointer).ok()?;
+        let mut node = self;
+        for token in &pointer.tokens {
+            node = match node {
+                JsonType::Object(obj) => obj.get_mut(token)?,
+                JsonType::Array(arr) => arr.get_mut(JsonPointer::index(token)?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonPointer;
+    use crate::{parse, JsonPath, JsonType, PathError};
+
+    #[test]
+    fn rfc6901_examples() {
+        let json = parse(
+            r#"{
+                "foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3,
+                "g|h": 4, "i\\j": 5, "k\"l": 6, " ": 7, "m~n": 8
+            }"#,
+        );
+        assert_eq!(json.pointer(""), Some(&json));
+        assert_eq!(json.pointer("/foo/0").unwrap().as_str(), Some("bar"));
+        let cases = [
+            ("/", 0),
+            ("/a~1b", 1),
+            ("/c%d", 2),
+            ("/e^f", 3),
+            ("/g|h", 4),
+            ("/i\\j", 5),
+            ("/k\"l", 6),
+            ("/ ", 7),
+            ("/m~0n", 8),
+        ];
+        for (pointer, espect) in cases {
+            assert_eq!(
+                json.pointer(pointer),
+                Some(&JsonType::from(espect)),
+                "{}",
+                pointer
+            );
+            assert_eq!(JsonPointer::parse(pointer).unwrap().to_string(), pointer);
+        }
+        for pointer in [
+            "/foo/2", "/foo/-", "/foo/01", "/foo/+1", "/x", "foo", "/m~2n",
+        ] {
+            assert!(json.pointer(pointer).is_none(), "{}", pointer);
+        }
+        assert_eq!(
+            JsonPointer::parse("/a/~x").unwrap_err(),
+            PathError::Syntax {
+                path: "/a/~x".to_string(),
+                offset: 3
+            }
+        );
+
+        let mut json = json;
+        *json.pointer_mut("/foo/1").unwrap() = JsonType::Null;
+        assert_eq!(json.pointer("/foo/1"), Some(&JsonType::Null));
+
+        let pointer = JsonPointer::parse("/a.b/0/~01").unwrap();
+        assert_eq!(pointer.tokens(), ["a.b", "0", "~1"]);
+        assert_eq!(pointer.parent().unwrap().child("x").to_string(), "/a.b/0/x");
+        let path = JsonPath::from(pointer.clone());
+        assert_eq!(path.to_string(), r#"["a.b"][0].~1"#);
+        assert_eq!(JsonPointer::from(path), pointer);
+        let path: JsonPath = "a.list.3".parse().unwrap();
+        assert_eq!(JsonPointer::from(path).to_string(), "/a/list/3");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::error::{ErrorKind, JsonError};
+use crate::options::ParseOptions;
+use crate::parser::TreeBuilder;
+use crate::read::PushSource;
+use crate::reader::{Event, JsonReader};
+use crate::JsonType;
+
+/// parser for input that arrives in pieces, e.g. frames of a network protocol
+///
+/// chunks can be split anywhere, even inside a string, number or escape sequence;
+/// strings and numbers continue where the last chunk ended, so a long token
+/// that arrives in small pieces is read once and not kept whole in the buffer
+///
+/// ```
+/// use dynamic_json::{JsonPushParser, JsonType};
+/// let mut parser = JsonPushParser::new();
+/// parser.feed(br#"{ "name": "caf"#).unwrap();
+/// parser.feed(b"\\u00").unwrap();
+/// parser.feed(br#"e9", "n": 12"#).unwrap();
+/// parser.feed(b"34 }").unwrap();
+/// let json = parser.finish().unwrap();
+/// assert_eq!(json["name"], JsonType::from("café"));
+/// assert_eq!(json["n"], JsonType::from(1234));
+/// ```
+pub struct JsonPushParser {
+    reader: JsonReader<PushSource>,
+    builder: TreeBuilder,
+}
+
+impl Default for JsonPushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonPushParser {
+    pub fn new() -> Self {
+        Self::with_options(&ParseOptions::default())
+    }
+
+    pub fn with_options(options: &ParseOptions) -> Self {
+        JsonPushParser {
+            reader: JsonReader::with_source(PushSource::new()).with_options(options),
+            builder: TreeBuilder::default(),
+        }
+    }
+
+    /// parse the next chunk into the tree returned by [`finish`](Self::finish)
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), JsonError> {
+        let builder = &mut self.builder;
+        drive(&mut self.reader, chunk, |event| builder.push(event))
+    }
+
+    /// signal the end of input and return the parsed value
+    pub fn finish(mut self) -> Result<JsonType, JsonError> {
+        self.reader.source_mut().finish();
+        let builder = &mut self.builder;
+        drive(&mut self.reader, &[], |event| builder.push(event))?;
+        match self.builder.take_root() {
+            Some(root) => Ok(root),
+            None => Err(self.reader.error(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    /// parse the next chunk and hand every complete event to `handler`
+    /// instead of building a tree
+    ///
+    /// ```
+    /// use dynamic_json::{Event, JsonPushParser, Scalar};
+    /// let mut parser = JsonPushParser::new();
+    /// let mut strings = vec![];
+    /// let mut handler = |event: Event| {
+    ///     if let Event::Value(Scalar::String(s)) = event {
+    ///         strings.push(s.to_string());
+    ///     }
+    /// };
+    /// parser.feed_events(br#"["ab"#, &mut handler).unwrap();
+    /// parser.feed_events(br#"c", "d"]"#, &mut handler).unwrap();
+    /// parser.finish_events(&mut handler).unwrap();
+    /// assert_eq!(strings, ["abc", "d"]);
+    /// ```
+    pub fn feed_events<F: FnMut(Event<'_>)>(
+        &mut self,
+        chunk: &[u8],
+        handler: F,
+    ) -> Result<(), JsonError> {
+        drive(&mut self.reader, chunk, handler)
+    }
+
+    /// signal the end of input, reporting the remaining events to `handler`
+    pub fn finish_events<F: FnMut(Event<'_>)>(mut self, handler: F) -> Result<(), JsonError> {
+        self.reader.source_mut().finish();
+        drive(&mut self.reader, &[], handler)?;
+        if !self.reader.is_complete() {
+            return Err(self.reader.error(ErrorKind::UnexpectedEof));
+        }
+        Ok(())
+    }
+}
+
+/// report events until the input runs out in the middle of a token
+fn drive<F: FnMut(Event<'_>)>(
+    reader: &mut JsonReader<PushSource>,
+    chunk: &[u8],
+    mut handler: F,
+) -> Result<(), JsonError> {
+    reader.source_mut().feed(chunk);
+    loop {
+        match reader.next_event() {
+            Ok(Some(event)) => handler(event),
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                if reader.source_mut().rewind_if_starved() {
+                    return Ok(());
+                }
+                return Err(err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonPushParser;
+    use crate::error::ErrorKind;
+    use crate::parse;
+
+    #[test]
+    fn feed_every_split() {
+        let input = r#" {"a": [1.5e3, -0, true, null], "s": "x\"\\é😀ü", "o": {}} "#;
+        let espect = parse(input);
+        for size in 1..input.len() {
+            let mut parser = JsonPushParser::new();
+            for chunk in input.as_bytes().chunks(size) {
+                parser.feed(chunk).unwrap();
+            }
+            assert_eq!(parser.finish().unwrap(), espect, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn resume_inside_tokens() {
+        // a chunk ending inside a string or number goes back to the last piece
+        // of it that was complete, only the bytes of an escape are read again
+        let text = r#"plain text, é and 😀\n\u00e9"#.repeat(2000) + r#"\ud83d\ude00"#;
+        let input = format!(
+            r#"{{"{}": [-1.{}5e10, "{}"]}}"#,
+            text,
+            "2".repeat(5000),
+            text
+        );
+        let mut parser = JsonPushParser::new();
+        for byte in input.as_bytes() {
+            parser.feed(std::slice::from_ref(byte)).unwrap();
+        }
+        let reread = parser.reader.source_mut().reread;
+        assert!(reread < input.len(), "{} bytes read again", reread);
+        assert_eq!(parser.finish().unwrap(), parse(&input));
+    }
+
+    #[test]
+    fn push_errors() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"[1, 2").unwrap();
+        let err = parser.finish().unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedEof));
+
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"[1, 2").unwrap();
+        parser.feed(b"3, \n").unwrap();
+        let err = parser.feed(b"tru]").unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidLiteral));
+        assert_eq!(err.offset(), Some(12));
+        assert_eq!(err.line(), Some(2));
+
+        // a number at the end of input is only complete once `finish` is called
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"42").unwrap();
+        assert_eq!(parser.finish().unwrap(), parse("42"));
+    }
+}
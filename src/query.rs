@@ -0,0 +1,1236 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::error::QueryError;
+use crate::regex::Regex;
+use crate::JsonType;
+
+/// array indices and slice bounds must be exact in a double, RFC 9535 2.1
+const MAX_INT: i64 = (1 << 53) - 1;
+
+/// a JSONPath query as defined by RFC 9535, parsed once and run against any
+/// number of values
+///
+/// ```
+/// use dynamic_json::{parse, JsonQuery};
+/// let json = parse(r#"{ "items": [
+///     { "name": "pen", "price": 2 },
+///     { "name": "book", "price": 12 }
+/// ] }"#);
+/// let query = JsonQuery::parse("$.items[?@.price < 10].name").unwrap();
+/// let names: Vec<_> = query.select(&json).into_iter().filter_map(|v| v.as_str()).collect();
+/// assert_eq!(names, ["pen"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonQuery {
+    segments: Vec<QuerySegment>,
+}
+
+#[derive(Debug, Clone)]
+struct QuerySegment {
+    /// `..` selects from the node and all its descendants
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    /// a query is true when it selects something, a function by its result
+    Test(Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(JsonType),
+    /// `@` when relative, `$` otherwise
+    Query {
+        relative: bool,
+        query: JsonQuery,
+    },
+    Call(Function, Vec<Operand>, Pattern),
+}
+
+/// the pattern of `match` and `search`
+#[derive(Debug, Clone)]
+enum Pattern {
+    /// a literal pattern compiled when the query is parsed, `None` if it is
+    /// not a valid I-Regexp
+    Literal(Option<Regex>),
+    /// compiled for each node, also used by the other functions
+    Dynamic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+/// the types of the function extensions, RFC 9535 2.4.1
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Value,
+    Logical,
+    Nodes,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "length" => Function::Length,
+            "count" => Function::Count,
+            "match" => Function::Match,
+            "search" => Function::Search,
+            "value" => Function::Value,
+            _ => return None,
+        })
+    }
+
+    fn params(self) -> &'static [Type] {
+        match self {
+            Function::Length => &[Type::Value],
+            Function::Count | Function::Value => &[Type::Nodes],
+            Function::Match | Function::Search => &[Type::Value, Type::Value],
+        }
+    }
+
+    fn result(self) -> Type {
+        match self {
+            Function::Match | Function::Search => Type::Logical,
+            _ => Type::Value,
+        }
+    }
+}
+
+impl Operand {
+    /// whether the operand can stand where a single value is expected
+    fn is_value(&self) -> bool {
+        match self {
+            Operand::Literal(_) => true,
+            Operand::Query { query, .. } => query.is_singular(),
+            Operand::Call(function, ..) => function.result() == Type::Value,
+        }
+    }
+}
+
+/// a step of the path to a selected node
+#[derive(Clone)]
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+#[derive(Clone)]
+struct Node<'a> {
+    /// empty unless paths were asked for
+    path: Vec<Step<'a>>,
+    value: &'a JsonType,
+}
+
+impl JsonQuery {
+    pub fn parse(query: &str) -> Result<JsonQuery, QueryError> {
+        let mut parser = Parser {
+            src: query,
+            pos: 0,
+            depth: 0,
+        };
+        if !parser.eat(b'$') {
+            return Err(parser.error("a query starts with `$`"));
+        }
+        let query = parser.segments()?;
+        if parser.pos != parser.src.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(query)
+    }
+
+    /// the values the query selects, in the order RFC 9535 gives them
+    pub fn select<'a>(&self, json: &'a JsonType) -> Vec<&'a JsonType> {
+        let root = Node {
+            path: vec![],
+            value: json,
+        };
+        let nodes = self.run(json, root, false);
+        nodes.into_iter().map(|node| node.value).collect()
+    }
+
+    /// the values the query selects together with their normalized paths,
+    /// like `$['items'][0]`
+    pub fn select_paths<'a>(&self, json: &'a JsonType) -> Vec<(String, &'a JsonType)> {
+        let root = Node {
+            path: vec![],
+            value: json,
+        };
+        let nodes = self.run(json, root, true);
+        nodes
+            .into_iter()
+            .map(|node| (normalized_path(&node.path), node.value))
+            .collect()
+    }
+
+    /// only names and indices, each segment selecting at most one node
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors.as_slice(),
+                    [Selector::Name(_) | Selector::Index(_)]
+                )
+        })
+    }
+
+    fn run<'a>(&self, root: &'a JsonType, start: Node<'a>, paths: bool) -> Vec<Node<'a>> {
+        let mut nodes = vec![start];
+        for segment in &self.segments {
+            let mut selected = vec![];
+            for node in &nodes {
+                if segment.descendant {
+                    let mut stack = vec![node.clone()];
+                    while let Some(node) = stack.pop() {
+                        for selector in &segment.selectors {
+                            selector.select(root, &node, paths, &mut selected);
+                        }
+                        let len = stack.len();
+                        children(&node, paths, &mut stack);
+                        stack[len..].reverse();
+                    }
+                } else {
+                    for selector in &segment.selectors {
+                        selector.select(root, node, paths, &mut selected);
+                    }
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+}
+
+fn child<'a>(node: &Node<'a>, step: Step<'a>, value: &'a JsonType, paths: bool) -> Node<'a> {
+    let mut path = vec![];
+    if paths {
+        path.reserve(node.path.len() + 1);
+        path.extend(node.path.iter().cloned());
+        path.push(step);
+    }
+    Node { path, value }
+}
+
+fn children<'a>(node: &Node<'a>, paths: bool, out: &mut Vec<Node<'a>>) {
+    match node.value {
+        JsonType::Object(obj) => {
+            for (k, v) in obj {
+                out.push(child(node, Step::Key(k), v, paths));
+            }
+        }
+        JsonType::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                out.push(child(node, Step::Index(i), v, paths));
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Selector {
+    fn select<'a>(
+        &self,
+        root: &'a JsonType,
+        node: &Node<'a>,
+        paths: bool,
+        out: &mut Vec<Node<'a>>,
+    ) {
+        match (self, node.value) {
+            (Selector::Name(name), JsonType::Object(obj)) => {
+                if let Some((k, v)) = obj.inner_map.get_key_value(name) {
+                    out.push(child(node, Step::Key(k), v, paths));
+                }
+            }
+            (Selector::Wildcard, _) => children(node, paths, out),
+            (Selector::Index(index), JsonType::Array(arr)) => {
+                let len = arr.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+                if (0..len).contains(&index) {
+                    let index = index as usize;
+                    out.push(child(node, Step::Index(index), &arr[index], paths));
+                }
+            }
+            (Selector::Slice(start, end, step), JsonType::Array(arr)) => {
+                for index in slice(arr.len() as i64, *start, *end, *step) {
+                    out.push(child(node, Step::Index(index), &arr[index], paths));
+                }
+            }
+            (Selector::Filter(expr), JsonType::Object(_) | JsonType::Array(_)) => {
+                let mut candidates = vec![];
+                children(node, paths, &mut candidates);
+                out.extend(
+                    candidates
+                        .into_iter()
+                        .filter(|candidate| expr.eval(root, candidate.value)),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// the indices of an array slice, RFC 9535 2.3.4.2.2
+fn slice(len: i64, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indices = vec![];
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+impl Expr {
+    fn eval(&self, root: &JsonType, current: &JsonType) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.eval(root, current)),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.eval(root, current)),
+            Expr::Not(expr) => !expr.eval(root, current),
+            Expr::Compare(left, op, right) => {
+                let left = left.value(root, current);
+                let right = right.value(root, current);
+                compare(left.as_deref(), *op, right.as_deref())
+            }
+            Expr::Test(Operand::Query { relative, query }) => !query
+                .select(if *relative { current } else { root })
+                .is_empty(),
+            Expr::Test(Operand::Call(function, args, pattern)) => {
+                call_logical(*function, args, pattern, root, current)
+            }
+            Expr::Test(Operand::Literal(_)) => false,
+        }
+    }
+}
+
+impl Operand {
+    /// the value of an operand of value type, `None` is Nothing
+    fn value<'a>(&'a self, root: &'a JsonType, current: &'a JsonType) -> Option<Cow<'a, JsonType>> {
+        match self {
+            Operand::Literal(value) => Some(Cow::Borrowed(value)),
+            Operand::Query { relative, query } => {
+                let nodes = query.select(if *relative { current } else { root });
+                match nodes.as_slice() {
+                    [value] => Some(Cow::Borrowed(*value)),
+                    _ => None,
+                }
+            }
+            Operand::Call(function, args, _) => call_value(*function, args, root, current),
+        }
+    }
+
+    fn nodes<'a>(&self, root: &'a JsonType, current: &'a JsonType) -> Vec<&'a JsonType> {
+        match self {
+            Operand::Query { relative, query } => {
+                query.select(if *relative { current } else { root })
+            }
+            _ => vec![],
+        }
+    }
+}
+
+fn call_value<'a>(
+    function: Function,
+    args: &'a [Operand],
+    root: &'a JsonType,
+    current: &'a JsonType,
+) -> Option<Cow<'a, JsonType>> {
+    let len = match function {
+        Function::Length => match args[0].value(root, current)?.as_ref() {
+            JsonType::String(s) => s.chars().count(),
+            JsonType::Array(arr) => arr.len(),
+            JsonType::Object(obj) => obj.len(),
+            _ => return None,
+        },
+        Function::Count => args[0].nodes(root, current).len(),
+        Function::Value => {
+            return match args[0].nodes(root, current).as_slice() {
+                [value] => Some(Cow::Borrowed(*value)),
+                _ => None,
+            }
+        }
+        Function::Match | Function::Search => return None,
+    };
+    Some(Cow::Owned(JsonType::Number(len as f64)))
+}
+
+fn call_logical(
+    function: Function,
+    args: &[Operand],
+    pattern: &Pattern,
+    root: &JsonType,
+    current: &JsonType,
+) -> bool {
+    let input = args[0].value(root, current);
+    let input = match input.as_deref() {
+        Some(JsonType::String(input)) => input,
+        _ => return false,
+    };
+    let regex = match pattern {
+        Pattern::Literal(regex) => regex.as_ref().map(Cow::Borrowed),
+        Pattern::Dynamic => match args[1].value(root, current).as_deref() {
+            Some(JsonType::String(pattern)) => Regex::new(pattern).map(Cow::Owned),
+            _ => None,
+        },
+    };
+    match regex {
+        Some(regex) if function == Function::Match => regex.is_match(input),
+        Some(regex) => regex.find(input),
+        None => false,
+    }
+}
+
+/// RFC 9535 2.3.5.2.2, Nothing only equals Nothing and only numbers and
+/// strings are ordered
+fn compare(left: Option<&JsonType>, op: Op, right: Option<&JsonType>) -> bool {
+    let less = |a: Option<&JsonType>, b: Option<&JsonType>| match (a, b) {
+        (Some(JsonType::Number(a)), Some(JsonType::Number(b))) => a < b,
+        (Some(JsonType::String(a)), Some(JsonType::String(b))) => a < b,
+        _ => false,
+    };
+    match op {
+        Op::Eq => left == right,
+        Op::Ne => left != right,
+        Op::Lt => less(left, right),
+        Op::Le => less(left, right) || left == right,
+        Op::Gt => less(right, left),
+        Op::Ge => less(right, left) || left == right,
+    }
+}
+
+/// `$['a'][0]`, names in single quotes with the escapes of RFC 9535 2.7
+fn normalized_path(path: &[Step]) -> String {
+    let mut out = String::from("$");
+    for step in path {
+        match step {
+            Step::Index(index) => {
+                let _ = write!(out, "[{}]", index);
+            }
+            Step::Key(key) => {
+                out.push_str("['");
+                for c in key.chars() {
+                    match c {
+                        '\u{8}' => out.push_str("\\b"),
+                        '\u{c}' => out.push_str("\\f"),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        '\t' => out.push_str("\\t"),
+                        '\'' => out.push_str("\\'"),
+                        '\\' => out.push_str("\\\\"),
+                        c if c < ' ' => {
+                            let _ = write!(out, "\\u{:04x}", c as u32);
+                        }
+                        c => out.push(c),
+                    }
+                }
+                out.push_str("']");
+            }
+        }
+    }
+    out
+}
+
+/// nesting limit of filters and parentheses
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn error(&self, message: &str) -> QueryError {
+        QueryError::Syntax {
+            message: message.to_string(),
+            offset: self.pos,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.pos + n).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.src[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            return true;
+        }
+        false
+    }
+
+    fn blank(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), QueryError> {
+        if !self.eat(b) {
+            return Err(self.error(&format!("expect `{}`", b as char)));
+        }
+        Ok(())
+    }
+
+    /// the segments after `$` or `@`, blanks may separate them
+    fn segments(&mut self) -> Result<JsonQuery, QueryError> {
+        let mut segments = vec![];
+        loop {
+            let start = self.pos;
+            self.blank();
+            match self.peek() {
+                Some(b'.') if self.peek_at(1) == Some(b'.') => {
+                    self.pos += 2;
+                    let selectors = match self.peek() {
+                        Some(b'[') => self.brackets()?,
+                        Some(b'*') => {
+                            self.pos += 1;
+                            vec![Selector::Wildcard]
+                        }
+                        _ => vec![Selector::Name(self.shorthand()?)],
+                    };
+                    segments.push(QuerySegment {
+                        descendant: true,
+                        selectors,
+                    });
+                }
+                Some(b'.') => {
+                    self.pos += 1;
+                    let selector = match self.eat(b'*') {
+                        true => Selector::Wildcard,
+                        false => Selector::Name(self.shorthand()?),
+                    };
+                    segments.push(QuerySegment {
+                        descendant: false,
+                        selectors: vec![selector],
+                    });
+                }
+                Some(b'[') => {
+                    let selectors = self.brackets()?;
+                    segments.push(QuerySegment {
+                        descendant: false,
+                        selectors,
+                    });
+                }
+                _ => {
+                    self.pos = start;
+                    return Ok(JsonQuery { segments });
+                }
+            }
+        }
+    }
+
+    /// a member name after `.` or `..`
+    fn shorthand(&mut self) -> Result<String, QueryError> {
+        let rest = &self.src[self.pos..];
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| {
+                let first = c.is_ascii_alphabetic() || *c == '_' || !c.is_ascii();
+                !(first || (*i > 0 && c.is_ascii_digit()))
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        if len == 0 {
+            return Err(self.error("expect a member name"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    /// `[` selectors separated by commas `]`
+    fn brackets(&mut self) -> Result<Vec<Selector>, QueryError> {
+        self.expect(b'[')?;
+        let mut selectors = vec![];
+        loop {
+            self.blank();
+            selectors.push(self.selector()?);
+            self.blank();
+            if self.eat(b']') {
+                return Ok(selectors);
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expect `,` or `]`"));
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, QueryError> {
+        Ok(match self.peek() {
+            Some(b'\'' | b'"') => Selector::Name(self.string()?),
+            Some(b'*') => {
+                self.pos += 1;
+                Selector::Wildcard
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                self.blank();
+                self.nest()?;
+                let expr = self.or()?;
+                self.depth -= 1;
+                Selector::Filter(expr)
+            }
+            _ => {
+                let start = self.int_opt()?;
+                self.blank();
+                if !self.eat(b':') {
+                    return match start {
+                        Some(index) => Ok(Selector::Index(index)),
+                        None => Err(self.error("expect a selector")),
+                    };
+                }
+                self.blank();
+                let end = self.int_opt()?;
+                self.blank();
+                let step = match self.eat(b':') {
+                    true => {
+                        self.blank();
+                        self.int_opt()?
+                    }
+                    false => None,
+                };
+                Selector::Slice(start, end, step)
+            }
+        })
+    }
+
+    fn nest(&mut self) -> Result<(), QueryError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        Ok(())
+    }
+
+    /// an integer without leading zeros in the exact range of a double
+    fn int_opt(&mut self) -> Result<Option<i64>, QueryError> {
+        let start = self.pos;
+        let negative = self.eat(b'-');
+        let digits = self.src[self.pos..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if digits == 0 {
+            if negative {
+                return Err(self.error("expect a digit"));
+            }
+            return Ok(None);
+        }
+        let text = &self.src[start..self.pos + digits];
+        if (digits > 1 && self.peek() == Some(b'0')) || text == "-0" {
+            return Err(self.error("invalid integer"));
+        }
+        match text.parse::<i64>() {
+            Ok(int) if (-MAX_INT..=MAX_INT).contains(&int) => {
+                self.pos += digits;
+                Ok(Some(int))
+            }
+            _ => Err(self.error("integer out of range")),
+        }
+    }
+
+    /// a string literal in single or double quotes
+    fn string(&mut self) -> Result<String, QueryError> {
+        let quote = self.peek().unwrap_or_default();
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = match self.src[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            match c {
+                c if c as u32 == quote as u32 => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'/') => '/',
+                        Some(b'\\') => '\\',
+                        Some(b) if b == quote => b as char,
+                        Some(b'u') => {
+                            self.pos += 1;
+                            out.push(self.unicode()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    out.push(escaped);
+                    self.pos += 1;
+                }
+                c if c < ' ' => return Err(self.error("control character in string")),
+                c => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// the hex digits of `\u`, with a following low surrogate if needed
+    fn unicode(&mut self) -> Result<char, QueryError> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat_str("\\u") {
+                    return Err(self.error("lone surrogate"));
+                }
+                let low = self.hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error("lone surrogate"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(self.error("lone surrogate")),
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, QueryError> {
+        let hex = self.src.get(self.pos..self.pos + 4).unwrap_or_default();
+        if hex.len() != 4 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(hex, 16).unwrap_or_default())
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut exprs = vec![self.and()?];
+        loop {
+            self.blank();
+            if !self.eat_str("||") {
+                break;
+            }
+            self.blank();
+            exprs.push(self.and()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => Expr::Or(exprs),
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut exprs = vec![self.basic()?];
+        loop {
+            let start = self.pos;
+            self.blank();
+            if !self.eat_str("&&") {
+                self.pos = start;
+                break;
+            }
+            self.blank();
+            exprs.push(self.basic()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => Expr::And(exprs),
+        })
+    }
+
+    fn basic(&mut self) -> Result<Expr, QueryError> {
+        if self.eat(b'!') {
+            self.blank();
+            let start = self.pos;
+            let expr = self.basic()?;
+            if let Expr::Compare(..) = expr {
+                self.pos = start;
+                return Err(self.error("a comparison must be in parentheses to be negated"));
+            }
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        if self.eat(b'(') {
+            self.nest()?;
+            self.blank();
+            let expr = self.or()?;
+            self.blank();
+            self.expect(b')')?;
+            self.depth -= 1;
+            return Ok(expr);
+        }
+        let start = self.pos;
+        let left = self.operand()?;
+        let before_op = self.pos;
+        self.blank();
+        let op = match self.comparison_op() {
+            Some(op) => op,
+            None => {
+                self.pos = before_op;
+                return match &left {
+                    Operand::Query { .. } => Ok(Expr::Test(left)),
+                    Operand::Call(function, ..) if function.result() != Type::Value => {
+                        Ok(Expr::Test(left))
+                    }
+                    _ => {
+                        self.pos = start;
+                        Err(self.error("expect a query or a function that is not of value type"))
+                    }
+                };
+            }
+        };
+        if !left.is_value() {
+            self.pos = start;
+            return Err(self.error("expect a literal, singular query or value function"));
+        }
+        self.blank();
+        let start = self.pos;
+        let right = self.operand()?;
+        if !right.is_value() {
+            self.pos = start;
+            return Err(self.error("expect a literal, singular query or value function"));
+        }
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn comparison_op(&mut self) -> Option<Op> {
+        for (text, op) in [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if self.eat_str(text) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    /// a literal, a query starting with `@` or `$`, or a function call
+    fn operand(&mut self) -> Result<Operand, QueryError> {
+        match self.peek() {
+            Some(b'@' | b'$') => {
+                let relative = self.peek() == Some(b'@');
+                self.pos += 1;
+                self.nest()?;
+                let query = self.segments()?;
+                self.depth -= 1;
+                Ok(Operand::Query { relative, query })
+            }
+            Some(b'\'' | b'"') => Ok(Operand::Literal(JsonType::String(self.string()?))),
+            Some(b'-' | b'0'..=b'9') => Ok(Operand::Literal(JsonType::Number(self.number()?))),
+            Some(b'a'..=b'z') => {
+                let name_len = self.src[self.pos..]
+                    .bytes()
+                    .take_while(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_'))
+                    .count();
+                let name = &self.src[self.pos..self.pos + name_len];
+                if self.peek_at(name_len) != Some(b'(') {
+                    let literal = match name {
+                        "true" => JsonType::Bool(true),
+                        "false" => JsonType::Bool(false),
+                        "null" => JsonType::Null,
+                        _ => return Err(self.error("expect a literal or a function call")),
+                    };
+                    self.pos += name_len;
+                    return Ok(Operand::Literal(literal));
+                }
+                let function = Function::from_name(name)
+                    .ok_or_else(|| self.error(&format!("unknown function `{}`", name)))?;
+                self.pos += name_len + 1;
+                self.nest()?;
+                let args = self.args(function)?;
+                self.depth -= 1;
+                let pattern = match (function, args.get(1)) {
+                    (
+                        Function::Match | Function::Search,
+                        Some(Operand::Literal(JsonType::String(pattern))),
+                    ) => Pattern::Literal(Regex::new(pattern)),
+                    _ => Pattern::Dynamic,
+                };
+                Ok(Operand::Call(function, args, pattern))
+            }
+            _ => Err(self.error("expect a filter expression")),
+        }
+    }
+
+    /// the arguments of `function` up to `)`, checked against its parameter types
+    fn args(&mut self, function: Function) -> Result<Vec<Operand>, QueryError> {
+        let mut args = vec![];
+        self.blank();
+        if !self.eat(b')') {
+            loop {
+                let start = self.pos;
+                let arg = self.operand()?;
+                let param = function.params().get(args.len());
+                let fits = match param {
+                    Some(Type::Value) => arg.is_value(),
+                    Some(Type::Nodes) => matches!(arg, Operand::Query { .. }),
+                    _ => false,
+                };
+                if !fits {
+                    self.pos = start;
+                    return Err(self.error(&format!(
+                        "argument {} of `{}` has the wrong type",
+                        args.len() + 1,
+                        function.name()
+                    )));
+                }
+                args.push(arg);
+                self.blank();
+                if self.eat(b')') {
+                    break;
+                }
+                self.expect(b',')?;
+                self.blank();
+            }
+        }
+        if args.len() != function.params().len() {
+            return Err(self.error(&format!(
+                "`{}` takes {} argument(s)",
+                function.name(),
+                function.params().len()
+            )));
+        }
+        Ok(args)
+    }
+
+    /// a number literal, `-0` is allowed here unlike in indices
+    fn number(&mut self) -> Result<f64, QueryError> {
+        let start = self.pos;
+        self.eat(b'-');
+        let int = self.src[self.pos..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if int == 0 || (int > 1 && self.peek() == Some(b'0')) {
+            return Err(self.error("invalid number"));
+        }
+        self.pos += int;
+        if self.eat(b'.') {
+            let frac = self.src[self.pos..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            if frac == 0 {
+                return Err(self.error("invalid number"));
+            }
+            self.pos += frac;
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            let exp = self.src[self.pos..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            if exp == 0 {
+                return Err(self.error("invalid number"));
+            }
+            self.pos += exp;
+        }
+        self.src[start..self.pos]
+            .parse()
+            .map_err(|_| self.error("invalid number"))
+    }
+}
+
+impl Function {
+    fn name(self) -> &'static str {
+        match self {
+            Function::Length => "length",
+            Function::Count => "count",
+            Function::Match => "match",
+            Function::Search => "search",
+            Function::Value => "value",
+        }
+    }
+}
+
+impl JsonType {
+    /// run a JSONPath query (RFC 9535) against this value
+    ///
+    /// ```
+    /// use dynamic_json::parse;
+    /// let json = parse(r#"{ "a": { "id": 1, "b": [{ "id": 2 }, { "id": 3 }] } }"#);
+    /// let ids: Vec<_> = json.query("$..id").unwrap().iter().filter_map(|v| v.as_u64()).collect();
+    /// assert_eq!(ids, [1, 2, 3]);
+    /// assert!(json.query("$.a[?length(@)]").is_err());
+    /// ```
+    pub fn query(&self, query: &str) -> Result<Vec<&JsonType>, QueryError> {
+        Ok(JsonQuery::parse(query)?.select(self))
+    }
+
+    /// like [`query`](JsonType::query) but each match comes with its
+    /// normalized path
+    ///
+    /// ```
+    /// use dynamic_json::parse;
+    /// let json = parse(r#"{ "a": [10, 20, 30] }"#);
+    /// let paths = json.query_paths("$.a[-2:]").unwrap();
+    /// assert_eq!(paths[0].0, "$['a'][1]");
+    /// assert_eq!(paths[1].1.as_u64(), Some(30));
+    /// ```
+    pub fn query_paths(&self, query: &str) -> Result<Vec<(String, &JsonType)>, QueryError> {
+        Ok(JsonQuery::parse(query)?.select_paths(self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Expr, JsonQuery, Operand, Pattern, Selector};
+    use crate::parse;
+
+    fn paths(json: &str, query: &str) -> Vec<String> {
+        let json = parse(json);
+        let paths = json.query_paths(query).unwrap();
+        paths.into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn rfc9535_queries() {
+        let store = r#"{ "store": {
+            "book": [
+                { "category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95 },
+                { "category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99 },
+                { "category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+                { "category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+            ],
+            "bicycle": { "color": "red", "price": 399 }
+        } }"#;
+        let cases: &[(&str, &[&str])] = &[
+            ("$", &["$"]),
+            (
+                "$.store.book[*].author",
+                &[
+                    "$['store']['book'][0]['author']",
+                    "$['store']['book'][1]['author']",
+                    "$['store']['book'][2]['author']",
+                    "$['store']['book'][3]['author']",
+                ],
+            ),
+            (
+                "$..author",
+                &[
+                    "$['store']['book'][0]['author']",
+                    "$['store']['book'][1]['author']",
+                    "$['store']['book'][2]['author']",
+                    "$['store']['book'][3]['author']",
+                ],
+            ),
+            (
+                "$.store.*",
+                &["$['store']['book']", "$['store']['bicycle']"],
+            ),
+            (
+                "$.store..price",
+                &[
+                    "$['store']['book'][0]['price']",
+                    "$['store']['book'][1]['price']",
+                    "$['store']['book'][2]['price']",
+                    "$['store']['book'][3]['price']",
+                    "$['store']['bicycle']['price']",
+                ],
+            ),
+            ("$..book[2]", &["$['store']['book'][2]"]),
+            ("$..book[-1]", &["$['store']['book'][3]"]),
+            (
+                "$..book[0,1]",
+                &["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[:2]",
+                &["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[::-2]",
+                &["$['store']['book'][3]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[?@.isbn]",
+                &["$['store']['book'][2]", "$['store']['book'][3]"],
+            ),
+            (
+                "$..book[?@.price<10]",
+                &["$['store']['book'][0]", "$['store']['book'][2]"],
+            ),
+            (
+                "$..book[?@.price > 10 && !(@.category == 'fiction' && @.isbn)]",
+                &["$['store']['book'][1]"],
+            ),
+            (
+                "$..book[?@.price == $.store.bicycle.price || @.author == \"Nigel Rees\"]",
+                &["$['store']['book'][0]"],
+            ),
+            (
+                "$..book[?match(@.author, 'J.*')].title",
+                &["$['store']['book'][3]['title']"],
+            ),
+            (
+                "$..book[?search(@.title, 'of')]",
+                &[
+                    "$['store']['book'][0]",
+                    "$['store']['book'][1]",
+                    "$['store']['book'][3]",
+                ],
+            ),
+            ("$..book[?length(@.title) == 9]", &["$['store']['book'][2]"]),
+            ("$.store[?count(@.*) == 2]", &["$['store']['bicycle']"]),
+            (
+                "$..book[?value(@..isbn) == '0-553-21311-3']",
+                &["$['store']['book'][2]"],
+            ),
+            (
+                "$..book[?@.missing == @.other]",
+                &[
+                    "$['store']['book'][0]",
+                    "$['store']['book'][1]",
+                    "$['store']['book'][2]",
+                    "$['store']['book'][3]",
+                ],
+            ),
+            (
+                "$ .store ['bicycle'] [ 'color' , \"price\" ]",
+                &[
+                    "$['store']['bicycle']['color']",
+                    "$['store']['bicycle']['price']",
+                ],
+            ),
+            ("$.nothing[0]", &[]),
+        ];
+        for (query, espect) in cases {
+            assert_eq!(paths(store, query), *espect, "{}", query);
+        }
+
+        let json = r#"{ "o": { "j": 1, "k": 2 }, "a": [5, 3, [{ "j": 4 }, { "k": 6 }]] }"#;
+        assert_eq!(paths(json, "$..j"), ["$['o']['j']", "$['a'][2][0]['j']"]);
+        assert_eq!(paths(json, "$.a[?@ > 3]"), ["$['a'][0]"]);
+        assert_eq!(paths(r#"{ "a'b\n": 1 }"#, "$.*"), ["$['a\\'b\\n']"]);
+        assert_eq!(paths("[0, 1, 2, 3, 4, 5]", "$[5:1:-2]"), ["$[5]", "$[3]"]);
+        assert_eq!(paths("[0, 1]", "$[0:2:0]"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn literal_patterns() {
+        let pattern = |query: &str| {
+            let query = JsonQuery::parse(query).unwrap();
+            match &query.segments[0].selectors[0] {
+                Selector::Filter(Expr::Test(Operand::Call(_, _, pattern))) => pattern.clone(),
+                _ => unreachable!(),
+            }
+        };
+        assert!(matches!(
+            pattern("$[?match(@, 'a.*')]"),
+            Pattern::Literal(Some(_))
+        ));
+        assert!(matches!(
+            pattern("$[?search(@, '(')]"),
+            Pattern::Literal(None)
+        ));
+        assert!(matches!(pattern("$[?search(@, @)]"), Pattern::Dynamic));
+
+        let json = parse(r#"["ab", "b", "("]"#);
+        assert_eq!(json.query("$[?match(@, 'a.*')]").unwrap(), [&json[0]]);
+        assert!(json.query("$[?search(@, '(')]").unwrap().is_empty());
+        assert_eq!(
+            json.query("$[?search(@, @)]").unwrap(),
+            [&json[0], &json[1]]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_queries() {
+        let json = parse("{}");
+        let invalid = [
+            "",
+            " $",
+            "$ ",
+            "$.",
+            "$..",
+            "$[",
+            "$[01]",
+            "$[-0]",
+            "$['a'",
+            "$['\\x']",
+            "$[9007199254740992]",
+            "$.1a",
+            "$[?@.a == @.*]",
+            "$[?@..a == 1]",
+            "$[?1]",
+            "$[?length(@)]",
+            "$[?count(@.a) == 1 == 2]",
+            "$[?foo(@)]",
+            "$[?match(@.a)]",
+            "$[?length(@.*) == 1]",
+            "$[?count(1) == 1]",
+            "$[?!@.a == 1]",
+            "$[?@.a === 1]",
+            "$[?(@.a]",
+            "$[?@.a == 01]",
+            "$[?true]",
+            "$[?match(@.a, 'x') == true]",
+        ];
+        for query in invalid {
+            assert!(json.query(query).is_err(), "{}", query);
+        }
+        let err = json.query("$.a[?@.b = 1]").unwrap_err();
+        assert_eq!(err.to_string(), "expect `,` or `]` at byte 9 of the query");
+    }
+}
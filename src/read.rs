@@ -0,0 +1,475 @@
+use std::io;
+
+use crate::error::{ErrorKind, JsonError};
+use crate::utils::*;
+
+/// byte input that the parser pulls from
+pub trait Source {
+    /// the next byte without consuming it, `None` at the end of input
+    fn peek(&mut self) -> Result<Option<u8>, JsonError>;
+
+    /// the next character without consuming it, `None` at the end of input
+    /// or if the next bytes are not valid utf-8
+    fn peek_char(&mut self) -> Result<Option<char>, JsonError>;
+
+    /// consume the byte returned by the last `peek`
+    fn bump(&mut self);
+
+    /// append the bytes up to the next `quote`, `\` or control character to `out`,
+    /// this is the only place where utf-8 is validated
+    fn read_plain(&mut self, out: &mut String, quote: u8) -> Result<(), JsonError>;
+
+    /// error located at the next unconsumed byte
+    fn error(&self, kind: ErrorKind) -> JsonError;
+
+    /// error for the character at the next unconsumed byte
+    fn unexpected_char(&mut self) -> JsonError;
+
+    /// called before each token and at the points where the lexer can
+    /// continue an unfinished string or number, a source that receives its
+    /// input in pieces goes back to the last mark when it runs out
+    fn mark(&mut self) {}
+}
+
+fn is_plain(b: u8, quote: u8) -> bool {
+    b != quote && b != b'\\' && b >= 0x20
+}
+
+pub struct SliceSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceSource { bytes, pos: 0 }
+    }
+
+    /// number of bytes consumed so far
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<'a> Source for SliceSource<'a> {
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, JsonError> {
+        Ok(self.bytes.get(self.pos).copied())
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, JsonError> {
+        Ok(decode_char(&self.bytes[self.pos..]))
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    fn read_plain(&mut self, out: &mut String, quote: u8) -> Result<(), JsonError> {
+        let rest = &self.bytes[self.pos..];
+        let len = rest
+            .iter()
+            .position(|b| !is_plain(*b, quote))
+            .unwrap_or(rest.len());
+        match std::str::from_utf8(&rest[..len]) {
+            Ok(run) => {
+                out.push_str(run);
+                self.pos += len;
+                Ok(())
+            }
+            Err(err) => {
+                self.pos += err.valid_up_to();
+                Err(self.error(ErrorKind::InvalidUtf8))
+            }
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> JsonError {
+        parse_error(self.bytes, self.pos, kind)
+    }
+
+    fn unexpected_char(&mut self) -> JsonError {
+        unexpected_char(self.bytes, self.pos)
+    }
+}
+
+const BUFFER_SIZE: usize = 8 * 1024;
+/// bytes kept before the current position when the buffer is refilled,
+/// so that errors can still show where they happened
+const HISTORY_SIZE: usize = 64;
+
+/// reads from an `io::Read` through a fixed size buffer, so memory usage does not
+/// depend on the size of the input, only on the size of the values in it
+pub struct IoSource<R> {
+    reader: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    end: usize,
+    /// absolute offset of `buf[0]`
+    consumed: usize,
+    line: usize,
+    column: usize,
+    eof: bool,
+}
+
+impl<R: io::Read> IoSource<R> {
+    pub fn new(reader: R) -> Self {
+        IoSource {
+            reader,
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            end: 0,
+            consumed: 0,
+            line: 1,
+            column: 1,
+            eof: false,
+        }
+    }
+
+    /// read more bytes after `end`, returns false at the end of input
+    fn fill(&mut self) -> Result<bool, JsonError> {
+        if self.eof {
+            return Ok(false);
+        }
+        if self.end == self.buf.len() {
+            let keep_from = self.pos.saturating_sub(HISTORY_SIZE);
+            self.buf.copy_within(keep_from..self.end, 0);
+            self.consumed += keep_from;
+            self.pos -= keep_from;
+            self.end -= keep_from;
+        }
+        loop {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Ok(false);
+                }
+                Ok(n) => {
+                    self.end += n;
+                    return Ok(true);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    return Err(JsonError::Io {
+                        kind: err.kind(),
+                        message: err.to_string(),
+                        offset: self.consumed + self.end,
+                    })
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        for b in &self.buf[self.pos..self.pos + n] {
+            if *b == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if is_char_boundary(*b) {
+                self.column += 1;
+            }
+        }
+        self.pos += n;
+    }
+}
+
+impl<R: io::Read> Source for IoSource<R> {
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, JsonError> {
+        if self.pos == self.end && !self.fill()? {
+            return Ok(None);
+        }
+        Ok(Some(self.buf[self.pos]))
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, JsonError> {
+        while self.end - self.pos < 4 && self.fill()? {}
+        Ok(decode_char(&self.buf[self.pos..self.end]))
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        self.advance(1);
+    }
+
+    fn read_plain(&mut self, out: &mut String, quote: u8) -> Result<(), JsonError> {
+        loop {
+            if self.pos == self.end && !self.fill()? {
+                return Ok(());
+            }
+            let rest = &self.buf[self.pos..self.end];
+            let len = rest
+                .iter()
+                .position(|b| !is_plain(*b, quote))
+                .unwrap_or(rest.len());
+            let (valid, complete) = match std::str::from_utf8(&rest[..len]) {
+                Ok(run) => {
+                    out.push_str(run);
+                    (len, len < rest.len())
+                }
+                Err(err) => {
+                    let valid = err.valid_up_to();
+                    out.push_str(std::str::from_utf8(&rest[..valid]).unwrap_or_default());
+                    if err.error_len().is_some() {
+                        self.advance(valid);
+                        return Err(self.error(ErrorKind::InvalidUtf8));
+                    }
+                    // a character split by the end of the buffer
+                    (valid, false)
+                }
+            };
+            self.advance(valid);
+            if complete {
+                return Ok(());
+            }
+            if self.pos < self.end && !self.fill()? {
+                return Err(self.error(ErrorKind::InvalidUtf8));
+            }
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> JsonError {
+        JsonError::Syntax {
+            kind,
+            offset: self.consumed + self.pos,
+            line: self.line,
+            column: self.column,
+            snippet: nearby_content(&self.buf[..self.end], self.pos),
+        }
+    }
+
+    fn unexpected_char(&mut self) -> JsonError {
+        while self.end - self.pos < 4 && self.fill().unwrap_or(false) {}
+        match decode_char(&self.buf[self.pos..self.end]) {
+            Some(c) => self.error(ErrorKind::UnexpectedChar(c)),
+            None => self.error(ErrorKind::InvalidUtf8),
+        }
+    }
+}
+
+/// input that is handed over in chunks by [`JsonPushParser`](crate::JsonPushParser),
+/// running out of bytes before `finish` means the current token has to wait
+/// for the next chunk instead of being an error
+#[derive(Default)]
+pub struct PushSource {
+    buf: Vec<u8>,
+    pos: usize,
+    /// absolute offset of `buf[0]`
+    consumed: usize,
+    line: usize,
+    column: usize,
+    /// position, line and column of the last mark
+    mark: (usize, usize, usize),
+    finished: bool,
+    starved: bool,
+    /// bytes read again after going back to a mark
+    #[cfg(test)]
+    pub(crate) reread: usize,
+}
+
+impl PushSource {
+    pub fn new() -> Self {
+        PushSource {
+            line: 1,
+            column: 1,
+            mark: (0, 1, 1),
+            ..Default::default()
+        }
+    }
+
+    /// append a chunk, dropping the bytes before the last mark
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let keep_from = self.mark.0.saturating_sub(HISTORY_SIZE);
+        if keep_from > 0 {
+            self.buf.drain(..keep_from);
+            self.consumed += keep_from;
+            self.pos -= keep_from;
+            self.mark.0 -= keep_from;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// no more chunks will follow
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// if the last error was caused by running out of input, go back to the
+    /// last mark and return true
+    pub fn rewind_if_starved(&mut self) -> bool {
+        if !self.starved {
+            return false;
+        }
+        self.starved = false;
+        #[cfg(test)]
+        {
+            self.reread += self.pos - self.mark.0;
+        }
+        (self.pos, self.line, self.column) = self.mark;
+        true
+    }
+
+    fn starve(&mut self) -> JsonError {
+        self.starved = true;
+        self.error(ErrorKind::UnexpectedEof)
+    }
+
+    fn advance(&mut self, n: usize) {
+        for b in &self.buf[self.pos..self.pos + n] {
+            if *b == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if is_char_boundary(*b) {
+                self.column += 1;
+            }
+        }
+        self.pos += n;
+    }
+}
+
+impl Source for PushSource {
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, JsonError> {
+        match self.buf.get(self.pos) {
+            Some(b) => Ok(Some(*b)),
+            None if self.finished => Ok(None),
+            None => Err(self.starve()),
+        }
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, JsonError> {
+        let rest = &self.buf[self.pos..];
+        match decode_char(rest) {
+            None if rest.len() < 4 && !self.finished => Err(self.starve()),
+            c => Ok(c),
+        }
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        self.advance(1);
+    }
+
+    fn read_plain(&mut self, out: &mut String, quote: u8) -> Result<(), JsonError> {
+        let rest = &self.buf[self.pos..];
+        let len = rest
+            .iter()
+            .position(|b| !is_plain(*b, quote))
+            .unwrap_or(rest.len());
+        match std::str::from_utf8(&rest[..len]) {
+            Ok(run) => {
+                out.push_str(run);
+                self.advance(len);
+                Ok(())
+            }
+            Err(err) => {
+                let valid = err.valid_up_to();
+                out.push_str(std::str::from_utf8(&rest[..valid]).unwrap_or_default());
+                self.advance(valid);
+                if err.error_len().is_none() && !self.finished {
+                    // a character split between two chunks, the caller keeps
+                    // what was read and waits for the rest with `peek_char`
+                    return Ok(());
+                }
+                Err(self.error(ErrorKind::InvalidUtf8))
+            }
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> JsonError {
+        JsonError::Syntax {
+            kind,
+            offset: self.consumed + self.pos,
+            line: self.line,
+            column: self.column,
+            snippet: nearby_content(&self.buf, self.pos),
+        }
+    }
+
+    fn unexpected_char(&mut self) -> JsonError {
+        let rest = &self.buf[self.pos..];
+        match decode_char(rest) {
+            Some(c) => self.error(ErrorKind::UnexpectedChar(c)),
+            None if rest.len() < 4 && !self.finished => self.starve(),
+            None => self.error(ErrorKind::InvalidUtf8),
+        }
+    }
+
+    fn mark(&mut self) {
+        self.mark = (self.pos, self.line, self.column);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IoSource, Source};
+    use crate::error::ErrorKind;
+    use crate::{parse_bytes, JsonType};
+
+    /// a reader that returns at most `chunk` bytes per read call
+    struct Chunked<'a> {
+        bytes: &'a [u8],
+        chunk: usize,
+    }
+
+    impl<'a> std::io::Read for Chunked<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn read_plain_across_chunks() {
+        let text = "aéb😀c".repeat(3000);
+        let input = format!("{}\"", text);
+        let mut source = IoSource::new(Chunked {
+            bytes: input.as_bytes(),
+            chunk: 7,
+        });
+        let mut out = String::new();
+        source.read_plain(&mut out, b'"').unwrap();
+        assert_eq!(out, text);
+        assert_eq!(source.peek().unwrap(), Some(b'"'));
+
+        let mut source = IoSource::new(Chunked {
+            bytes: b"ab\xffcd",
+            chunk: 2,
+        });
+        let err = source.read_plain(&mut out, b'"').unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidUtf8));
+        assert_eq!(err.offset(), Some(2));
+    }
+
+    #[test]
+    fn parse_from_reader() {
+        let mut input = String::from("[");
+        for i in 0..2000 {
+            input.push_str(&format!(
+                r#"{{"id": {}, "name": "caf\u00e9 ü {}"}}, "#,
+                i, i
+            ));
+        }
+        input.push_str("null]");
+        let json = JsonType::from_reader(Chunked {
+            bytes: input.as_bytes(),
+            chunk: 3,
+        })
+        .unwrap();
+        assert_eq!(json, parse_bytes(input.as_bytes()).unwrap());
+
+        // errors report the absolute offset, not the offset inside the buffer
+        let broken = input.replace("null]", "nul]");
+        let err = JsonType::from_reader(Chunked {
+            bytes: broken.as_bytes(),
+            chunk: 1000,
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidLiteral));
+        assert_eq!(err.offset(), Some(broken.len() - 1));
+        assert_eq!(err.snippet().map(|s| s.ends_with("nul]")), Some(true));
+    }
+}
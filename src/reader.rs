@@ -0,0 +1,372 @@
+use std::io;
+
+use crate::error::{ErrorKind, JsonError};
+use crate::lexer::{Comment, Lexer, Token};
+use crate::options::ParseOptions;
+use crate::read::{IoSource, SliceSource, Source};
+
+/// a scalar json value borrowed from the reader
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Scalar<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(&'a str),
+}
+
+/// a step of the document structure reported by [`JsonReader`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event<'a> {
+    StartObject,
+    /// the key of the next object member, followed by the events of its value
+    Key(&'a str),
+    EndObject,
+    StartArray,
+    EndArray,
+    Value(Scalar<'a>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    /// expecting any value
+    Value,
+    /// right after `{`, expecting a key or `}`
+    ObjectFirst,
+    /// after `,` in an object, expecting a key
+    ObjectKey,
+    /// after a key, expecting `:`
+    Colon,
+    /// right after `[`, expecting a value or `]`
+    ArrayFirst,
+    /// after `,` in an array, expecting a value
+    ArrayNext,
+    /// after a member or element, expecting `,` or the end of the container
+    AfterValue,
+    /// the root value is complete, the rest of the input is not checked yet
+    End,
+    /// the rest of the input is checked
+    Done,
+}
+
+/// owned version of `Event` so the state machine can finish before
+/// borrowing the lexer for the result
+enum Emit {
+    StartObject,
+    Key,
+    EndObject,
+    StartArray,
+    EndArray,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String,
+}
+
+/// pull based reader that reports a json document as a sequence of events
+/// without building a tree, so memory usage only depends on the nesting depth
+///
+/// ```
+/// use dynamic_json::{Event, JsonReader, Scalar};
+/// let mut reader = JsonReader::new(r#"{ "a": [1, "x"] }"#);
+/// let mut events = vec![];
+/// while let Some(event) = reader.next_event().unwrap() {
+///     events.push(format!("{:?}", event));
+/// }
+/// assert_eq!(
+///     events,
+///     [
+///         "StartObject",
+///         "Key(\"a\")",
+///         "StartArray",
+///         "Value(Number(1.0))",
+///         "Value(String(\"x\"))",
+///         "EndArray",
+///         "EndObject",
+///     ]
+/// );
+/// ```
+pub struct JsonReader<S> {
+    lexer: Lexer<S>,
+    stack: Vec<Container>,
+    state: State,
+}
+
+impl<'a> JsonReader<SliceSource<'a>> {
+    pub fn new(s: &'a str) -> Self {
+        Self::from_bytes(s.as_bytes())
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        Self::with_source(SliceSource::new(bytes))
+    }
+}
+
+impl<R: io::Read> JsonReader<IoSource<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::with_source(IoSource::new(reader))
+    }
+}
+
+impl<S: Source> JsonReader<S> {
+    pub(crate) fn with_source(source: S) -> Self {
+        JsonReader {
+            lexer: Lexer::new(source),
+            stack: vec![],
+            state: State::Value,
+        }
+    }
+
+    /// parse with the given extensions instead of strict json
+    ///
+    /// ```
+    /// use dynamic_json::{Event, JsonReader, ParseOptions};
+    /// let options = ParseOptions {
+    ///     allow_unquoted_keys: true,
+    ///     ..Default::default()
+    /// };
+    /// let mut reader = JsonReader::new("{ key: 1 }").with_options(&options);
+    /// reader.next_event().unwrap();
+    /// assert_eq!(reader.next_event().unwrap(), Some(Event::Key("key")));
+    /// ```
+    pub fn with_options(mut self, options: &ParseOptions) -> Self {
+        self.lexer.options = options.clone();
+        self
+    }
+
+    /// number of objects and arrays that are currently open
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// whether the root value has been read completely
+    pub fn is_complete(&self) -> bool {
+        matches!(self.state, State::End | State::Done)
+    }
+
+    /// the next event, or `None` once the root value is complete
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>, JsonError> {
+        let emit = match self.step()? {
+            Some(emit) => emit,
+            None => return Ok(None),
+        };
+        Ok(Some(match emit {
+            Emit::StartObject => Event::StartObject,
+            Emit::Key => Event::Key(self.lexer.string()),
+            Emit::EndObject => Event::EndObject,
+            Emit::StartArray => Event::StartArray,
+            Emit::EndArray => Event::EndArray,
+            Emit::Null => Event::Value(Scalar::Null),
+            Emit::Bool(b) => Event::Value(Scalar::Bool(b)),
+            Emit::Number(num) => Event::Value(Scalar::Number(num)),
+            Emit::String => Event::Value(Scalar::String(self.lexer.string())),
+        }))
+    }
+
+    pub(crate) fn keep_comments(&mut self) {
+        self.lexer.keep_comments();
+    }
+
+    /// comments skipped since the last call, they precede the token of the
+    /// last event
+    pub(crate) fn take_comments(&mut self) -> Vec<Comment> {
+        self.lexer.take_comments()
+    }
+
+    pub(crate) fn error(&self, kind: ErrorKind) -> JsonError {
+        self.lexer.error(kind)
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        self.lexer.source_mut()
+    }
+
+    fn step(&mut self) -> Result<Option<Emit>, JsonError> {
+        let trailing_commas = self.lexer.options.allow_trailing_commas;
+        let single_quotes = self.lexer.options.allow_single_quotes;
+        let unquoted_keys = self.lexer.options.allow_unquoted_keys;
+        loop {
+            match self.state {
+                State::Done => return Ok(None),
+                State::End if self.lexer.options.allow_trailing_content => {
+                    self.state = State::Done;
+                    return Ok(None);
+                }
+                _ => (),
+            }
+            self.lexer.source_mut().mark();
+            let next = match self.lexer.resumes() {
+                // a string or number that ran out of input in the last chunk
+                Some(first) => Some(first),
+                None => {
+                    self.lexer.skip_spaces()?;
+                    self.lexer.peek()?
+                }
+            };
+            match (self.state, next) {
+                (State::End, None) => {
+                    self.state = State::Done;
+                    return Ok(None);
+                }
+                (State::End, Some(_)) => {
+                    return Err(self.lexer.error(ErrorKind::TrailingCharacters));
+                }
+                (State::ObjectFirst, Some(b'}'))
+                | (State::AfterValue, Some(b'}'))
+                | (State::ObjectKey, Some(b'}'))
+                    if self.stack.last() == Some(&Container::Object)
+                        && (self.state != State::ObjectKey || trailing_commas) =>
+                {
+                    self.lexer.bump();
+                    self.end_container();
+                    return Ok(Some(Emit::EndObject));
+                }
+                (State::ArrayFirst, Some(b']'))
+                | (State::AfterValue, Some(b']'))
+                | (State::ArrayNext, Some(b']'))
+                    if self.stack.last() == Some(&Container::Array)
+                        && (self.state != State::ArrayNext || trailing_commas) =>
+                {
+                    self.lexer.bump();
+                    self.end_container();
+                    return Ok(Some(Emit::EndArray));
+                }
+                (State::ObjectFirst | State::ObjectKey, Some(b'"')) => {
+                    self.lexer.parse_string()?;
+                    self.state = State::Colon;
+                    return Ok(Some(Emit::Key));
+                }
+                (State::ObjectFirst | State::ObjectKey, Some(b'\'')) if single_quotes => {
+                    self.lexer.parse_string()?;
+                    self.state = State::Colon;
+                    return Ok(Some(Emit::Key));
+                }
+                (State::ObjectFirst | State::ObjectKey, Some(b))
+                    if unquoted_keys && self.lexer.starts_identifier(b) =>
+                {
+                    self.lexer.parse_identifier()?;
+                    self.state = State::Colon;
+                    return Ok(Some(Emit::Key));
+                }
+                (State::Colon, Some(b':')) => {
+                    self.lexer.bump();
+                    self.state = State::Value;
+                }
+                (State::AfterValue, Some(b',')) => {
+                    self.lexer.bump();
+                    self.state = match self.stack.last() {
+                        Some(Container::Object) => State::ObjectKey,
+                        _ => State::ArrayNext,
+                    };
+                }
+                (State::Value | State::ArrayFirst | State::ArrayNext, Some(b)) => {
+                    return self.value(b).map(Some)
+                }
+                _ => return Err(self.lexer.unexpected()),
+            }
+        }
+    }
+
+    fn value(&mut self, first: u8) -> Result<Emit, JsonError> {
+        let emit = match first {
+            b'{' => {
+                self.lexer.bump();
+                self.stack.push(Container::Object);
+                self.state = State::ObjectFirst;
+                return Ok(Emit::StartObject);
+            }
+            b'[' => {
+                self.lexer.bump();
+                self.stack.push(Container::Array);
+                self.state = State::ArrayFirst;
+                return Ok(Emit::StartArray);
+            }
+            _ => match self.lexer.parse_scalar(first)? {
+                Token::Null => Emit::Null,
+                Token::Bool(b) => Emit::Bool(b),
+                Token::Number(num) => Emit::Number(num),
+                Token::String => Emit::String,
+            },
+        };
+        self.end_value();
+        Ok(emit)
+    }
+
+    fn end_container(&mut self) {
+        self.stack.pop();
+        self.end_value();
+    }
+
+    fn end_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::End
+        } else {
+            State::AfterValue
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Event, JsonReader, Scalar};
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn read_events() {
+        let mut reader = JsonReader::new(r#" [ {"k": null, "b": true}, [], {}, -1.5, "s\n" ] "#);
+        let mut events = vec![];
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(format!("{:?}", event));
+        }
+        let espect = [
+            Event::StartArray,
+            Event::StartObject,
+            Event::Key("k"),
+            Event::Value(Scalar::Null),
+            Event::Key("b"),
+            Event::Value(Scalar::Bool(true)),
+            Event::EndObject,
+            Event::StartArray,
+            Event::EndArray,
+            Event::StartObject,
+            Event::EndObject,
+            Event::Value(Scalar::Number(-1.5)),
+            Event::Value(Scalar::String("s\n")),
+            Event::EndArray,
+        ]
+        .iter()
+        .map(|e| format!("{:?}", e))
+        .collect::<Vec<_>>();
+        assert_eq!(events, espect);
+        assert_eq!(reader.depth(), 0);
+    }
+
+    #[test]
+    fn reject_bad_structure() {
+        let cases = [
+            ("[1 2]", ErrorKind::UnexpectedChar('2')),
+            ("[1,]", ErrorKind::UnexpectedChar(']')),
+            (r#"{"a" 1}"#, ErrorKind::UnexpectedChar('1')),
+            (r#"{"a": 1,}"#, ErrorKind::UnexpectedChar('}')),
+            (r#"{"a": 1]"#, ErrorKind::UnexpectedChar(']')),
+            ("{1: 2}", ErrorKind::UnexpectedChar('1')),
+            ("[1, [2]", ErrorKind::UnexpectedEof),
+        ];
+        for (input, kind) in cases {
+            let mut reader = JsonReader::new(input);
+            let err = loop {
+                match reader.next_event() {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => panic!("{} should be rejected", input),
+                    Err(err) => break err,
+                }
+            };
+            assert_eq!(err.kind(), Some(&kind), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,518 @@
+//! I-Regexp (RFC 9485), the interoperable regular expressions used by the
+//! `match` and `search` functions of JSONPath
+//!
+//! patterns compile to a small NFA that is simulated one character at a
+//! time, so matching is linear in the input whatever the pattern
+
+/// unicode categories of `\p{..}`, approximated with the predicates of `char`
+#[derive(Debug, Clone, Copy)]
+enum Category {
+    Letter,
+    Upper,
+    Lower,
+    Number,
+    Digit,
+    Space,
+    Control,
+}
+
+impl Category {
+    fn from_name(name: &str) -> Option<Category> {
+        Some(match name {
+            "L" => Category::Letter,
+            "Lu" => Category::Upper,
+            "Ll" => Category::Lower,
+            "N" => Category::Number,
+            "Nd" => Category::Digit,
+            "Z" | "Zs" => Category::Space,
+            "Cc" => Category::Control,
+            _ => return None,
+        })
+    }
+
+    fn contains(self, c: char) -> bool {
+        match self {
+            Category::Letter => c.is_alphabetic(),
+            Category::Upper => c.is_uppercase(),
+            Category::Lower => c.is_lowercase(),
+            Category::Number => c.is_numeric(),
+            Category::Digit => c.is_ascii_digit(),
+            Category::Space => c.is_whitespace() && !c.is_control(),
+            Category::Control => c.is_control(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Range(char, char),
+    Category(Category, bool),
+}
+
+/// the characters one step of the pattern accepts
+#[derive(Debug, Clone)]
+struct Set {
+    negated: bool,
+    items: Vec<Item>,
+}
+
+impl Set {
+    fn char(c: char) -> Set {
+        Set {
+            negated: false,
+            items: vec![Item::Range(c, c)],
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match *item {
+            Item::Range(from, to) => from <= c && c <= to,
+            Item::Category(category, negated) => category.contains(c) != negated,
+        });
+        found != self.negated
+    }
+}
+
+enum Node {
+    Set(Set),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, u32, Option<u32>),
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(Set),
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+/// larger programs are rejected, counted repetitions are expanded
+const MAX_INSTS: usize = 10_000;
+
+/// bound on the steps taken by `compile`, which also covers repetitions
+/// of groups that compile to nothing
+const MAX_WORK: usize = 100_000;
+
+/// larger bounds of `{m,n}` are rejected
+const MAX_REPEAT: u32 = 1_000;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    prog: Vec<Inst>,
+}
+
+impl Regex {
+    /// `None` if the pattern is not a valid I-Regexp
+    pub fn new(pattern: &str) -> Option<Regex> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos != parser.chars.len() {
+            return None;
+        }
+        let mut prog = vec![];
+        compile(&node, &mut prog, &mut 0)?;
+        prog.push(Inst::Match);
+        Some(Regex { prog })
+    }
+
+    /// whether the whole of `s` matches
+    pub fn is_match(&self, s: &str) -> bool {
+        self.run(s, true)
+    }
+
+    /// whether some substring of `s` matches
+    pub fn find(&self, s: &str) -> bool {
+        self.run(s, false)
+    }
+
+    fn run(&self, s: &str, anchored: bool) -> bool {
+        let mut current = Threads::new(self.prog.len());
+        let mut next = Threads::new(self.prog.len());
+        current.add(&self.prog, 0);
+        let mut chars = s.chars();
+        loop {
+            if current
+                .list
+                .iter()
+                .any(|pc| matches!(self.prog[*pc], Inst::Match))
+                && !anchored
+            {
+                return true;
+            }
+            let c = match chars.next() {
+                Some(c) => c,
+                None => break,
+            };
+            next.clear();
+            for &pc in &current.list {
+                if let Inst::Char(set) = &self.prog[pc] {
+                    if set.contains(c) {
+                        next.add(&self.prog, pc + 1);
+                    }
+                }
+            }
+            if !anchored {
+                next.add(&self.prog, 0);
+            }
+            std::mem::swap(&mut current, &mut next);
+            if current.list.is_empty() {
+                return false;
+            }
+        }
+        current
+            .list
+            .iter()
+            .any(|pc| matches!(self.prog[*pc], Inst::Match))
+    }
+}
+
+/// the states the NFA is in, each at most once
+struct Threads {
+    list: Vec<usize>,
+    /// the generation in which a state was last added
+    seen: Vec<usize>,
+    generation: usize,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: vec![],
+            seen: vec![0; len],
+            generation: 1,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+
+    /// add a state and everything reachable from it without input
+    fn add(&mut self, prog: &[Inst], pc: usize) {
+        if self.seen[pc] == self.generation {
+            return;
+        }
+        self.seen[pc] = self.generation;
+        match prog[pc] {
+            Inst::Jmp(to) => self.add(prog, to),
+            Inst::Split(a, b) => {
+                self.add(prog, a);
+                self.add(prog, b);
+            }
+            _ => self.list.push(pc),
+        }
+    }
+}
+
+/// whether a node compiles to no instructions at all
+fn is_empty(node: &Node) -> bool {
+    match node {
+        Node::Set(_) | Node::Alt(_) => false,
+        Node::Concat(nodes) => nodes.iter().all(is_empty),
+        Node::Repeat(node, _, max) => *max == Some(0) || is_empty(node),
+    }
+}
+
+fn compile(node: &Node, prog: &mut Vec<Inst>, work: &mut usize) -> Option<()> {
+    *work += 1;
+    if prog.len() > MAX_INSTS || *work > MAX_WORK {
+        return None;
+    }
+    match node {
+        Node::Set(set) => prog.push(Inst::Char(set.clone())),
+        Node::Concat(nodes) => {
+            for node in nodes {
+                compile(node, prog, work)?;
+            }
+        }
+        Node::Alt(nodes) => {
+            let mut jumps = vec![];
+            for (i, node) in nodes.iter().enumerate() {
+                if i + 1 == nodes.len() {
+                    compile(node, prog, work)?;
+                } else {
+                    let split = prog.len();
+                    prog.push(Inst::Split(split + 1, 0));
+                    compile(node, prog, work)?;
+                    jumps.push(prog.len());
+                    prog.push(Inst::Jmp(0));
+                    let next = prog.len();
+                    prog[split] = Inst::Split(split + 1, next);
+                }
+            }
+            let end = prog.len();
+            for jump in jumps {
+                prog[jump] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat(_, _, _) if is_empty(node) => {}
+        Node::Repeat(node, min, max) => {
+            for _ in 0..*min {
+                compile(node, prog, work)?;
+            }
+            match max {
+                None => {
+                    let split = prog.len();
+                    prog.push(Inst::Split(split + 1, 0));
+                    compile(node, prog, work)?;
+                    prog.push(Inst::Jmp(split));
+                    let end = prog.len();
+                    prog[split] = Inst::Split(split + 1, end);
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(prog.len());
+                        prog.push(Inst::Split(0, 0));
+                        compile(node, prog, work)?;
+                    }
+                    let end = prog.len();
+                    for split in splits {
+                        prog[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+    Some(())
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn alternation(&mut self) -> Option<Node> {
+        let mut branches = vec![self.branch()?];
+        while self.eat('|') {
+            branches.push(self.branch()?);
+        }
+        Some(match branches.len() {
+            1 => branches.pop().unwrap(),
+            _ => Node::Alt(branches),
+        })
+    }
+
+    fn branch(&mut self) -> Option<Node> {
+        let mut pieces = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            pieces.push(self.quantifier(atom)?);
+        }
+        Some(Node::Concat(pieces))
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        Some(match self.next()? {
+            '(' => {
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    return None;
+                }
+                node
+            }
+            '.' => Node::Set(Set {
+                negated: true,
+                items: vec![Item::Range('\n', '\n'), Item::Range('\r', '\r')],
+            }),
+            '[' => Node::Set(self.class()?),
+            '\\' => Node::Set(self.escape()?),
+            '*' | '+' | '?' | '{' | '}' | ']' => return None,
+            c => Node::Set(Set::char(c)),
+        })
+    }
+
+    fn quantifier(&mut self, atom: Node) -> Option<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = if self.eat(',') {
+                    match self.peek() {
+                        Some('}') => None,
+                        _ => Some(self.number()?),
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') || max.is_some_and(|max| max < min) {
+                    return None;
+                }
+                (min, max)
+            }
+            _ => return Some(atom),
+        };
+        self.pos += 1;
+        Some(Node::Repeat(Box::new(atom), min, max))
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok().filter(|n| *n <= MAX_REPEAT)
+    }
+
+    /// `\` has been read
+    fn escape(&mut self) -> Option<Set> {
+        let c = self.next()?;
+        Some(match c {
+            'n' => Set::char('\n'),
+            'r' => Set::char('\r'),
+            't' => Set::char('\t'),
+            'p' | 'P' => {
+                if !self.eat('{') {
+                    return None;
+                }
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != '}') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                if !self.eat('}') {
+                    return None;
+                }
+                Set {
+                    negated: false,
+                    items: vec![Item::Category(Category::from_name(&name)?, c == 'P')],
+                }
+            }
+            '(' | ')' | '*' | '+' | '-' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|' | '}' => {
+                Set::char(c)
+            }
+            _ => return None,
+        })
+    }
+
+    /// `[` has been read
+    fn class(&mut self) -> Option<Set> {
+        let negated = self.eat('^');
+        let mut items = vec![];
+        let mut first = true;
+        loop {
+            let c = self.next()?;
+            let from = match c {
+                ']' if !first => break,
+                '[' => return None,
+                '\\' => {
+                    let escaped = self.escape()?;
+                    match escaped.items.as_slice() {
+                        [Item::Range(c, _)] => *c,
+                        _ => {
+                            items.extend(escaped.items);
+                            first = false;
+                            continue;
+                        }
+                    }
+                }
+                c => c,
+            };
+            first = false;
+            let to = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                match self.next()? {
+                    '\\' => match self.escape()?.items.as_slice() {
+                        [Item::Range(c, _)] => *c,
+                        _ => return None,
+                    },
+                    '[' | ']' => return None,
+                    c => c,
+                }
+            } else {
+                from
+            };
+            if to < from {
+                return None;
+            }
+            items.push(Item::Range(from, to));
+        }
+        Some(Set { negated, items })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Regex;
+
+    #[test]
+    fn i_regexp() {
+        let cases = [
+            ("a.c", "abc", true, true),
+            ("a.c", "a\nc", false, false),
+            ("ab*c", "xabbbcx", false, true),
+            ("(ab|cd)+", "abcdab", true, true),
+            ("[a-c]{2,3}", "abca", false, true),
+            ("[^0-9]+", "abc", true, true),
+            ("[^0-9]+", "123", false, false),
+            ("colou?r", "color", true, true),
+            ("x{2}", "xxx", false, true),
+            ("\\.\\*", ".*", true, true),
+            ("\\p{Lu}\\p{Ll}+", "Élan", true, true),
+            ("[a\\-z]", "-", true, true),
+            ("", "", true, true),
+            (
+                "(a*)*b",
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac",
+                false,
+                false,
+            ),
+        ];
+        for (pattern, input, full, search) in cases {
+            let regex = Regex::new(pattern).unwrap();
+            assert_eq!(regex.is_match(input), full, "{} {}", pattern, input);
+            assert_eq!(regex.find(input), search, "{} {}", pattern, input);
+        }
+        for pattern in ["(", "a)", "*", "[b-a]", "a{2,1}", "\\d", "\\p{Xx}", "[abc"] {
+            assert!(Regex::new(pattern).is_none(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn repetition_limits() {
+        let regex = Regex::new("((){1000}){1000}").unwrap();
+        assert!(regex.is_match(""));
+        assert!(Regex::new("a{0}").unwrap().is_match(""));
+        for pattern in [
+            "((){65535}){65535}",
+            "a{4294967295}",
+            "a{1001}",
+            "((|){1000}){1000}",
+            "((a{0,1000}){1000}){1000}",
+        ] {
+            assert!(Regex::new(pattern).is_none(), "{}", pattern);
+        }
+    }
+}
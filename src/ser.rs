@@ -0,0 +1,158 @@
+use crate::lexer::is_identifier;
+use crate::JsonType;
+
+/// controls the output of [`JsonType::serialize_with`]
+///
+/// ```
+/// use dynamic_json::{json_obj, JsonObject, JsonType, SerializeOptions};
+/// let json: JsonType = json_obj! { "key" => f64::INFINITY }.into();
+/// let options = SerializeOptions {
+///     indent: 2,
+///     json5: true,
+/// };
+/// assert_eq!(json.serialize_with(&options), "{\n  key: Infinity\n}");
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SerializeOptions {
+    /// spaces per nesting level, 0 writes everything on a single line
+    pub indent: u32,
+    /// write JSON5: keys that are identifiers without quotes, `NaN` and `Infinity`
+    /// as literals instead of `null`
+    pub json5: bool,
+}
+
+/// serialize `json` as if it was nested `acc_indent / indent` levels deep
+pub fn serialize(json: &JsonType, indent: u32, acc_indent: u32) -> String {
+    let options = SerializeOptions {
+        indent,
+        ..Default::default()
+    };
+    serialize_with(json, &options, acc_indent)
+}
+
+pub(crate) fn serialize_with(
+    json: &JsonType,
+    options: &SerializeOptions,
+    acc_indent: u32,
+) -> String {
+    let indent = options.indent;
+    let wrap = if indent > 0 {
+        " ".repeat(acc_indent as usize)
+    } else {
+        "".to_string()
+    };
+    let wrap_start = if indent > 0 { "\n" } else { "" };
+    let wrap_end = if indent > 0 {
+        "\n".to_string() + &" ".repeat((acc_indent - indent) as usize)
+    } else {
+        "".to_string()
+    };
+    let gap = if indent > 0 { " " } else { "" };
+    let sep = format!(",{}", wrap_start);
+    match json {
+        JsonType::Null => "null".to_string(),
+        JsonType::Number(num) => serialize_number(*num, options.json5),
+        JsonType::String(s) => serialize_string(s),
+        JsonType::Bool(boolean) => boolean.to_string(),
+        JsonType::Object(obj) => {
+            let mut items: Vec<String> = vec![];
+            for (key, value) in obj {
+                items.push(format!(
+                    "{}{}:{}{}",
+                    wrap,
+                    serialize_key(key, options.json5),
+                    gap,
+                    serialize_with(value, options, acc_indent + indent),
+                ));
+            }
+
+            let s = format!("{{{}{}{}}}", wrap_start, items.join(&sep), wrap_end);
+            s
+        }
+        JsonType::Array(arr) => {
+            let mut items = vec![];
+            for item in arr {
+                items.push(format!(
+                    "{}{}",
+                    wrap,
+                    serialize_with(item, options, acc_indent + indent)
+                ));
+            }
+            format!("[{}{}{}]", wrap_start, items.join(&sep), wrap_end)
+        }
+    }
+}
+
+/// quote a string, escaping the characters json does not allow to appear raw
+pub(crate) fn serialize_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// object keys that are identifiers are written without quotes in json5
+pub(crate) fn serialize_key(key: &str, json5: bool) -> String {
+    if json5 && is_identifier(key) {
+        key.to_string()
+    } else {
+        serialize_string(key)
+    }
+}
+
+/// format a number so that parsing the output gives back the same f64,
+/// json has no representation for NaN and infinity so they become `null`
+pub(crate) fn serialize_number(num: f64, json5: bool) -> String {
+    if json5 && num.is_nan() {
+        return "NaN".to_string();
+    }
+    if json5 && num.is_infinite() {
+        return if num > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if !num.is_finite() {
+        return "null".to_string();
+    }
+    let abs = num.abs();
+    if abs != 0.0 && !(1e-7..1e21).contains(&abs) {
+        format!("{:e}", num)
+    } else {
+        num.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SerializeOptions;
+    use crate::{json_arr, parse_json5, JsonType};
+
+    #[test]
+    fn serialize_json5() {
+        let json = json_arr![f64::NAN, f64::NEG_INFINITY, 1.5];
+        let options = SerializeOptions {
+            json5: true,
+            ..Default::default()
+        };
+        assert_eq!(json.serialize_with(&options), "[NaN,-Infinity,1.5]");
+        assert_eq!(json.serialize(), "[null,null,1.5]");
+
+        let json = parse_json5(r#"{ "a_$1": 1, "2b": 2, "": 3, "ключ": 4, "a-b": 5 }"#).unwrap();
+        let out = json.serialize_with(&options);
+        for key in ["a_$1:1", "\"2b\":2", "\"\":3", "ключ:4", "\"a-b\":5"] {
+            assert!(out.contains(key), "{} in {}", key, out);
+        }
+        assert_eq!(parse_json5(&out).unwrap(), json);
+    }
+}
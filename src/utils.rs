@@ -1,76 +1,78 @@
-pub fn is_valid_ending_quote(chars: &Vec<char>, idx: usize) -> bool {
-    if chars[idx] == '"' {
-        if idx == 0 {
-            return false;
-        }
-        let mut is_escaped = false;
-        let mut i = idx - 1;
-        while chars[i] == '\\' {
-            is_escaped = !is_escaped;
-            if i == 0 {
-                break;
-            }
-            i -= 1;
-        }
-        return !is_escaped;
-    } else {
-        return false;
-    }
-}
-
-pub fn match_literal(chars: &Vec<char>, idx: usize, literal: &str) -> bool {
-    if idx <= chars.len() - literal.len() {
-        for (i, c) in literal.chars().enumerate() {
-            if chars[idx + i] != c {
-                return false;
-            }
-            return true;
-        }
-    }
-    return false;
-}
+use crate::error::{ErrorKind, JsonError};
 
-pub fn skip_spaces(chars: &Vec<char>, idx: &mut usize) {
-    skip_chars(chars, idx, &[' ', '\r', '\n']);
+pub fn is_char_boundary(b: u8) -> bool {
+    b & 0xC0 != 0x80
 }
 
-pub fn skip_chars(chars: &Vec<char>, idx: &mut usize, to_skip: &[char]) {
-    while *idx < chars.len() && to_skip.contains(&chars[*idx]) {
-        *idx += 1;
+/// up to 50 bytes of input ending with the character at `idx`
+pub fn nearby_content(bytes: &[u8], idx: usize) -> String {
+    let mut end = bytes.len().min(idx + 1);
+    while end < bytes.len() && !is_char_boundary(bytes[end]) {
+        end += 1;
+    }
+    let mut start = end.saturating_sub(50);
+    while start < end && !is_char_boundary(bytes[start]) {
+        start += 1;
     }
+    String::from_utf8_lossy(&bytes[start..end]).into_owned()
 }
 
-pub fn skip_util_char(chars: &Vec<char>, idx: &mut usize, util_chars: &[char]) {
-    while *idx < chars.len() && !util_chars.contains(&chars[*idx]) {
-        *idx += 1;
+pub fn parse_error(bytes: &[u8], idx: usize, kind: ErrorKind) -> JsonError {
+    let idx = idx.min(bytes.len());
+    let mut line = 1;
+    let mut column = 1;
+    for b in &bytes[..idx] {
+        if *b == b'\n' {
+            line += 1;
+            column = 1;
+        } else if is_char_boundary(*b) {
+            column += 1;
+        }
+    }
+    JsonError::Syntax {
+        kind,
+        offset: idx,
+        line,
+        column,
+        snippet: nearby_content(bytes, idx),
     }
 }
 
-pub fn nearby_content(chars: &Vec<char>, idx: usize) -> String {
-    let start = if idx < 50 { 0 } else { idx - 50 };
-    chars[start..=idx].iter().collect::<String>()
+/// the first character of `bytes` if it starts with valid utf-8
+pub fn decode_char(bytes: &[u8]) -> Option<char> {
+    let bytes = &bytes[..bytes.len().min(4)];
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(err) => std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    valid.chars().next()
 }
 
-pub fn parse_error(chars: &Vec<char>, idx: usize) -> ! {
-    panic!("{}", nearby_content(chars, idx));
+/// error for a character that can not start or continue a json value
+pub fn unexpected_char(bytes: &[u8], idx: usize) -> JsonError {
+    match decode_char(&bytes[idx.min(bytes.len())..]) {
+        Some(c) => parse_error(bytes, idx, ErrorKind::UnexpectedChar(c)),
+        None => parse_error(bytes, idx, ErrorKind::InvalidUtf8),
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::is_valid_ending_quote;
+    use super::{parse_error, unexpected_char};
+    use crate::error::ErrorKind;
 
     #[test]
-    fn valid_ending_quote() {
-        let s = r#"aaaaa""#;
-        let r = is_valid_ending_quote(&s.chars().collect(), s.len() - 1);
-        assert_eq!(r, true);
-
-        let s = r#"aaaa\""#;
-        let r = is_valid_ending_quote(&s.chars().collect(), s.len() - 1);
-        assert_eq!(r, false);
+    fn error_position() {
+        let bytes = "{\n  \"é\": x".as_bytes();
+        let err = parse_error(bytes, 10, ErrorKind::UnexpectedChar('x'));
+        assert_eq!(err.offset(), Some(10));
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.column(), Some(8));
+        assert_eq!(err.snippet(), Some("{\n  \"é\": x"));
 
-        let s = r#"aaa\\""#;
-        let r = is_valid_ending_quote(&s.chars().collect(), s.len() - 1);
-        assert_eq!(r, true);
+        let err = unexpected_char("[1, é]".as_bytes(), 4);
+        assert_eq!(err.kind(), Some(&ErrorKind::UnexpectedChar('é')));
+        let err = unexpected_char(b"[1, \xff]", 4);
+        assert_eq!(err.kind(), Some(&ErrorKind::InvalidUtf8));
     }
-}
\ No newline at end of file
+}
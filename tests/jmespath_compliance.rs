@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::Path;
+
+use dynamic_json::{parse, JmesPath, JsonType, QueryError};
+
+/// the error kind of the compliance suite an error stands for
+fn kind(error: &QueryError) -> &str {
+    let message = match error {
+        QueryError::Syntax { message, .. } | QueryError::Runtime { message } => message,
+    };
+    let named = [
+        "unknown-function",
+        "invalid-arity",
+        "invalid-value",
+        "invalid-type",
+    ]
+    .into_iter()
+    .find(|kind| message.starts_with(&format!("{}:", kind)));
+    match (named, error) {
+        (Some(kind), _) => kind,
+        (None, QueryError::Syntax { .. }) => "syntax",
+        (None, QueryError::Runtime { .. }) => "runtime",
+    }
+}
+
+/// runs every case of the `.json` files in `dir`, which use the layout of the
+/// jmespath.test compliance suite, and returns how many there were
+fn run_suite(dir: &Path) -> usize {
+    let mut files: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => return 0,
+    };
+    files.sort();
+    let mut failures = vec![];
+    let mut count = 0;
+    for file in &files {
+        let name = file.file_name().unwrap().to_string_lossy();
+        let suite = parse(&fs::read_to_string(file).unwrap());
+        for group in suite.as_array().unwrap() {
+            let given = group.get("given").unwrap();
+            for case in group.get("cases").unwrap().as_array().unwrap() {
+                count += 1;
+                let expression = case.get("expression").unwrap().as_str().unwrap();
+                let outcome = JmesPath::compile(expression).and_then(|expr| expr.search(given));
+                let ok = match (case.get("result"), case.get("error"), &outcome) {
+                    (Ok(expected), _, Ok(result)) => result == expected,
+                    (_, Ok(JsonType::String(expected)), Err(error)) => kind(error) == expected,
+                    // the benchmark cases only have to run
+                    (Err(_), Err(_), Ok(_)) => case.get("bench").is_ok(),
+                    _ => false,
+                };
+                if !ok {
+                    failures.push(format!("{}: {} => {:?}", name, expression, outcome));
+                }
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases in {} failed:\n{}",
+        failures.len(),
+        count,
+        dir.display(),
+        failures.join("\n")
+    );
+    count
+}
+
+/// the files of the upstream suite, kept unmodified in tests/jmespath
+#[test]
+fn jmespath_compliance() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/jmespath");
+    if run_suite(Path::new(dir)) == 0 {
+        eprintln!("the upstream jmespath.test files are not in {}", dir);
+    }
+}
+
+/// cases generated with the python implementation, see tests/jmespath/README.md
+#[test]
+fn jmespath_generated() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/jmespath/generated");
+    assert!(run_suite(Path::new(dir)) > 0);
+}